@@ -0,0 +1,468 @@
+//! Accessibility audit of a rendered rmpc theme: every style's foreground is
+//! paired with its explicit or inherited background and checked against WCAG
+//! 2.x contrast levels and APCA lightness contrast.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+
+use crate::color;
+
+const WCAG_AA_TEXT: f32 = 4.5;
+const WCAG_AAA_TEXT: f32 = 7.0;
+/// WCAG 1.4.11 non-text contrast; there is no AAA level for UI components.
+const WCAG_NON_TEXT: f32 = 3.0;
+/// APCA Lc for readable content text and for non-text spot elements.
+const APCA_TEXT_MIN: f32 = 60.0;
+const APCA_NON_TEXT_MIN: f32 = 30.0;
+
+/// Style paths containing one of these draw borders, separators or bar
+/// graphics rather than text.
+const NON_TEXT_MARKERS: [&str; 4] = ["border", "separator", "progress_bar", "scrollbar"];
+
+#[derive(Debug, Error)]
+pub enum AuditError {
+    #[error("theme has no background_color")]
+    MissingBackground,
+    #[error("unterminated string starting at byte {0}")]
+    UnterminatedString(usize),
+    #[error("unbalanced '{0}' at byte {1}")]
+    Unbalanced(char, usize),
+    #[error("invalid color \"{value}\" for {style}")]
+    InvalidColor { style: String, value: String },
+}
+
+/// WCAG conformance level a theme is required to meet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AuditLevel {
+    #[default]
+    Aa,
+    Aaa,
+}
+
+impl std::str::FromStr for AuditLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "aa" => Ok(AuditLevel::Aa),
+            "aaa" => Ok(AuditLevel::Aaa),
+            other => Err(format!("unknown WCAG level '{other}' (expected aa or aaa)")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairKind {
+    Text,
+    /// Borders, separators and bar graphics (WCAG 1.4.11).
+    NonText,
+}
+
+/// One foreground/background combination the theme can draw.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    /// Path of the style in the theme, e.g. `tab_bar.inactive_style`.
+    pub style: String,
+    pub fg: [u8; 3],
+    pub bg: [u8; 3],
+    /// The style sets no `bg`, so it is drawn on the pane background.
+    pub bg_inherited: bool,
+    /// The style's modifiers include `Bold`.
+    pub bold: bool,
+    pub kind: PairKind,
+    pub contrast: f32,
+    pub apca_lc: f32,
+}
+
+impl AuditEntry {
+    fn new(style: String, fg: [u8; 3], bg: [u8; 3], bg_inherited: bool, bold: bool) -> Self {
+        let kind = if NON_TEXT_MARKERS.iter().any(|marker| style.contains(marker)) {
+            PairKind::NonText
+        } else {
+            PairKind::Text
+        };
+        Self {
+            contrast: color::calculate_contrast_ratio(
+                color::rgb8_to_lab(fg),
+                color::rgb8_to_lab(bg),
+            ),
+            apca_lc: color::apca_contrast(fg, bg),
+            style,
+            fg,
+            bg,
+            bg_inherited,
+            bold,
+            kind,
+        }
+    }
+
+    /// Minimum WCAG contrast ratio for this pair at `level`.
+    pub fn required_contrast(&self, level: AuditLevel) -> f32 {
+        match (self.kind, level) {
+            (PairKind::NonText, _) => WCAG_NON_TEXT,
+            (PairKind::Text, AuditLevel::Aa) => WCAG_AA_TEXT,
+            (PairKind::Text, AuditLevel::Aaa) => WCAG_AAA_TEXT,
+        }
+    }
+
+    pub fn passes(&self, level: AuditLevel) -> bool {
+        self.contrast >= self.required_contrast(level)
+    }
+
+    pub fn passes_apca(&self) -> bool {
+        let min = match self.kind {
+            PairKind::Text => APCA_TEXT_MIN,
+            PairKind::NonText => APCA_NON_TEXT_MIN,
+        };
+        self.apca_lc.abs() >= min
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Punct(char),
+    /// Numbers and other scalar literals; only their position matters.
+    Other,
+}
+
+fn tokenize(ron: &str) -> Result<Vec<(usize, Token)>, AuditError> {
+    let bytes = ron.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match b {
+            b if b.is_ascii_whitespace() => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            // `#![enable(...)]` extension attributes
+            b'#' => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'"' => {
+                let start = i;
+                let mut value = String::new();
+                let mut chars = ron[i + 1..].char_indices();
+                loop {
+                    match chars.next() {
+                        Some((offset, '"')) => {
+                            i += offset + 2;
+                            break;
+                        }
+                        Some((_, '\\')) => {
+                            if let Some((_, escaped)) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        Some((_, c)) => value.push(c),
+                        None => return Err(AuditError::UnterminatedString(start)),
+                    }
+                }
+                tokens.push((start, Token::Str(value)));
+            }
+            b'(' | b')' | b'[' | b']' | b'{' | b'}' | b':' | b',' => {
+                tokens.push((i, Token::Punct(b as char)));
+                i += 1;
+            }
+            b if b.is_ascii_alphabetic() || b == b'_' => {
+                let start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                tokens.push((start, Token::Ident(ron[start..i].to_string())));
+            }
+            _ => {
+                let start = i;
+                i += ron[i..].chars().next().map_or(1, char::len_utf8);
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !b"()[]{}:,\"".contains(&bytes[i])
+                {
+                    i += 1;
+                }
+                tokens.push((start, Token::Other));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// An open `(`, `[` or `{`.
+struct Frame {
+    close: char,
+    label: Option<String>,
+    index: usize,
+    fg: Option<[u8; 3]>,
+    bg: Option<[u8; 3]>,
+    bold: bool,
+}
+
+fn frame_path(frames: &[Frame]) -> String {
+    let mut path = String::new();
+    for label in frames.iter().filter_map(|frame| frame.label.as_deref()) {
+        if !path.is_empty() && !label.starts_with('[') {
+            path.push('.');
+        }
+        path.push_str(label);
+    }
+    path
+}
+
+fn parse_color(style: &str, value: &str) -> Result<[u8; 3], AuditError> {
+    color::hex_to_rgb(value).ok_or_else(|| AuditError::InvalidColor {
+        style: style.to_string(),
+        value: value.to_string(),
+    })
+}
+
+/// Styles in document order with their raw fg, optional bg and boldness, plus
+/// the top-level color settings.
+type ParsedStyles = (
+    Vec<(String, [u8; 3], Option<[u8; 3]>, bool)>,
+    HashMap<String, String>,
+);
+
+fn parse_styles(ron: &str) -> Result<ParsedStyles, AuditError> {
+    let tokens = tokenize(ron)?;
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut styles = Vec::new();
+    let mut settings = HashMap::new();
+    let mut pending_key: Option<String> = None;
+
+    let mut iter = tokens.iter().peekable();
+    while let Some((pos, token)) = iter.next() {
+        match token {
+            Token::Ident(name) => {
+                if let Some((_, Token::Punct(':'))) = iter.peek() {
+                    iter.next();
+                    pending_key = Some(name.clone());
+                }
+            }
+            Token::Str(value) => {
+                if let Some(key) = pending_key.take() {
+                    match (frames.len(), key.as_str()) {
+                        (1, _) => {
+                            settings.insert(key, value.clone());
+                        }
+                        (_, "fg" | "bg") => {
+                            let style = frame_path(&frames);
+                            let rgb = parse_color(&style, value)?;
+                            let frame = frames.last_mut().expect("depth checked above");
+                            if key == "fg" {
+                                frame.fg = Some(rgb);
+                            } else {
+                                frame.bg = Some(rgb);
+                            }
+                        }
+                        (_, "modifiers") => {
+                            let frame = frames.last_mut().expect("depth checked above");
+                            frame.bold = value.split('|').any(|m| m.trim() == "Bold");
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Token::Punct(open @ ('(' | '[' | '{')) => {
+                let label = pending_key.take().or_else(|| {
+                    frames
+                        .last()
+                        .filter(|parent| parent.close == ']')
+                        .map(|parent| format!("[{}]", parent.index))
+                });
+                frames.push(Frame {
+                    close: match open {
+                        '(' => ')',
+                        '[' => ']',
+                        _ => '}',
+                    },
+                    label,
+                    index: 0,
+                    fg: None,
+                    bg: None,
+                    bold: false,
+                });
+            }
+            Token::Punct(close @ (')' | ']' | '}')) => {
+                let path = frame_path(&frames);
+                let frame = frames
+                    .pop()
+                    .filter(|frame| frame.close == *close)
+                    .ok_or(AuditError::Unbalanced(*close, *pos))?;
+                if let Some(fg) = frame.fg {
+                    styles.push((path, fg, frame.bg, frame.bold));
+                }
+            }
+            Token::Punct(',') => {
+                pending_key = None;
+                if let Some(frame) = frames.last_mut() {
+                    frame.index += 1;
+                }
+            }
+            Token::Punct(_) | Token::Other => pending_key = None,
+        }
+    }
+    if let Some(frame) = frames.last() {
+        return Err(AuditError::Unbalanced(frame.close, ron.len()));
+    }
+    Ok((styles, settings))
+}
+
+/// Enumerate every fg/bg pair a rendered theme can draw. Styles without `bg`
+/// inherit `header_background_color` inside the header and `background_color`
+/// elsewhere; `text_color` is checked against both.
+pub fn audit_theme(ron: &str) -> Result<Vec<AuditEntry>, AuditError> {
+    let (styles, settings) = parse_styles(ron)?;
+    let setting = |key: &str| {
+        settings
+            .get(key)
+            .map(|value| parse_color(key, value))
+            .transpose()
+    };
+    let background = setting("background_color")?.ok_or(AuditError::MissingBackground)?;
+    let header_background = setting("header_background_color")?.unwrap_or(background);
+
+    let mut entries = Vec::new();
+    if let Some(text) = setting("text_color")? {
+        entries.push(AuditEntry::new(
+            "text_color".into(),
+            text,
+            background,
+            true,
+            false,
+        ));
+        if header_background != background {
+            entries.push(AuditEntry::new(
+                "text_color@header".into(),
+                text,
+                header_background,
+                true,
+                false,
+            ));
+        }
+    }
+    for (style, fg, bg, bold) in styles {
+        let inherited = if style.starts_with("header") {
+            header_background
+        } else {
+            background
+        };
+        entries.push(AuditEntry::new(
+            style,
+            fg,
+            bg.unwrap_or(inherited),
+            bg.is_none(),
+            bold,
+        ));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THEME: &str = r##"#![enable(implicit_some)]
+(
+    background_color: "#101010",
+    text_color: "#e0e0e0",
+    header_background_color: "#ffffff",
+    tab_bar: (
+        active_style: (fg: "#000000", bg: "#f0c000", modifiers: "Bold"),
+        // dim on purpose
+        inactive_style: (fg: "#303030"),
+    ),
+    borders_style: (fg: "#606060"),
+    header: (
+        rows: [
+            (left: [(kind: Text("["), style: (fg: "#777777"))]),
+        ],
+    ),
+)
+"##;
+
+    #[test]
+    fn enumerates_styles_with_inherited_backgrounds() {
+        let entries = audit_theme(THEME).expect("audit");
+        let find = |style: &str| {
+            entries
+                .iter()
+                .find(|entry| entry.style == style)
+                .unwrap_or_else(|| panic!("{style} missing"))
+        };
+        let names: Vec<&str> = entries.iter().map(|entry| entry.style.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "text_color",
+                "text_color@header",
+                "tab_bar.active_style",
+                "tab_bar.inactive_style",
+                "borders_style",
+                "header.rows[0].left[0].style",
+            ]
+        );
+
+        let active = find("tab_bar.active_style");
+        assert!(!active.bg_inherited && active.bg == [0xf0, 0xc0, 0x00]);
+        assert!(active.bold);
+        assert!(active.passes(AuditLevel::Aaa));
+
+        let inactive = find("tab_bar.inactive_style");
+        assert!(inactive.bg_inherited && inactive.bg == [0x10, 0x10, 0x10]);
+        assert!(!inactive.bold);
+        assert!(!inactive.passes(AuditLevel::Aa) && !inactive.passes_apca());
+
+        let border = find("borders_style");
+        assert_eq!(border.kind, PairKind::NonText);
+        assert!(border.passes(AuditLevel::Aaa));
+
+        let header = find("header.rows[0].left[0].style");
+        assert_eq!(header.bg, [0xff, 0xff, 0xff]);
+        assert!(!find("text_color@header").passes(AuditLevel::Aa));
+    }
+
+    #[test]
+    fn parses_every_style_of_a_rendered_theme() {
+        use crate::roles::{map_colors_to_roles, sample_palette, RoleOptions};
+
+        let (roles, _, _) = map_colors_to_roles(&sample_palette(), &RoleOptions::default(), false);
+        let entries = audit_theme(&crate::render::theme_ron(&roles, true)).expect("audit");
+        for style in [
+            "text_color",
+            "tab_bar.inactive_style",
+            "highlighted_item_style",
+            "level_styles.trace",
+            "scrollbar.thumb_style",
+            "header.rows[1].right[0].kind.separator_style",
+        ] {
+            assert!(
+                entries.iter().any(|entry| entry.style == style),
+                "{style} missing"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_themes() {
+        assert!(matches!(
+            audit_theme("(text_color: \"#ffffff\")"),
+            Err(AuditError::MissingBackground)
+        ));
+        assert!(matches!(
+            audit_theme("(background_color: \"#000000\", a: (fg: \"#12\"))"),
+            Err(AuditError::InvalidColor { .. })
+        ));
+        assert!(matches!(
+            audit_theme("(background_color: \"#000000\", a: (fg: \"#ffffff\"]"),
+            Err(AuditError::Unbalanced(']', _))
+        ));
+    }
+}
@@ -5,13 +5,33 @@
 //! - Color-tool by L. Jégou (CC BY 3.0): https://github.com/ljegou/Color-tool
 //! - CIE 15:2018 (Colorimetry, 4th Edition) for LAB/LUV
 //! - IEC 61966-2-1:1999 for sRGB gamma and XYZ transforms
+//! - Björn Ottosson, "A perceptual color space for image processing" (2020) for OKLab
+//! - Safdar et al., Optics Express 25(13), 2017 for Jzazbz
+//! - Li et al., "Comprehensive color solutions: CAM16, CAT16, and CAM16-UCS" (2017)
+//! - APCA-W3 0.0.98G-4g (Myndex) for APCA lightness contrast
+//! - Machado et al., "A physiologically-based model for simulation of color vision
+//!   deficiency" (IEEE TVCG 2009) for CVD simulation
+
+// Matrix coefficients below are quoted verbatim from the reference papers.
+#![allow(clippy::excessive_precision)]
+
+use std::sync::OnceLock;
+
+use rayon::prelude::*;
 
 const EPSILON: f32 = 1e-6;
-const XYZ_WHITE: [f32; 3] = [0.95047, 1.0, 1.08883]; // D65
+pub(crate) const XYZ_WHITE: [f32; 3] = [0.95047, 1.0, 1.08883]; // D65
+
+/// Linear sRGB to XYZ (D65).
+pub(crate) const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.4124564, 0.3575761, 0.1804375],
+    [0.2126729, 0.7151522, 0.0721750],
+    [0.0193339, 0.1191920, 0.9503041],
+];
 
 #[inline]
 fn clamp01(v: f32) -> f32 {
-    v.max(0.0).min(1.0)
+    v.clamp(0.0, 1.0)
 }
 
 #[inline]
@@ -49,14 +69,8 @@ pub fn linear_to_srgb8(rgb: [f32; 3]) -> [u8; 3] {
 }
 
 pub fn rgb_to_xyz(linear_rgb: [f32; 3]) -> [f32; 3] {
-    let r = linear_rgb[0];
-    let g = linear_rgb[1];
-    let b = linear_rgb[2];
-    [
-        0.4124564 * r + 0.3575761 * g + 0.1804375 * b,
-        0.2126729 * r + 0.7151522 * g + 0.0721750 * b,
-        0.0193339 * r + 0.1191920 * g + 0.9503041 * b,
-    ]
+    let [r, g, b] = linear_rgb;
+    SRGB_TO_XYZ.map(|m| m[0] * r + m[1] * g + m[2] * b)
 }
 
 pub fn xyz_to_rgb(xyz: [f32; 3]) -> [f32; 3] {
@@ -71,7 +85,7 @@ pub fn xyz_to_rgb(xyz: [f32; 3]) -> [f32; 3] {
 }
 
 #[inline]
-fn f_lab(t: f32) -> f32 {
+pub(crate) fn f_lab(t: f32) -> f32 {
     const DELTA: f32 = 6.0 / 29.0;
     if t > DELTA.powi(3) {
         t.cbrt()
@@ -91,7 +105,22 @@ fn f_lab_inv(t: f32) -> f32 {
 }
 
 pub fn rgb8_to_lab(rgb: [u8; 3]) -> [f32; 3] {
-    let linear = srgb8_to_linear(rgb);
+    linear_to_lab(srgb8_to_linear(rgb))
+}
+
+/// Lab from gamma-encoded sRGB floats. Values outside [0, 1] (wide-gamut sources
+/// converted to sRGB) are kept rather than clipped.
+pub fn srgb_to_lab(srgb: [f32; 3]) -> [f32; 3] {
+    linear_to_lab(srgb.map(srgb_to_linear_extended))
+}
+
+/// sRGB transfer function extended to negative values by odd symmetry.
+#[inline]
+pub fn srgb_to_linear_extended(c: f32) -> f32 {
+    c.signum() * srgb_to_linear(c.abs())
+}
+
+fn linear_to_lab(linear: [f32; 3]) -> [f32; 3] {
     let xyz = rgb_to_xyz(linear);
     let xr = xyz[0] / XYZ_WHITE[0];
     let yr = xyz[1] / XYZ_WHITE[1];
@@ -102,7 +131,7 @@ pub fn rgb8_to_lab(rgb: [u8; 3]) -> [f32; 3] {
     [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
 }
 
-pub fn lab_to_rgb8(lab: [f32; 3]) -> [u8; 3] {
+pub fn lab_to_xyz(lab: [f32; 3]) -> [f32; 3] {
     let l = lab[0];
     let a = lab[1];
     let b = lab[2];
@@ -112,13 +141,19 @@ pub fn lab_to_rgb8(lab: [f32; 3]) -> [u8; 3] {
     let xr = f_lab_inv(fx);
     let yr = f_lab_inv(fy);
     let zr = f_lab_inv(fz);
-    let xyz = [xr * XYZ_WHITE[0], yr * XYZ_WHITE[1], zr * XYZ_WHITE[2]];
-    let linear = xyz_to_rgb(xyz).map(clamp01);
+    [xr * XYZ_WHITE[0], yr * XYZ_WHITE[1], zr * XYZ_WHITE[2]]
+}
+
+pub fn lab_to_rgb8(lab: [f32; 3]) -> [u8; 3] {
+    let linear = xyz_to_rgb(lab_to_xyz(lab)).map(clamp01);
     linear_to_srgb8(linear)
 }
 
 pub fn rgb8_to_luv(rgb: [u8; 3]) -> [f32; 3] {
-    let linear = srgb8_to_linear(rgb);
+    linear_to_luv(srgb8_to_linear(rgb))
+}
+
+fn linear_to_luv(linear: [f32; 3]) -> [f32; 3] {
     let xyz = rgb_to_xyz(linear);
     let denom = xyz[0] + 15.0 * xyz[1] + 3.0 * xyz[2];
     let (u_prime, v_prime) = if denom.abs() < EPSILON {
@@ -143,10 +178,10 @@ pub fn rgb8_to_luv(rgb: [u8; 3]) -> [f32; 3] {
     [l, u, v]
 }
 
-pub fn luv_to_rgb8(luv: [f32; 3]) -> [u8; 3] {
+pub fn luv_to_xyz(luv: [f32; 3]) -> [f32; 3] {
     let l = luv[0];
     if l.abs() < EPSILON {
-        return [0, 0, 0];
+        return [0.0, 0.0, 0.0];
     }
     let denom_ref = XYZ_WHITE[0] + 15.0 * XYZ_WHITE[1] + 3.0 * XYZ_WHITE[2];
     let u_ref = 4.0 * XYZ_WHITE[0] / denom_ref;
@@ -168,15 +203,20 @@ pub fn luv_to_rgb8(luv: [f32; 3]) -> [u8; 3] {
         let z = y * (12.0 - 3.0 * u_prime - 20.0 * v_prime) / denom;
         (x, z)
     };
-    let xyz = [x, y, z];
-    let linear = xyz_to_rgb(xyz).map(clamp01);
+    [x, y, z]
+}
+
+pub fn luv_to_rgb8(luv: [f32; 3]) -> [u8; 3] {
+    let linear = xyz_to_rgb(luv_to_xyz(luv)).map(clamp01);
     linear_to_srgb8(linear)
 }
 
 pub fn rgb8_to_yuv(rgb: [u8; 3]) -> [f32; 3] {
-    let r = rgb[0] as f32;
-    let g = rgb[1] as f32;
-    let b = rgb[2] as f32;
+    yuv_from_255([rgb[0] as f32, rgb[1] as f32, rgb[2] as f32])
+}
+
+fn yuv_from_255(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb;
     // BT.601 coefficients (matching CC BY 3.0 Color-tool reference)
     let y = r * 0.299 + g * 0.587 + b * 0.114;
     let u = r * -0.168736 + g * -0.331264 + b * 0.5 + 128.0;
@@ -196,9 +236,11 @@ pub fn yuv_to_rgb8(yuv: [f32; 3]) -> [u8; 3] {
 }
 
 pub fn rgb8_to_hsl(rgb: [u8; 3]) -> [f32; 3] {
-    let r = rgb[0] as f32 / 255.0;
-    let g = rgb[1] as f32 / 255.0;
-    let b = rgb[2] as f32 / 255.0;
+    srgb_to_hsl(rgb.map(|c| c as f32 / 255.0))
+}
+
+pub fn srgb_to_hsl(srgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = srgb;
     let max = r.max(g.max(b));
     let min = r.min(g.min(b));
     let delta = max - min;
@@ -245,9 +287,11 @@ pub fn hsl_to_rgb8(hsl: [f32; 3]) -> [u8; 3] {
 }
 
 pub fn rgb8_to_hsv(rgb: [u8; 3]) -> [f32; 3] {
-    let r = rgb[0] as f32 / 255.0;
-    let g = rgb[1] as f32 / 255.0;
-    let b = rgb[2] as f32 / 255.0;
+    srgb_to_hsv(rgb.map(|c| c as f32 / 255.0))
+}
+
+pub fn srgb_to_hsv(srgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = srgb;
     let max = r.max(g.max(b));
     let min = r.min(g.min(b));
     let delta = max - min;
@@ -316,6 +360,49 @@ pub fn calculate_contrast_ratio(lab1: [f32; 3], lab2: [f32; 3]) -> f32 {
     (lighter + 0.05) / (darker + 0.05)
 }
 
+/// APCA-W3 (0.0.98G-4g) lightness contrast Lc of `text` drawn on `background`.
+/// Positive for dark text on a light background, negative for light on dark;
+/// compare the magnitude against the APCA use-case thresholds.
+pub fn apca_contrast(text: [u8; 3], background: [u8; 3]) -> f32 {
+    const BLACK_THRESHOLD: f32 = 0.022;
+    const BLACK_CLAMP: f32 = 1.414;
+    const SCALE: f32 = 1.14;
+    const LOW_CLIP: f32 = 0.1;
+    const OFFSET: f32 = 0.027;
+
+    let screen_luminance = |rgb: [u8; 3]| {
+        let [r, g, b] = rgb.map(|c| (c as f32 / 255.0).powf(2.4));
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        if y < BLACK_THRESHOLD {
+            y + (BLACK_THRESHOLD - y).powf(BLACK_CLAMP)
+        } else {
+            y
+        }
+    };
+    let y_text = screen_luminance(text);
+    let y_bg = screen_luminance(background);
+    if (y_bg - y_text).abs() < 0.0005 {
+        return 0.0;
+    }
+
+    let lc = if y_bg > y_text {
+        let s = (y_bg.powf(0.56) - y_text.powf(0.57)) * SCALE;
+        if s < LOW_CLIP {
+            0.0
+        } else {
+            s - OFFSET
+        }
+    } else {
+        let s = (y_bg.powf(0.65) - y_text.powf(0.62)) * SCALE;
+        if s > -LOW_CLIP {
+            0.0
+        } else {
+            s + OFFSET
+        }
+    };
+    lc * 100.0
+}
+
 /// Calculate CIE76 Delta E color difference
 /// Returns perceptual distance where 0 = identical, >100 = very different
 pub fn delta_e_cie76(lab1: [f32; 3], lab2: [f32; 3]) -> f32 {
@@ -330,10 +417,29 @@ pub fn rgb_to_hex(rgb: [u8; 3]) -> String {
     format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2])
 }
 
+/// Parse a `#RRGGBB` or `RRGGBB` hex string into RGB
+pub fn hex_to_rgb(hex: &str) -> Option<[u8; 3]> {
+    let digits = hex.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn hex_round_trip() {
+        assert_eq!(hex_to_rgb("#1a2B3c"), Some([0x1a, 0x2b, 0x3c]));
+        assert_eq!(hex_to_rgb("ffffff"), Some([255, 255, 255]));
+        assert_eq!(hex_to_rgb(&rgb_to_hex([7, 128, 250])), Some([7, 128, 250]));
+        assert_eq!(hex_to_rgb("#fff"), None);
+        assert_eq!(hex_to_rgb("#gg0000"), None);
+    }
+
     fn assert_rgb_close(a: [u8; 3], b: [u8; 3], tol: u8) {
         for i in 0..3 {
             let diff = a[i].abs_diff(b[i]);
@@ -385,6 +491,115 @@ mod tests {
         assert_rgb_close(rgb, back, 2);
     }
 
+    #[test]
+    fn jzazbz_round_trip() {
+        let samples = [[255, 0, 0], [12, 200, 64], [240, 240, 240], [3, 3, 40]];
+        for rgb in samples {
+            let jab = rgb8_to_jzazbz(rgb);
+            let back = jzazbz_to_rgb8(jab);
+            assert_rgb_close(rgb, back, 2);
+        }
+    }
+
+    #[test]
+    fn cam16_ucs_round_trip() {
+        let samples = [[255, 0, 0], [12, 200, 64], [240, 240, 240], [18, 42, 200]];
+        for rgb in samples {
+            let jab = rgb8_to_cam16_ucs(rgb);
+            let back = cam16_ucs_to_rgb8(jab);
+            assert_rgb_close(rgb, back, 2);
+        }
+    }
+
+    #[test]
+    fn cam16_white_has_full_lightness() {
+        let jab = rgb8_to_cam16_ucs([255, 255, 255]);
+        // J = 100 maps to J' = 170 / 1.7 = 100 in the UCS lightness scale.
+        assert!((jab[0] - 100.0).abs() < 0.5, "J' = {}", jab[0]);
+        assert!(jab[1].hypot(jab[2]) < 2.5);
+    }
+
+    #[test]
+    fn every_cluster_space_round_trips() {
+        let spaces = [
+            ColorSpace::Cielab,
+            ColorSpace::Rgb,
+            ColorSpace::Hsl,
+            ColorSpace::Hsv,
+            ColorSpace::Yuv,
+            ColorSpace::Cieluv,
+            ColorSpace::Oklab,
+            ColorSpace::Oklch,
+            ColorSpace::LinearRgb,
+            ColorSpace::Jzazbz,
+            ColorSpace::Cam16Ucs,
+        ];
+        let samples = [
+            [200, 32, 240],
+            [128, 200, 32],
+            [10, 10, 10],
+            [250, 250, 245],
+        ];
+        for space in spaces {
+            assert_eq!(ColorSpace::parse(space.name()), Some(space));
+            for rgb in samples {
+                let back = space.decode(space.encode(rgb));
+                assert_rgb_close(rgb, back, 3);
+            }
+        }
+    }
+
+    #[test]
+    fn float_encoding_matches_rgb8_encoding() {
+        let rgb = [200, 32, 240];
+        let srgb = rgb.map(|c| c as f32 / 255.0);
+        for space in [
+            ColorSpace::Cielab,
+            ColorSpace::Hsl,
+            ColorSpace::Oklab,
+            ColorSpace::Yuv,
+        ] {
+            let a = space.encode(rgb);
+            let b = space.encode_srgb(srgb);
+            for i in 0..3 {
+                assert!((a[i] - b[i]).abs() < 1e-3, "{space:?} axis {i}");
+            }
+        }
+    }
+
+    #[test]
+    fn gamut_mapping_preserves_hue_over_clipping() {
+        // A saturated OKLab red pushed well outside sRGB.
+        let oklab = [0.6, 0.35, 0.2];
+        let mapped = linear_to_srgb8_mapped(oklab_to_linear(oklab));
+        let hue_in = oklab_to_oklch(oklab)[2];
+        let hue_out = oklab_to_oklch(rgb8_to_oklab(mapped))[2];
+        assert!(delta_hue_degrees(hue_in, hue_out) < 3.0);
+        let l_out = rgb8_to_oklab(mapped)[0];
+        assert!((l_out - 0.6).abs() < 0.02);
+    }
+
+    #[test]
+    fn hardcoded_inverse_matrices_match() {
+        let pairs = [
+            (JZ_XYZ_TO_LMS, JZ_LMS_TO_XYZ),
+            (JZ_LMS_TO_IAB, JZ_IAB_TO_LMS),
+            (CAM16_M, CAM16_M_INV),
+        ];
+        for (m, inv) in pairs {
+            for (i, basis) in [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+                .into_iter()
+                .enumerate()
+            {
+                let back = mat3_mul(&m, mat3_mul(&inv, basis));
+                for (j, value) in back.iter().enumerate() {
+                    let expected = if i == j { 1.0 } else { 0.0 };
+                    assert!((value - expected).abs() < 1e-5);
+                }
+            }
+        }
+    }
+
     #[test]
     fn known_lab_value_for_red() {
         let lab = rgb8_to_lab([255, 0, 0]);
@@ -392,9 +607,97 @@ mod tests {
         assert!((lab[1] - 80.09).abs() < 0.5);
         assert!((lab[2] - 67.20).abs() < 0.5);
     }
+
+    #[test]
+    fn apca_matches_reference_values() {
+        let white = [255, 255, 255];
+        let black = [0, 0, 0];
+        assert!((apca_contrast(black, white) - 106.04).abs() < 0.1);
+        assert!((apca_contrast(white, black) + 107.88).abs() < 0.1);
+        assert!((apca_contrast([0x88, 0x88, 0x88], white) - 63.06).abs() < 0.1);
+        assert_eq!(apca_contrast([10, 10, 10], [10, 10, 10]), 0.0);
+    }
+
+    #[test]
+    fn cvd_simulation_keeps_grays_and_merges_deutan_confusion_pair() {
+        for kind in CvdKind::ALL {
+            for v in [0u8, 64, 128, 200, 255] {
+                let lab = rgb8_to_lab([v, v, v]);
+                assert!(
+                    delta_e_cie76(simulate_cvd_lab(lab, kind), lab) < 1.0,
+                    "{kind:?} {v}"
+                );
+            }
+        }
+        let red = rgb8_to_lab([190, 80, 60]);
+        let green = rgb8_to_lab([100, 130, 50]);
+        assert!(delta_e_cie76(red, green) > 40.0);
+        let deutan = CvdKind::Deuteranopia;
+        let simulated = delta_e_cie76(
+            simulate_cvd_lab(red, deutan),
+            simulate_cvd_lab(green, deutan),
+        );
+        assert!(simulated < 5.0, "{simulated}");
+    }
+
+    #[test]
+    fn batch_lab_matches_scalar() {
+        let mut pixels = Vec::new();
+        for r in (0..=255u16).step_by(15) {
+            for g in (0..=255u16).step_by(17) {
+                for b in (0..=255u16).step_by(51) {
+                    pixels.push([r as u8, g as u8, b as u8]);
+                }
+            }
+        }
+        pixels.extend([[0, 0, 0], [255, 255, 255], [1, 1, 1], [3, 2, 1]]);
+        let batch = srgb8_slice_to_lab(&pixels);
+        for (px, lab) in pixels.iter().zip(batch.iter()) {
+            assert_eq!(*lab, rgb8_to_lab(*px), "{px:?}");
+        }
+
+        let floats: Vec<[f32; 3]> = pixels.iter().map(|p| p.map(|c| c as f32 / 255.0)).collect();
+        let float_batch = srgb_slice_to_lab(&floats);
+        for (px, lab) in floats.iter().zip(float_batch.iter()) {
+            assert_eq!(*lab, srgb_to_lab(*px));
+        }
+    }
+
+    #[test]
+    fn batch_encoding_matches_per_pixel_encoding() {
+        let pixels: Vec<[u8; 3]> = (0..5000u32)
+            .map(|i| [(i * 7) as u8, (i * 13 + 5) as u8, (i * 29 + 11) as u8])
+            .collect();
+        for name in [
+            "CIELAB",
+            "RGB",
+            "HSL",
+            "HSV",
+            "YUV",
+            "CIELUV",
+            "OKLAB",
+            "OKLCH",
+            "LINEAR-RGB",
+            "JZAZBZ",
+            "CAM16-UCS",
+        ] {
+            let space = ColorSpace::parse(name).unwrap();
+            let batch = space.encode_slice(&pixels);
+            for (px, point) in pixels.iter().zip(batch.iter()) {
+                assert_eq!(*point, space.encode(*px), "{name} {px:?}");
+            }
+        }
+        assert_eq!(
+            srgb8_slice_to_linear(&pixels[..10]),
+            pixels[..10]
+                .iter()
+                .map(|&p| srgb8_to_linear(p))
+                .collect::<Vec<_>>()
+        );
+    }
 }
-pub fn rgb8_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
-    let [r, g, b] = srgb8_to_linear(rgb);
+pub fn linear_to_oklab(linear: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = linear;
     let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
     let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
     let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
@@ -410,7 +713,7 @@ pub fn rgb8_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
     [l_star, a_star, b_star]
 }
 
-pub fn oklab_to_rgb8(oklab: [f32; 3]) -> [u8; 3] {
+pub fn oklab_to_linear(oklab: [f32; 3]) -> [f32; 3] {
     let l = oklab[0];
     let a = oklab[1];
     let b = oklab[2];
@@ -423,11 +726,19 @@ pub fn oklab_to_rgb8(oklab: [f32; 3]) -> [u8; 3] {
     let m = m_cbrt.powi(3);
     let s = s_cbrt.powi(3);
 
-    let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
-    let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
-    let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+    [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+    ]
+}
 
-    linear_to_srgb8([r, g, b])
+pub fn rgb8_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
+    linear_to_oklab(srgb8_to_linear(rgb))
+}
+
+pub fn oklab_to_rgb8(oklab: [f32; 3]) -> [u8; 3] {
+    linear_to_srgb8(oklab_to_linear(oklab))
 }
 
 pub fn oklab_to_oklch(oklab: [f32; 3]) -> [f32; 3] {
@@ -459,3 +770,565 @@ pub fn delta_hue_degrees(h1: f32, h2: f32) -> f32 {
     }
     delta
 }
+
+// Jzazbz constants (Safdar et al. 2017). Relative XYZ is scaled so the sRGB
+// white sits at 203 cd/m², the SDR reference white used by ITU-R BT.2408.
+const JZ_WHITE_NITS: f32 = 203.0;
+const JZ_B: f32 = 1.15;
+const JZ_G: f32 = 0.66;
+const JZ_C1: f32 = 3424.0 / 4096.0;
+const JZ_C2: f32 = 2413.0 / 128.0;
+const JZ_C3: f32 = 2392.0 / 128.0;
+const JZ_N: f32 = 2610.0 / 16384.0;
+const JZ_P: f32 = 1.7 * 2523.0 / 32.0;
+const JZ_D: f32 = -0.56;
+const JZ_D0: f32 = 1.629_549_9e-11;
+
+const JZ_XYZ_TO_LMS: [[f32; 3]; 3] = [
+    [0.41478972, 0.579999, 0.0146480],
+    [-0.2015100, 1.120649, 0.0531008],
+    [-0.0166008, 0.264800, 0.6684799],
+];
+const JZ_LMS_TO_XYZ: [[f32; 3]; 3] = [
+    [1.92422644, -1.00479231, 0.0376514040],
+    [0.350316762, 0.726481194, -0.0653844229],
+    [-0.0909828110, -0.312728291, 1.52276656],
+];
+const JZ_LMS_TO_IAB: [[f32; 3]; 3] = [
+    [0.5, 0.5, 0.0],
+    [3.524000, -4.066708, 0.542708],
+    [0.199076, 1.096799, -1.295875],
+];
+const JZ_IAB_TO_LMS: [[f32; 3]; 3] = [
+    [1.0, 0.138605043, 0.0580473162],
+    [1.0, -0.138605043, -0.0580473162],
+    [1.0, -0.0960192420, -0.811891896],
+];
+
+#[inline]
+fn mat3_mul(m: &[[f32; 3]; 3], v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[0][1] * v[1] + m[0][2] * v[2],
+        m[1][0] * v[0] + m[1][1] * v[1] + m[1][2] * v[2],
+        m[2][0] * v[0] + m[2][1] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+#[inline]
+fn pq_encode(x: f32) -> f32 {
+    let xn = (x.max(0.0) / 10000.0).powf(JZ_N);
+    ((JZ_C1 + JZ_C2 * xn) / (1.0 + JZ_C3 * xn)).powf(JZ_P)
+}
+
+#[inline]
+fn pq_decode(v: f32) -> f32 {
+    let vp = v.max(0.0).powf(1.0 / JZ_P);
+    let num = (vp - JZ_C1).max(0.0);
+    let den = JZ_C2 - JZ_C3 * vp;
+    if den < EPSILON {
+        return 10000.0;
+    }
+    10000.0 * (num / den).powf(1.0 / JZ_N)
+}
+
+pub fn xyz_to_jzazbz(xyz: [f32; 3]) -> [f32; 3] {
+    let [x, y, z] = xyz.map(|c| c * JZ_WHITE_NITS);
+    let xp = JZ_B * x - (JZ_B - 1.0) * z;
+    let yp = JZ_G * y - (JZ_G - 1.0) * x;
+    let lms = mat3_mul(&JZ_XYZ_TO_LMS, [xp, yp, z]).map(pq_encode);
+    let [iz, az, bz] = mat3_mul(&JZ_LMS_TO_IAB, lms);
+    let jz = ((1.0 + JZ_D) * iz) / (1.0 + JZ_D * iz) - JZ_D0;
+    [jz, az, bz]
+}
+
+pub fn jzazbz_to_xyz(jab: [f32; 3]) -> [f32; 3] {
+    let [jz, az, bz] = jab;
+    let jz0 = jz + JZ_D0;
+    let iz = jz0 / (1.0 + JZ_D - JZ_D * jz0);
+    let lms = mat3_mul(&JZ_IAB_TO_LMS, [iz, az, bz]).map(pq_decode);
+    let [xp, yp, z] = mat3_mul(&JZ_LMS_TO_XYZ, lms);
+    let x = (xp + (JZ_B - 1.0) * z) / JZ_B;
+    let y = (yp + (JZ_G - 1.0) * x) / JZ_G;
+    [x, y, z].map(|c| c / JZ_WHITE_NITS)
+}
+
+pub fn rgb8_to_jzazbz(rgb: [u8; 3]) -> [f32; 3] {
+    xyz_to_jzazbz(rgb_to_xyz(srgb8_to_linear(rgb)))
+}
+
+pub fn jzazbz_to_rgb8(jab: [f32; 3]) -> [u8; 3] {
+    linear_to_srgb8_mapped(xyz_to_rgb(jzazbz_to_xyz(jab)))
+}
+
+// CAM16 with the CSS Color 4 default viewing conditions: D65 white, adapting
+// luminance 64/π·0.2 cd/m², 20% grey background, average surround.
+const CAM16_M: [[f32; 3]; 3] = [
+    [0.401288, 0.650173, -0.051461],
+    [-0.250268, 1.204414, 0.045854],
+    [-0.002079, 0.048952, 0.953127],
+];
+const CAM16_M_INV: [[f32; 3]; 3] = [
+    [1.8620678, -1.0112547, 0.14918678],
+    [0.38752654, 0.62144744, -0.00897398],
+    [-0.01584150, -0.03412294, 1.0499644],
+];
+
+struct Cam16Viewing {
+    d_rgb: [f32; 3],
+    fl: f32,
+    n: f32,
+    z: f32,
+    nbb: f32,
+    c: f32,
+    nc: f32,
+    aw: f32,
+}
+
+fn cam16_viewing() -> &'static Cam16Viewing {
+    static VIEWING: std::sync::OnceLock<Cam16Viewing> = std::sync::OnceLock::new();
+    VIEWING.get_or_init(|| {
+        let white = XYZ_WHITE.map(|c| c * 100.0);
+        let la = 64.0 / std::f32::consts::PI * 0.2;
+        let yb = 20.0;
+        let (f, c, nc) = (1.0f32, 0.69f32, 1.0f32);
+
+        let rgb_w = mat3_mul(&CAM16_M, white);
+        let k = 1.0 / (5.0 * la + 1.0);
+        let k4 = k.powi(4);
+        let fl = 0.2 * k4 * (5.0 * la) + 0.1 * (1.0 - k4).powi(2) * (5.0 * la).cbrt();
+        let n = yb / white[1];
+        let z = 1.48 + n.sqrt();
+        let nbb = 0.725 * n.powf(-0.2);
+        let d = (f * (1.0 - (1.0 / 3.6) * ((-la - 42.0) / 92.0).exp())).clamp(0.0, 1.0);
+        let d_rgb = rgb_w.map(|w| d * white[1] / w + 1.0 - d);
+        let rgb_aw = [
+            cam16_adapt(rgb_w[0] * d_rgb[0], fl),
+            cam16_adapt(rgb_w[1] * d_rgb[1], fl),
+            cam16_adapt(rgb_w[2] * d_rgb[2], fl),
+        ];
+        let aw = (2.0 * rgb_aw[0] + rgb_aw[1] + 0.05 * rgb_aw[2]) * nbb;
+        Cam16Viewing {
+            d_rgb,
+            fl,
+            n,
+            z,
+            nbb,
+            c,
+            nc,
+            aw,
+        }
+    })
+}
+
+// Post-adaptation compression without the +0.1 offset; the offset cancels in
+// a/b and is dropped from the achromatic signal consistently on both sides.
+#[inline]
+fn cam16_adapt(component: f32, fl: f32) -> f32 {
+    let x = (fl * component.abs() / 100.0).powf(0.42);
+    component.signum() * 400.0 * x / (x + 27.13)
+}
+
+#[inline]
+fn cam16_unadapt(adapted: f32, fl: f32) -> f32 {
+    let a = adapted.abs().min(399.999);
+    adapted.signum() * 100.0 / fl * (27.13 * a / (400.0 - a)).powf(1.0 / 0.42)
+}
+
+pub fn xyz_to_cam16_ucs(xyz: [f32; 3]) -> [f32; 3] {
+    let vc = cam16_viewing();
+    let rgb = mat3_mul(&CAM16_M, xyz.map(|c| c * 100.0));
+    let ra = cam16_adapt(rgb[0] * vc.d_rgb[0], vc.fl);
+    let ga = cam16_adapt(rgb[1] * vc.d_rgb[1], vc.fl);
+    let ba = cam16_adapt(rgb[2] * vc.d_rgb[2], vc.fl);
+
+    let a = ra - 12.0 * ga / 11.0 + ba / 11.0;
+    let b = (ra + ga - 2.0 * ba) / 9.0;
+    let h = b.atan2(a);
+    let et = 0.25 * ((h + 2.0).cos() + 3.8);
+    let achromatic = (2.0 * ra + ga + 0.05 * ba) * vc.nbb;
+    let j = 100.0 * (achromatic / vc.aw).max(0.0).powf(vc.c * vc.z);
+    let denom = ra + ga + 1.05 * ba;
+    let t = if denom.abs() < EPSILON {
+        0.0
+    } else {
+        (50000.0 / 13.0 * vc.nc * vc.nbb * et * (a * a + b * b).sqrt()) / denom
+    };
+    let chroma = t.max(0.0).powf(0.9) * (j / 100.0).sqrt() * (1.64 - 0.29f32.powf(vc.n)).powf(0.73);
+    let m = chroma * vc.fl.powf(0.25);
+
+    let j_ucs = 1.7 * j / (1.0 + 0.007 * j);
+    let m_ucs = (1.0 + 0.0228 * m).ln() / 0.0228;
+    [j_ucs, m_ucs * h.cos(), m_ucs * h.sin()]
+}
+
+pub fn cam16_ucs_to_xyz(jab: [f32; 3]) -> [f32; 3] {
+    let vc = cam16_viewing();
+    let [j_ucs, a_ucs, b_ucs] = jab;
+    let j = j_ucs / (1.7 - 0.007 * j_ucs);
+    if j <= EPSILON {
+        return [0.0, 0.0, 0.0];
+    }
+    let m_ucs = (a_ucs * a_ucs + b_ucs * b_ucs).sqrt();
+    let m = ((0.0228 * m_ucs).exp() - 1.0) / 0.0228;
+    let h = b_ucs.atan2(a_ucs);
+
+    let chroma = m / vc.fl.powf(0.25);
+    let t =
+        (chroma / ((j / 100.0).sqrt() * (1.64 - 0.29f32.powf(vc.n)).powf(0.73))).powf(1.0 / 0.9);
+    let et = 0.25 * ((h + 2.0).cos() + 3.8);
+    let achromatic = vc.aw * (j / 100.0).powf(1.0 / (vc.c * vc.z));
+    let p2 = achromatic / vc.nbb;
+    let (sin_h, cos_h) = h.sin_cos();
+    let gamma = if t < EPSILON {
+        0.0
+    } else {
+        let p1 = 50000.0 / 13.0 * vc.nc * vc.nbb * et;
+        23.0 * p2 * t / (23.0 * p1 + 11.0 * t * cos_h + 108.0 * t * sin_h)
+    };
+    let a = gamma * cos_h;
+    let b = gamma * sin_h;
+
+    let ra = (460.0 * p2 + 451.0 * a + 288.0 * b) / 1403.0;
+    let ga = (460.0 * p2 - 891.0 * a - 261.0 * b) / 1403.0;
+    let ba = (460.0 * p2 - 220.0 * a - 6300.0 * b) / 1403.0;
+    let rgb = [
+        cam16_unadapt(ra, vc.fl) / vc.d_rgb[0],
+        cam16_unadapt(ga, vc.fl) / vc.d_rgb[1],
+        cam16_unadapt(ba, vc.fl) / vc.d_rgb[2],
+    ];
+    mat3_mul(&CAM16_M_INV, rgb).map(|c| c / 100.0)
+}
+
+pub fn rgb8_to_cam16_ucs(rgb: [u8; 3]) -> [f32; 3] {
+    xyz_to_cam16_ucs(rgb_to_xyz(srgb8_to_linear(rgb)))
+}
+
+pub fn cam16_ucs_to_rgb8(jab: [f32; 3]) -> [u8; 3] {
+    linear_to_srgb8_mapped(xyz_to_rgb(cam16_ucs_to_xyz(jab)))
+}
+
+#[inline]
+fn linear_in_gamut(linear: [f32; 3]) -> bool {
+    const GAMUT_EPS: f32 = 1e-4;
+    linear
+        .iter()
+        .all(|c| c.is_finite() && *c >= -GAMUT_EPS && *c <= 1.0 + GAMUT_EPS)
+}
+
+/// Map linear sRGB into gamut by reducing OKLCH chroma at constant lightness
+/// and hue, instead of clipping each channel independently.
+pub fn linear_to_srgb8_mapped(linear: [f32; 3]) -> [u8; 3] {
+    if linear_in_gamut(linear) {
+        return linear_to_srgb8(linear);
+    }
+    if linear.iter().any(|c| !c.is_finite()) {
+        return [0, 0, 0];
+    }
+    let [l, c, h] = oklab_to_oklch(linear_to_oklab(linear));
+    if l <= 0.0 {
+        return [0, 0, 0];
+    }
+    if l >= 1.0 {
+        return [255, 255, 255];
+    }
+    let mut lo = 0.0f32;
+    let mut hi = c;
+    for _ in 0..24 {
+        let mid = 0.5 * (lo + hi);
+        if linear_in_gamut(oklab_to_linear(oklch_to_oklab([l, mid, h]))) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    linear_to_srgb8(oklab_to_linear(oklch_to_oklab([l, lo, h])))
+}
+
+/// Dichromacies simulated by `simulate_cvd_lab`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CvdKind {
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+impl CvdKind {
+    pub const ALL: [CvdKind; 3] = [
+        CvdKind::Protanopia,
+        CvdKind::Deuteranopia,
+        CvdKind::Tritanopia,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CvdKind::Protanopia => "protan",
+            CvdKind::Deuteranopia => "deutan",
+            CvdKind::Tritanopia => "tritan",
+        }
+    }
+
+    fn matrix(self) -> &'static [[f32; 3]; 3] {
+        match self {
+            CvdKind::Protanopia => &CVD_PROTAN,
+            CvdKind::Deuteranopia => &CVD_DEUTAN,
+            CvdKind::Tritanopia => &CVD_TRITAN,
+        }
+    }
+}
+
+/// Machado, Oliveira & Fernandes (2009) simulation matrices at severity 1.0,
+/// applied to linear sRGB.
+const CVD_PROTAN: [[f32; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const CVD_DEUTAN: [[f32; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+const CVD_TRITAN: [[f32; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+
+/// Lab color as perceived with the given dichromacy. Neutral grays map to
+/// themselves.
+pub fn simulate_cvd_lab(lab: [f32; 3], kind: CvdKind) -> [f32; 3] {
+    let linear = xyz_to_rgb(lab_to_xyz(lab)).map(clamp01);
+    linear_to_lab(mat3_mul(kind.matrix(), linear).map(clamp01))
+}
+
+/// Color spaces available for clustering. Each space converts sRGB samples
+/// into its own coordinates and maps centroids back to 8-bit sRGB.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Cielab,
+    Rgb,
+    Hsl,
+    Hsv,
+    Yuv,
+    Cieluv,
+    Oklab,
+    /// Cartesian embedding of OKLCH: (L, C·cos h, C·sin h).
+    Oklch,
+    LinearRgb,
+    Jzazbz,
+    Cam16Ucs,
+}
+
+impl ColorSpace {
+    pub fn parse(name: &str) -> Option<Self> {
+        let normalized = name.to_ascii_uppercase().replace(['-', '_'], "");
+        match normalized.as_str() {
+            "CIELAB" | "LAB" => Some(Self::Cielab),
+            "RGB" => Some(Self::Rgb),
+            "HSL" => Some(Self::Hsl),
+            "HSV" => Some(Self::Hsv),
+            "YUV" => Some(Self::Yuv),
+            "CIELUV" | "LUV" => Some(Self::Cieluv),
+            "OKLAB" => Some(Self::Oklab),
+            "OKLCH" => Some(Self::Oklch),
+            "LINEARRGB" | "LINEAR" | "LRGB" => Some(Self::LinearRgb),
+            "JZAZBZ" => Some(Self::Jzazbz),
+            "CAM16UCS" | "CAM16" => Some(Self::Cam16Ucs),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Cielab => "CIELAB",
+            Self::Rgb => "RGB",
+            Self::Hsl => "HSL",
+            Self::Hsv => "HSV",
+            Self::Yuv => "YUV",
+            Self::Cieluv => "CIELUV",
+            Self::Oklab => "OKLAB",
+            Self::Oklch => "OKLCH",
+            Self::LinearRgb => "LINEAR-RGB",
+            Self::Jzazbz => "JZAZBZ",
+            Self::Cam16Ucs => "CAM16-UCS",
+        }
+    }
+
+    /// Component that stores a hue angle in degrees, if the space has one.
+    pub fn hue_axis(self) -> Option<usize> {
+        match self {
+            Self::Hsl | Self::Hsv => Some(0),
+            _ => None,
+        }
+    }
+
+    /// Encode gamma-encoded sRGB floats, possibly outside [0, 1] for wide-gamut
+    /// sources, without quantizing to 8 bits first.
+    pub fn encode_srgb(self, srgb: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Rgb => srgb.map(|c| c * 255.0),
+            Self::Hsl => srgb_to_hsl(srgb.map(clamp01)),
+            Self::Hsv => srgb_to_hsv(srgb.map(clamp01)),
+            Self::Yuv => yuv_from_255(srgb.map(|c| c * 255.0)),
+            _ => self
+                .encode_linear(srgb.map(srgb_to_linear_extended))
+                .expect("space is defined on linear light"),
+        }
+    }
+
+    /// Encode from linear-light RGB; `None` for the spaces defined on
+    /// gamma-encoded values (RGB, HSL, HSV, YUV).
+    fn encode_linear(self, linear: [f32; 3]) -> Option<[f32; 3]> {
+        Some(match self {
+            Self::Cielab => linear_to_lab(linear),
+            Self::Cieluv => linear_to_luv(linear),
+            Self::Oklab => linear_to_oklab(linear),
+            Self::Oklch => {
+                let [l, c, h] = oklab_to_oklch(linear_to_oklab(linear));
+                let (sin_h, cos_h) = h.to_radians().sin_cos();
+                [l, c * cos_h, c * sin_h]
+            }
+            Self::LinearRgb => linear,
+            Self::Jzazbz => xyz_to_jzazbz(rgb_to_xyz(linear)),
+            Self::Cam16Ucs => xyz_to_cam16_ucs(rgb_to_xyz(linear)),
+            Self::Rgb | Self::Hsl | Self::Hsv | Self::Yuv => return None,
+        })
+    }
+
+    pub fn encode(self, rgb: [u8; 3]) -> [f32; 3] {
+        match self {
+            Self::Cielab => rgb8_to_lab(rgb),
+            Self::Rgb => [rgb[0] as f32, rgb[1] as f32, rgb[2] as f32],
+            Self::Hsl => rgb8_to_hsl(rgb),
+            Self::Hsv => rgb8_to_hsv(rgb),
+            Self::Yuv => rgb8_to_yuv(rgb),
+            Self::Cieluv => rgb8_to_luv(rgb),
+            Self::Oklab => rgb8_to_oklab(rgb),
+            Self::Oklch => {
+                let [l, c, h] = oklab_to_oklch(rgb8_to_oklab(rgb));
+                let (sin_h, cos_h) = h.to_radians().sin_cos();
+                [l, c * cos_h, c * sin_h]
+            }
+            Self::LinearRgb => srgb8_to_linear(rgb),
+            Self::Jzazbz => rgb8_to_jzazbz(rgb),
+            Self::Cam16Ucs => rgb8_to_cam16_ucs(rgb),
+        }
+    }
+
+    pub fn decode(self, point: [f32; 3]) -> [u8; 3] {
+        match self {
+            Self::Cielab => linear_to_srgb8_mapped(xyz_to_rgb(lab_to_xyz(point))),
+            Self::Rgb => point.map(|c| c.round().clamp(0.0, 255.0) as u8),
+            Self::Hsl => hsl_to_rgb8(point),
+            Self::Hsv => hsv_to_rgb8(point),
+            Self::Yuv => yuv_to_rgb8(point),
+            Self::Cieluv => linear_to_srgb8_mapped(xyz_to_rgb(luv_to_xyz(point))),
+            Self::Oklab => linear_to_srgb8_mapped(oklab_to_linear(point)),
+            Self::Oklch => {
+                let c = (point[1] * point[1] + point[2] * point[2]).sqrt();
+                let h = point[2].atan2(point[1]).to_degrees();
+                linear_to_srgb8_mapped(oklab_to_linear(oklch_to_oklab([point[0], c, h])))
+            }
+            Self::LinearRgb => linear_to_srgb8_mapped(point),
+            Self::Jzazbz => jzazbz_to_rgb8(point),
+            Self::Cam16Ucs => cam16_ucs_to_rgb8(point),
+        }
+    }
+}
+
+// ============================================================================
+// Batch conversion
+// ============================================================================
+
+/// Samples per rayon task; large enough to amortize scheduling, small enough to
+/// balance across cores for a few hundred thousand samples.
+const BATCH_CHUNK: usize = 4096;
+
+/// Linear-light value for every 8-bit sRGB code, identical to `srgb_to_linear(c / 255)`.
+pub fn srgb8_to_linear_lut() -> &'static [f32; 256] {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+    LUT.get_or_init(|| {
+        let mut lut = [0.0; 256];
+        for (i, v) in lut.iter_mut().enumerate() {
+            *v = srgb_to_linear(i as f32 / 255.0);
+        }
+        lut
+    })
+}
+
+/// Map `input` into a new vector chunk by chunk on the rayon pool. Output order
+/// matches input order.
+fn par_convert<T: Sync>(
+    input: &[T],
+    convert: impl Fn(&[T], &mut [[f32; 3]]) + Sync,
+) -> Vec<[f32; 3]> {
+    let mut out = vec![[0.0; 3]; input.len()];
+    out.par_chunks_mut(BATCH_CHUNK)
+        .zip(input.par_chunks(BATCH_CHUNK))
+        .for_each(|(dst, src)| convert(src, dst));
+    out
+}
+
+pub fn srgb8_slice_to_linear(pixels: &[[u8; 3]]) -> Vec<[f32; 3]> {
+    let lut = srgb8_to_linear_lut();
+    par_convert(pixels, |src, dst| {
+        for (px, out) in src.iter().zip(dst.iter_mut()) {
+            *out = px.map(|c| lut[c as usize]);
+        }
+    })
+}
+
+/// Lab for 8-bit sRGB samples. Uses the AVX2 kernel when the `simd` feature is
+/// enabled and the CPU supports it; either way the result is bit-identical to
+/// `rgb8_to_lab`.
+pub fn srgb8_slice_to_lab(pixels: &[[u8; 3]]) -> Vec<[f32; 3]> {
+    let lut = srgb8_to_linear_lut();
+    par_convert(pixels, |src, dst| {
+        #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+        if crate::simd::has_avx2() {
+            // SAFETY: AVX2 support was checked at runtime; `dst` matches `src` in length.
+            unsafe { crate::simd::avx2::rgb8_to_lab(src, lut, dst) };
+            return;
+        }
+        for (px, out) in src.iter().zip(dst.iter_mut()) {
+            *out = linear_to_lab(px.map(|c| lut[c as usize]));
+        }
+    })
+}
+
+/// Lab for gamma-encoded sRGB float samples (see `srgb_to_lab`).
+pub fn srgb_slice_to_lab(pixels: &[[f32; 3]]) -> Vec<[f32; 3]> {
+    par_convert(pixels, |src, dst| {
+        for (px, out) in src.iter().zip(dst.iter_mut()) {
+            *out = srgb_to_lab(*px);
+        }
+    })
+}
+
+impl ColorSpace {
+    /// `encode` over a slice, in parallel, linearizing through the sRGB lookup
+    /// table. CIELAB takes the SIMD path of `srgb8_slice_to_lab`; the result
+    /// matches `encode` bit for bit on every CPU.
+    pub fn encode_slice(self, pixels: &[[u8; 3]]) -> Vec<[f32; 3]> {
+        if self == Self::Cielab {
+            return srgb8_slice_to_lab(pixels);
+        }
+        let lut = srgb8_to_linear_lut();
+        par_convert(pixels, |src, dst| {
+            for (px, out) in src.iter().zip(dst.iter_mut()) {
+                *out = self
+                    .encode_linear(px.map(|c| lut[c as usize]))
+                    .unwrap_or_else(|| self.encode(*px));
+            }
+        })
+    }
+
+    /// `encode_srgb` over a slice, in parallel.
+    pub fn encode_srgb_slice(self, pixels: &[[f32; 3]]) -> Vec<[f32; 3]> {
+        par_convert(pixels, |src, dst| {
+            for (px, out) in src.iter().zip(dst.iter_mut()) {
+                *out = self.encode_srgb(*px);
+            }
+        })
+    }
+}
@@ -0,0 +1,357 @@
+//! High-level entry point for embedding the generator: sample an image, cluster
+//! it and solve the theme roles in one call.
+//!
+//! ```no_run
+//! use rmpc_theme_gen::generator::{ThemeConfig, ThemeGenerator};
+//!
+//! let theme = ThemeGenerator::new(ThemeConfig::default())
+//!     .generate("cover.jpg")
+//!     .expect("theme");
+//! std::fs::write("current-song.ron", theme.to_ron()).expect("write theme");
+//! ```
+
+use std::cmp::Ordering;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::color::ColorSpace;
+use crate::image_pipeline::{prepare_samples, CropRect, SampleParams, SamplingError};
+use crate::kmeans::{
+    prequantize, run_kmeans, run_kmeans_prequantized, run_kmeans_weighted, KMeansAlgorithm,
+    KMeansConfig, KMeansInit,
+};
+use crate::render;
+use crate::roles::{
+    cvd_conflicts, map_colors_to_roles, pin_conflicts, ColorCluster, ColorRole, CvdMode,
+    DebugOutput, PinConflict, Preset, RoleAssignment, RoleOptions, RolePins, RoleSolver,
+};
+
+#[derive(Debug, Error)]
+pub enum GenerateError {
+    #[error(transparent)]
+    Sampling(#[from] SamplingError),
+    #[error("no pixels sampled from image")]
+    NoSamples,
+    #[error("unsupported color space: {0}")]
+    UnsupportedColorSpace(String),
+    #[error("no weighted samples left after quantization")]
+    EmptyAfterQuantization,
+}
+
+pub type Result<T> = std::result::Result<T, GenerateError>;
+
+/// Pixel samples kept in `DebugOutput::samples` for plotting.
+const DEBUG_SAMPLE_LIMIT: usize = 2000;
+
+/// K-means settings applied to the sampled pixels.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClusteringParams {
+    /// Clustering color space name as accepted by `ColorSpace::parse`.
+    pub space: String,
+    /// Requested cluster count; capped at the number of points when clustering.
+    pub k: usize,
+    pub max_iters: usize,
+    pub tol: f32,
+    pub seed: u64,
+    pub algorithm: KMeansAlgorithm,
+    pub init: KMeansInit,
+    pub n_init: usize,
+    pub quantize_bits: Option<u8>,
+}
+
+impl Default for ClusteringParams {
+    fn default() -> Self {
+        Self {
+            space: "CIELAB".to_string(),
+            k: 30,
+            max_iters: 40,
+            tol: 1e-3,
+            seed: 1,
+            algorithm: KMeansAlgorithm::default(),
+            init: KMeansInit::PlusPlus,
+            n_init: 1,
+            quantize_bits: None,
+        }
+    }
+}
+
+/// Everything that determines a generated theme.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThemeConfig {
+    /// Sampling settings. `path` is replaced by the image given to `generate`.
+    pub sampling: SampleParams,
+    pub clustering: ClusteringParams,
+    /// Strategy for picking the core roles. Defaults to greedy so configs
+    /// written before the option existed keep their meaning.
+    #[serde(default)]
+    pub solver: RoleSolver,
+    /// Require role separation to survive this simulated color vision deficiency.
+    #[serde(default)]
+    pub cvd: Option<CvdMode>,
+    /// Roles fixed to a given color; the rest are solved around them.
+    #[serde(default, skip_serializing_if = "RolePins::is_empty")]
+    pub pins: RolePins,
+    /// Palette and guardrail adjustments of the selected preset.
+    #[serde(default, skip_serializing_if = "Preset::is_default")]
+    pub preset: Preset,
+    pub scrollbar_enabled: bool,
+    /// Collect pairwise and per-role diagnostics into `Theme::debug`.
+    #[serde(skip)]
+    pub debug: bool,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        Self {
+            sampling: SampleParams::new(""),
+            clustering: ClusteringParams::default(),
+            solver: RoleSolver::Greedy,
+            cvd: None,
+            pins: RolePins::default(),
+            preset: Preset::default(),
+            scrollbar_enabled: true,
+            debug: false,
+        }
+    }
+}
+
+/// A solved theme: the clusters it was built from, the color chosen for every
+/// role and sampling statistics.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Theme {
+    /// Clusters sorted by weighted share, most dominant first.
+    pub clusters: Vec<ColorCluster>,
+    pub roles: Vec<RoleAssignment>,
+    /// Guardrails that the configured pins leave unmet.
+    pub pin_conflicts: Vec<PinConflict>,
+    /// `--cvd` separations that no candidate met.
+    pub cvd_conflicts: Vec<PinConflict>,
+    pub scrollbar_enabled: bool,
+    pub total_samples: usize,
+    pub iterations: usize,
+    pub bits_per_channel: u8,
+    pub icc_profile_applied: bool,
+    pub transparent_fraction: f32,
+    pub crop: Option<CropRect>,
+    /// Histogram buckets clustered when `quantize_bits` is set.
+    pub unique_buckets: Option<usize>,
+    pub debug: Option<DebugOutput>,
+}
+
+impl Theme {
+    pub fn role(&self, role: ColorRole) -> Option<&RoleAssignment> {
+        self.roles.iter().find(|assignment| assignment.role == role)
+    }
+
+    /// Render the rmpc RON theme file.
+    pub fn to_ron(&self) -> String {
+        render::theme_ron(&self.roles, self.scrollbar_enabled)
+    }
+}
+
+/// Every n-th sample, so at most `limit` remain spread across the whole image.
+fn thin_samples(samples: &[[u8; 3]], limit: usize) -> Vec<[u8; 3]> {
+    let step = samples.len().div_ceil(limit.max(1)).max(1);
+    samples.iter().step_by(step).copied().collect()
+}
+
+pub struct ThemeGenerator {
+    config: ThemeConfig,
+}
+
+impl ThemeGenerator {
+    pub fn new(config: ThemeConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn config(&self) -> &ThemeConfig {
+        &self.config
+    }
+
+    pub fn generate(&self, image: impl AsRef<Path>) -> Result<Theme> {
+        let sample_params = SampleParams {
+            path: image.as_ref().to_path_buf(),
+            ..self.config.sampling.clone()
+        };
+        let clustering = &self.config.clustering;
+
+        // Sample pixels from image
+        let mut sample_result = prepare_samples(&sample_params)?;
+        if sample_result.samples.is_empty() {
+            return Err(GenerateError::NoSamples);
+        }
+
+        // Convert samples to chosen color space; prepare_samples already produced Lab
+        let space = ColorSpace::parse(&clustering.space)
+            .ok_or_else(|| GenerateError::UnsupportedColorSpace(clustering.space.clone()))?;
+        let dataset: Vec<[f32; 3]> = match sample_result.samples_lab.take() {
+            Some(lab) if space == ColorSpace::Cielab => lab,
+            _ => match &sample_result.samples_f32 {
+                Some(samples) => space.encode_srgb_slice(samples),
+                None => space.encode_slice(&sample_result.samples),
+            },
+        };
+
+        // Optionally collapse samples into weighted histogram buckets
+        let quantized = clustering.quantize_bits.map(|bits| {
+            prequantize(
+                &sample_result.samples,
+                &dataset,
+                sample_result.weights.as_deref(),
+                bits,
+                space.hue_axis(),
+            )
+        });
+        if quantized.as_ref().is_some_and(|q| q.points.is_empty()) {
+            return Err(GenerateError::EmptyAfterQuantization);
+        }
+
+        // Run K-means clustering
+        let cluster_len = quantized.as_ref().map_or(dataset.len(), |q| q.points.len());
+        let k = clustering.k.min(cluster_len.max(1));
+        let kmeans_config = KMeansConfig {
+            k,
+            max_iters: clustering.max_iters,
+            tol: clustering.tol,
+            seed: clustering.seed,
+            warm_start: None,
+            mini_batch: None,
+            hue_axis: space.hue_axis(),
+            algorithm: clustering.algorithm,
+            init: clustering.init,
+            n_init: clustering.n_init,
+        };
+        let kmeans_result = match (&quantized, &sample_result.weights) {
+            (Some(q), _) => run_kmeans_prequantized(q, &kmeans_config),
+            (None, Some(weights)) => run_kmeans_weighted(&dataset, weights, &kmeans_config),
+            (None, None) => run_kmeans(&dataset, &kmeans_config),
+        };
+        let total_weight: f64 = kmeans_result.weights.iter().map(|&w| w as f64).sum();
+
+        // Convert centroids to all color spaces
+        let mut clusters: Vec<ColorCluster> = Vec::with_capacity(kmeans_result.centroids.len());
+        for ((centroid, &count), &weight) in kmeans_result
+            .centroids
+            .iter()
+            .zip(kmeans_result.counts.iter())
+            .zip(kmeans_result.weights.iter())
+        {
+            if count == 0 {
+                continue;
+            }
+
+            // Convert centroid from clustering space to RGB
+            let rgb_u8 = space.decode(*centroid);
+            let share = (weight as f64) / total_weight.max(f64::MIN_POSITIVE);
+            clusters.push(ColorCluster {
+                count,
+                ..ColorCluster::from_rgb(rgb_u8, share)
+            });
+        }
+
+        // Sort clusters by weighted share (descending) so dominance follows the
+        // sampling weights; equals count order for uniform sampling
+        clusters.sort_by(|a, b| b.share.partial_cmp(&a.share).unwrap_or(Ordering::Equal));
+
+        // Map colors to theme element roles
+        let role_options = RoleOptions {
+            solver: self.config.solver,
+            cvd: self.config.cvd,
+            pins: self.config.pins,
+            preset: self.config.preset,
+        };
+        let (roles, pairwise_debug, roles_debug) =
+            map_colors_to_roles(&clusters, &role_options, self.config.debug);
+        let pin_conflicts = pin_conflicts(&roles, &role_options);
+        let cvd_conflicts = cvd_conflicts(&roles, &role_options);
+
+        Ok(Theme {
+            clusters,
+            roles,
+            pin_conflicts,
+            cvd_conflicts,
+            scrollbar_enabled: self.config.scrollbar_enabled,
+            total_samples: sample_result.sampled_pixels,
+            iterations: kmeans_result.iterations,
+            bits_per_channel: sample_result.bits_per_channel,
+            icc_profile_applied: sample_result.icc_profile_applied,
+            transparent_fraction: sample_result.transparent_fraction,
+            crop: sample_result.crop,
+            unique_buckets: quantized.as_ref().map(|q| q.unique_buckets()),
+            debug: self.config.debug.then(|| DebugOutput {
+                pairwise: pairwise_debug,
+                roles: roles_debug,
+                samples: thin_samples(&sample_result.samples, DEBUG_SAMPLE_LIMIT),
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color;
+    use image::{Rgb, RgbImage};
+    use tempfile::{Builder, NamedTempFile};
+
+    fn write_cover() -> NamedTempFile {
+        let mut img = RgbImage::new(96, 96);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = match (x < 64, y < 48) {
+                (true, _) => Rgb([24, 28, 40]),
+                (false, true) => Rgb([220, 120, 40]),
+                (false, false) => Rgb([90, 170, 210]),
+            };
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+        file
+    }
+
+    #[test]
+    fn generates_every_role_and_renders_ron() {
+        let cover = write_cover();
+        let config = ThemeConfig {
+            clustering: ClusteringParams {
+                k: 6,
+                ..ClusteringParams::default()
+            },
+            ..ThemeConfig::default()
+        };
+        let theme = ThemeGenerator::new(config)
+            .generate(cover.path())
+            .expect("theme");
+
+        assert_eq!(theme.clusters[0].rgb.r, 24);
+        let background = theme.role(ColorRole::Background).expect("background");
+        let text = theme.role(ColorRole::Text).expect("text");
+        assert!(color::calculate_contrast_ratio(background.lab, text.lab) >= 4.5);
+        assert!(theme.role(ColorRole::HeaderAccent).is_some());
+        assert!(theme.debug.is_none());
+
+        let ron = theme.to_ron();
+        assert!(ron.contains(&background.hex));
+        assert!(ron.contains("scrollbar"));
+    }
+
+    #[test]
+    fn unknown_color_space_is_an_error() {
+        let cover = write_cover();
+        let config = ThemeConfig {
+            clustering: ClusteringParams {
+                space: "CMYK".to_string(),
+                ..ClusteringParams::default()
+            },
+            ..ThemeConfig::default()
+        };
+        let err = ThemeGenerator::new(config)
+            .generate(cover.path())
+            .expect_err("unsupported space");
+        assert!(matches!(err, GenerateError::UnsupportedColorSpace(name) if name == "CMYK"));
+    }
+}
@@ -1,11 +1,17 @@
+use std::io::Cursor;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use std::time::Instant;
 
 use image::imageops::FilterType;
-use image::{DynamicImage, ImageReader, RgbImage};
+use image::{
+    DynamicImage, GrayImage, ImageDecoder, ImageFormat, ImageReader, RgbImage, Rgba32FImage,
+    RgbaImage,
+};
+use moxcms::{ColorProfile, DataColorSpace, Layout, ToneReprCurve, TransformOptions};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -14,18 +20,127 @@ pub enum SamplingError {
     Io(#[from] std::io::Error),
     #[error("failed to decode image: {0}")]
     Decode(#[from] image::ImageError),
+    #[error("failed to apply embedded ICC profile: {0}")]
+    ColorManagement(String),
 }
 
 pub type Result<T> = std::result::Result<T, SamplingError>;
 
-#[derive(Debug, Clone)]
+/// How much each sampled pixel counts when clustering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum WeightingMode {
+    /// Every pixel counts equally; no weights are attached.
+    #[default]
+    Uniform,
+    /// Gaussian falloff from the center of the (cropped) image.
+    Center,
+    /// Frequency-tuned contrast saliency: distance from the image's mean Lab color.
+    Saliency,
+    /// Pixels on strong luma edges (outlines, anti-aliasing) count less.
+    EdgeSuppressed,
+}
+
+impl FromStr for WeightingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match normalized.as_str() {
+            "uniform" => Ok(WeightingMode::Uniform),
+            "center" => Ok(WeightingMode::Center),
+            "saliency" => Ok(WeightingMode::Saliency),
+            "edgesuppressed" => Ok(WeightingMode::EdgeSuppressed),
+            _ => Err(format!(
+                "unknown weighting mode '{s}' (expected uniform, center, saliency or edge-suppressed)"
+            )),
+        }
+    }
+}
+
+/// Resampling filter used when shrinking an image before sampling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeFilter {
+    #[default]
+    Lanczos3,
+    /// Box/area average: each output pixel is the mean of the source pixels it
+    /// covers. Much cheaper, and plenty for color statistics.
+    Area,
+}
+
+impl FromStr for ResizeFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lanczos3" | "lanczos" => Ok(ResizeFilter::Lanczos3),
+            "area" | "box" => Ok(ResizeFilter::Area),
+            other => Err(format!(
+                "unknown resize filter '{other}' (expected lanczos3 or area)"
+            )),
+        }
+    }
+}
+
+/// How pixels with partial or full transparency contribute to the sample set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlphaMode {
+    /// Drop pixels whose alpha is below `alpha_threshold`.
+    #[default]
+    Skip,
+    /// Keep each pixel with probability proportional to its alpha.
+    Weight,
+    /// Composite every pixel over the `matte` color.
+    Matte,
+}
+
+impl FromStr for AlphaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(AlphaMode::Skip),
+            "weight" => Ok(AlphaMode::Weight),
+            "matte" => Ok(AlphaMode::Matte),
+            other => Err(format!(
+                "unknown alpha mode '{other}' (expected skip, weight or matte)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SampleParams {
     pub path: PathBuf,
     pub stride: u32,
     pub min_lum: u8,
     pub max_samples: usize,
     pub max_dimension: Option<u32>,
+    /// Shrink so the longest side is about this many pixels before sampling.
+    /// JPEGs are scaled by 1/2, 1/4 or 1/8 during decode to get close cheaply.
+    pub target_size: Option<u32>,
+    pub resize_filter: ResizeFilter,
     pub seed: u64,
+    /// Convert images with an embedded non-sRGB ICC profile to sRGB before sampling.
+    pub color_management: bool,
+    pub alpha_mode: AlphaMode,
+    /// Pixels with alpha below this value count as transparent.
+    pub alpha_threshold: u8,
+    /// Background used by `AlphaMode::Matte`.
+    pub matte: [u8; 3],
+    /// Detect uniform frames/letterbox bars at the edges and sample inside them.
+    pub crop_borders: bool,
+    /// Down-weight high edge-density regions such as typography.
+    pub suppress_text: bool,
+    pub weighting: WeightingMode,
 }
 
 impl SampleParams {
@@ -36,7 +151,16 @@ impl SampleParams {
             min_lum: 0,
             max_samples: 300_000,
             max_dimension: Some(3200),
+            target_size: None,
+            resize_filter: ResizeFilter::Lanczos3,
             seed: 1,
+            color_management: true,
+            alpha_mode: AlphaMode::Skip,
+            alpha_threshold: 128,
+            matte: [0, 0, 0],
+            crop_borders: false,
+            suppress_text: false,
+            weighting: WeightingMode::Uniform,
         }
     }
 }
@@ -44,14 +168,37 @@ impl SampleParams {
 #[derive(Debug, Serialize)]
 pub struct SampleResult {
     pub samples: Vec<[u8; 3]>,
+    /// Gamma-encoded sRGB floats from the high-precision path (16-bit sources or
+    /// ICC-converted images). Wide-gamut colors may fall outside [0, 1].
+    pub samples_f32: Option<Vec<[f32; 3]>>,
+    /// CIELAB of every sample, converted in parallel chunks (through the AVX2
+    /// kernel under the `simd` feature); CIELAB clustering uses it as is.
     pub samples_lab: Option<Vec<[f32; 3]>>,
+    /// Per-sample weights from `SampleParams::weighting`; `None` for uniform.
+    pub weights: Option<Vec<f32>>,
     pub width: u32,
     pub height: u32,
     pub total_pixels: u64,
     pub sampled_pixels: usize,
+    pub bits_per_channel: u8,
+    pub icc_profile_applied: bool,
+    /// Share of stride-grid pixels (inside `crop`) whose alpha is below
+    /// `alpha_threshold`.
+    pub transparent_fraction: f32,
+    /// Detected border crop, in the coordinates of the (downscaled) `width` x
+    /// `height` image. `None` when no border was found or cropping is disabled.
+    pub crop: Option<CropRect>,
     pub duration_ms: u128,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
 const LUMA_R: f32 = 0.2126;
 const LUMA_G: f32 = 0.7152;
 const LUMA_B: f32 = 0.0722;
@@ -59,84 +206,875 @@ const LUMA_B: f32 = 0.0722;
 pub fn prepare_samples(params: &SampleParams) -> Result<SampleResult> {
     let start = Instant::now();
 
-    // Use with_guessed_format() to handle files without extensions
-    // This reads the file header to detect the format automatically
-    let img = ImageReader::open(&params.path)?
-        .with_guessed_format()?
-        .decode()?;
-
-    let rgb = to_rgb_with_downscale(img, params.max_dimension);
-    let (width, height) = rgb.dimensions();
-    let samples = sample_pixels(&rgb, params);
-    let samples_lab = samples
+    let limit = match (params.max_dimension, params.target_size) {
+        (Some(max), Some(target)) => Some(max.min(target)),
+        (max, target) => max.or(target),
+    };
+    let (img, icc) = decode_image(params, limit)?;
+
+    let bits_per_channel = bits_per_channel(&img);
+    let profile = icc.as_deref().and_then(non_srgb_profile);
+
+    if bits_per_channel <= 8 && profile.is_none() {
+        let rgba = to_rgba_with_downscale(img, limit, params.resize_filter);
+        let (width, height) = rgba.dimensions();
+        let region = SampleRegion::analyze(width, height, params, |x, y| {
+            rgba.get_pixel(x, y).0.map(|c| c as f32)
+        });
+        let GridSamples {
+            samples,
+            weights,
+            transparent_fraction,
+        } = sample_pixels(&rgba, &region, params);
+        let samples_lab = crate::color::srgb8_slice_to_lab(&samples);
+        let sampled_pixels = samples.len();
+
+        return Ok(SampleResult {
+            samples,
+            samples_f32: None,
+            samples_lab: Some(samples_lab),
+            weights,
+            width,
+            height,
+            total_pixels: width as u64 * height as u64,
+            sampled_pixels,
+            bits_per_channel,
+            icc_profile_applied: false,
+            transparent_fraction,
+            crop: region.reported_crop(),
+            duration_ms: start.elapsed().as_millis(),
+        });
+    }
+
+    let mut rgba = to_rgba32f_with_downscale(img, limit, params.resize_filter);
+    let icc_profile_applied = match &profile {
+        Some(source) => {
+            convert_to_srgb(&mut rgba, source)?;
+            true
+        }
+        None => false,
+    };
+    let (width, height) = rgba.dimensions();
+    let region = SampleRegion::analyze(width, height, params, |x, y| {
+        rgba.get_pixel(x, y).0.map(|c| c.clamp(0.0, 1.0) * 255.0)
+    });
+    let GridSamples {
+        samples: samples_f32,
+        weights,
+        transparent_fraction,
+    } = sample_pixels_f32(&rgba, &region, params);
+    let samples = samples_f32
         .iter()
-        .map(|rgb| crate::color::rgb8_to_lab(*rgb))
+        .map(|px| px.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8))
         .collect::<Vec<_>>();
+    let samples_lab = crate::color::srgb_slice_to_lab(&samples_f32);
     let sampled_pixels = samples.len();
 
     Ok(SampleResult {
         samples,
+        samples_f32: Some(samples_f32),
         samples_lab: Some(samples_lab),
+        weights,
         width,
         height,
         total_pixels: width as u64 * height as u64,
         sampled_pixels,
+        bits_per_channel,
+        icc_profile_applied,
+        transparent_fraction,
+        crop: region.reported_crop(),
         duration_ms: start.elapsed().as_millis(),
     })
 }
 
-fn to_rgb_with_downscale(img: DynamicImage, limit: Option<u32>) -> RgbImage {
-    let rgb = img.to_rgb8();
-    if let Some(max_dim) = limit {
-        let (w, h) = rgb.dimensions();
-        let current_max = w.max(h);
-        if current_max > max_dim {
-            let scale = max_dim as f32 / current_max as f32;
-            let dst_w = ((w as f32) * scale).round().max(1.0) as u32;
-            let dst_h = ((h as f32) * scale).round().max(1.0) as u32;
-            return image::imageops::resize(&rgb, dst_w, dst_h, FilterType::Lanczos3);
+fn bits_per_channel(img: &DynamicImage) -> u8 {
+    let color = img.color();
+    (color.bits_per_pixel() / color.channel_count() as u16) as u8
+}
+
+/// Parse an embedded ICC profile, returning it only when it describes an RGB
+/// space whose primaries or tone curves differ from sRGB (so a conversion is
+/// worth doing).
+fn non_srgb_profile(icc: &[u8]) -> Option<ColorProfile> {
+    let profile = ColorProfile::new_from_slice(icc).ok()?;
+    if profile.color_space != DataColorSpace::Rgb {
+        return None;
+    }
+    let srgb = ColorProfile::new_srgb();
+    let close = |a: moxcms::Xyzd, b: moxcms::Xyzd| {
+        (a.x - b.x).abs() < 2e-3 && (a.y - b.y).abs() < 2e-3 && (a.z - b.z).abs() < 2e-3
+    };
+    let matches_srgb = close(profile.red_colorant, srgb.red_colorant)
+        && close(profile.green_colorant, srgb.green_colorant)
+        && close(profile.blue_colorant, srgb.blue_colorant)
+        && same_trc(&profile.red_trc, &srgb.red_trc)
+        && same_trc(&profile.green_trc, &srgb.green_trc)
+        && same_trc(&profile.blue_trc, &srgb.blue_trc);
+    if matches_srgb {
+        None
+    } else {
+        Some(profile)
+    }
+}
+
+/// Compare two tone curves by sampling them, since equivalent curves are often
+/// encoded differently (a parametric curve versus a 1024-entry table).
+fn same_trc(a: &Option<ToneReprCurve>, b: &Option<ToneReprCurve>) -> bool {
+    let (Some(a), Some(b)) = (a, b) else {
+        // Nothing to compare against; the colorants decide.
+        return true;
+    };
+    let (Ok(a), Ok(b)) = (a.make_linear_evaluator(), b.make_linear_evaluator()) else {
+        return false;
+    };
+    (0..=16).all(|i| {
+        let x = i as f32 / 16.0;
+        (a.evaluate_value(x) - b.evaluate_value(x)).abs() < 2e-3
+    })
+}
+
+fn convert_to_srgb(img: &mut Rgba32FImage, source: &ColorProfile) -> Result<()> {
+    let srgb = ColorProfile::new_srgb();
+    // Extended range keeps out-of-sRGB colors as values below 0 or above 1
+    // instead of clipping them at the destination.
+    let options = TransformOptions {
+        prefer_fixed_point: false,
+        allow_extended_range_rgb_xyz: true,
+        ..TransformOptions::default()
+    };
+    let transform = source
+        .create_transform_f32(Layout::Rgba, &srgb, Layout::Rgba, options)
+        .map_err(|err| SamplingError::ColorManagement(err.to_string()))?;
+    let src = img.as_raw().clone();
+    transform
+        .transform(&src, img.as_mut())
+        .map_err(|err| SamplingError::ColorManagement(err.to_string()))
+}
+
+fn downscaled_dimensions(w: u32, h: u32, limit: Option<u32>) -> Option<(u32, u32)> {
+    let max_dim = limit?;
+    let current_max = w.max(h);
+    if current_max <= max_dim {
+        return None;
+    }
+    let scale = max_dim as f32 / current_max as f32;
+    let dst_w = ((w as f32) * scale).round().max(1.0) as u32;
+    let dst_h = ((h as f32) * scale).round().max(1.0) as u32;
+    Some((dst_w, dst_h))
+}
+
+fn decode_image(
+    params: &SampleParams,
+    limit: Option<u32>,
+) -> Result<(DynamicImage, Option<Vec<u8>>)> {
+    let bytes = std::fs::read(&params.path)?;
+    // Use with_guessed_format() to handle files without extensions
+    // This reads the file header to detect the format automatically
+    let reader = ImageReader::new(Cursor::new(bytes.as_slice())).with_guessed_format()?;
+    // DCT scaling only kicks in when a target size was asked for, so plain
+    // `max_dimension` downscaling keeps decoding at full resolution.
+    if reader.format() == Some(ImageFormat::Jpeg) && params.target_size.is_some() {
+        if let Some(decoded) =
+            limit.and_then(|limit| decode_jpeg_scaled(&bytes, limit, params.color_management))
+        {
+            return Ok(decoded);
+        }
+    }
+    let mut decoder = reader.into_decoder()?;
+    let icc = if params.color_management {
+        decoder.icc_profile().ok().flatten()
+    } else {
+        None
+    };
+    Ok((DynamicImage::from_decoder(decoder)?, icc))
+}
+
+/// Decode a JPEG with DCT-domain scaling to the smallest of 1/8, 1/4, 1/2 or
+/// full size that still covers `limit` on the longest side. Returns `None`
+/// when no scaling applies or for inputs handled better by the full decoder
+/// (CMYK, 16-bit, or anything jpeg-decoder rejects), so the caller falls back.
+fn decode_jpeg_scaled(
+    bytes: &[u8],
+    limit: u32,
+    want_icc: bool,
+) -> Option<(DynamicImage, Option<Vec<u8>>)> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    decoder.read_info().ok()?;
+    let info = decoder.info()?;
+    let (req_w, req_h) = downscaled_dimensions(info.width as u32, info.height as u32, Some(limit))?;
+    let (out_w, out_h) = decoder.scale(req_w as u16, req_h as u16).ok()?;
+    if (out_w, out_h) == (info.width, info.height) {
+        return None;
+    }
+    let pixels = decoder.decode().ok()?;
+    let (w, h) = (out_w as u32, out_h as u32);
+    let img = match decoder.info()?.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => {
+            DynamicImage::ImageRgb8(RgbImage::from_raw(w, h, pixels)?)
+        }
+        jpeg_decoder::PixelFormat::L8 => {
+            DynamicImage::ImageLuma8(GrayImage::from_raw(w, h, pixels)?)
+        }
+        _ => return None,
+    };
+    let icc = if want_icc {
+        decoder.icc_profile()
+    } else {
+        None
+    };
+    Some((img, icc))
+}
+
+fn to_rgba_with_downscale(
+    img: DynamicImage,
+    limit: Option<u32>,
+    filter: ResizeFilter,
+) -> RgbaImage {
+    let (w, h) = (img.width(), img.height());
+    if img.color().has_alpha() && downscaled_dimensions(w, h, limit).is_some() {
+        // Filter in premultiplied float so transparent pixels don't bleed
+        // their (usually black) color into the opaque edges.
+        let resized = to_rgba32f_with_downscale(img, limit, filter);
+        return DynamicImage::ImageRgba32F(resized).to_rgba8();
+    }
+    let rgba = img.to_rgba8();
+    match (downscaled_dimensions(w, h, limit), filter) {
+        (Some((dst_w, dst_h)), ResizeFilter::Lanczos3) => {
+            image::imageops::resize(&rgba, dst_w, dst_h, FilterType::Lanczos3)
+        }
+        (Some((dst_w, dst_h)), ResizeFilter::Area) => {
+            let data = area_downscale(
+                rgba.as_raw(),
+                (w, h),
+                (dst_w, dst_h),
+                |c| c as f32,
+                |v| v.round() as u8,
+            );
+            RgbaImage::from_raw(dst_w, dst_h, data).expect("area downscale buffer size")
+        }
+        (None, _) => rgba,
+    }
+}
+
+fn to_rgba32f_with_downscale(
+    img: DynamicImage,
+    limit: Option<u32>,
+    filter: ResizeFilter,
+) -> Rgba32FImage {
+    let (w, h) = (img.width(), img.height());
+    let Some((dst_w, dst_h)) = downscaled_dimensions(w, h, limit) else {
+        return img.to_rgba32f();
+    };
+    if !img.color().has_alpha() {
+        return match filter {
+            ResizeFilter::Lanczos3 => img
+                .resize_exact(dst_w, dst_h, FilterType::Lanczos3)
+                .to_rgba32f(),
+            ResizeFilter::Area => {
+                let rgba = img.to_rgba32f();
+                let data = area_downscale(rgba.as_raw(), (w, h), (dst_w, dst_h), |c| c, |v| v);
+                Rgba32FImage::from_raw(dst_w, dst_h, data).expect("area downscale buffer size")
+            }
+        };
+    }
+    let mut rgba = img.to_rgba32f();
+    premultiply(&mut rgba);
+    let mut resized = match filter {
+        ResizeFilter::Lanczos3 => {
+            image::imageops::resize(&rgba, dst_w, dst_h, FilterType::Lanczos3)
+        }
+        ResizeFilter::Area => {
+            let data = area_downscale(rgba.as_raw(), (w, h), (dst_w, dst_h), |c| c, |v| v);
+            Rgba32FImage::from_raw(dst_w, dst_h, data).expect("area downscale buffer size")
+        }
+    };
+    unpremultiply(&mut resized);
+    resized
+}
+
+fn premultiply(img: &mut Rgba32FImage) {
+    for px in img.pixels_mut() {
+        let a = px.0[3];
+        for c in &mut px.0[..3] {
+            *c *= a;
+        }
+    }
+}
+
+fn unpremultiply(img: &mut Rgba32FImage) {
+    for px in img.pixels_mut() {
+        // Lanczos ringing can push alpha slightly outside 0..=1.
+        let a = px.0[3].clamp(0.0, 1.0);
+        px.0[3] = a;
+        for c in &mut px.0[..3] {
+            *c = if a > 0.0 { *c / a } else { 0.0 };
+        }
+    }
+}
+
+/// Box-filter an interleaved RGBA buffer down to `dst` dimensions: every output
+/// pixel averages the block of source pixels that maps onto it.
+fn area_downscale<T: Copy>(
+    src: &[T],
+    (src_w, src_h): (u32, u32),
+    (dst_w, dst_h): (u32, u32),
+    to_f32: impl Fn(T) -> f32,
+    from_f32: impl Fn(f32) -> T,
+) -> Vec<T> {
+    const CHANNELS: usize = 4;
+    let span = |dst: u32, src_len: u32, dst_len: u32| {
+        let start = (dst as u64 * src_len as u64 / dst_len as u64) as u32;
+        let end = ((dst as u64 + 1) * src_len as u64 / dst_len as u64) as u32;
+        (start, end.max(start + 1).min(src_len))
+    };
+    let mut out = Vec::with_capacity(dst_w as usize * dst_h as usize * CHANNELS);
+    for dy in 0..dst_h {
+        let (y0, y1) = span(dy, src_h, dst_h);
+        for dx in 0..dst_w {
+            let (x0, x1) = span(dx, src_w, dst_w);
+            let mut sum = [0.0f32; CHANNELS];
+            for y in y0..y1 {
+                let row = (y as usize * src_w as usize + x0 as usize) * CHANNELS;
+                let row_end = (y as usize * src_w as usize + x1 as usize) * CHANNELS;
+                for px in src[row..row_end].chunks_exact(CHANNELS) {
+                    for (s, &c) in sum.iter_mut().zip(px) {
+                        *s += to_f32(c);
+                    }
+                }
+            }
+            let n = ((x1 - x0) * (y1 - y0)) as f32;
+            out.extend(sum.iter().map(|s| from_f32(s / n)));
+        }
+    }
+    out
+}
+
+/// Max per-channel difference (0-255 scale) for a pixel to match a border color.
+const BORDER_TOLERANCE: f32 = 24.0;
+/// Share of a line that must match for it to count as part of a border.
+const BORDER_MIN_UNIFORM: f32 = 0.98;
+/// A side that stays uniform past this share of the image is background, not a frame.
+const BORDER_MAX_FRACTION: f32 = 0.4;
+const BORDER_MIN_THICKNESS: u32 = 2;
+/// Share of the first line past a border that must differ from it. A frame
+/// ends in a clean transition; a plain backdrop ends where the subject begins.
+const BORDER_MIN_TRANSITION: f32 = 0.5;
+
+const TEXT_CELL: u32 = 16;
+/// Luma gradient (0-255 scale) above which a pixel counts as an edge.
+const TEXT_EDGE_THRESHOLD: f32 = 48.0;
+/// Edge density where down-weighting starts and where it bottoms out.
+const TEXT_DENSITY_START: f32 = 0.08;
+const TEXT_DENSITY_FULL: f32 = 0.3;
+const TEXT_MIN_WEIGHT: f32 = 0.1;
+
+/// The part of the image that gets sampled, plus optional per-cell weights.
+struct SampleRegion {
+    crop: CropRect,
+    cropped: bool,
+    text: Option<EdgeDensityMap>,
+    /// Row-major weights for the stride grid inside `crop`.
+    grid_weights: Option<Vec<f32>>,
+    grid_cols: u32,
+}
+
+impl SampleRegion {
+    /// `rgba_at` returns in-gamut sRGB and alpha on a 0-255 scale.
+    fn analyze(
+        width: u32,
+        height: u32,
+        params: &SampleParams,
+        rgba_at: impl Fn(u32, u32) -> [f32; 4],
+    ) -> Self {
+        let rgb_at = |x, y| {
+            let [r, g, b, _] = rgba_at(x, y);
+            [r, g, b]
+        };
+        let border = if params.crop_borders {
+            // Transparent pixels all look alike whatever color they store, so
+            // transparent padding reads as a uniform border.
+            let threshold = params.alpha_threshold as f32;
+            let border_key = |x, y| {
+                let [r, g, b, a] = rgba_at(x, y);
+                if a < threshold {
+                    [0.0; 4]
+                } else {
+                    [r, g, b, 255.0]
+                }
+            };
+            detect_border(width, height, &border_key)
+        } else {
+            None
+        };
+        let text = if params.suppress_text {
+            Some(EdgeDensityMap::build(width, height, &rgb_at))
+        } else {
+            None
+        };
+        let crop = border.unwrap_or(CropRect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+        let stride = params.stride.max(1);
+        let grid_cols = crop.width.div_ceil(stride);
+        let grid_weights = grid_weights(crop, stride, params.weighting, &rgb_at);
+        Self {
+            crop,
+            cropped: border.is_some(),
+            text,
+            grid_weights,
+            grid_cols,
+        }
+    }
+
+    fn grid_weight(&self, x: u32, y: u32, stride: u32) -> f32 {
+        match &self.grid_weights {
+            Some(weights) => {
+                let gx = (x - self.crop.x) / stride;
+                let gy = (y - self.crop.y) / stride;
+                weights[(gy * self.grid_cols + gx) as usize]
+            }
+            None => 1.0,
+        }
+    }
+
+    fn reported_crop(&self) -> Option<CropRect> {
+        self.cropped.then_some(self.crop)
+    }
+}
+
+fn channels_match(a: [f32; 4], b: [f32; 4]) -> bool {
+    a.iter()
+        .zip(b.iter())
+        .all(|(x, y)| (x - y).abs() <= BORDER_TOLERANCE)
+}
+
+fn line_mean(len: u32, at: &impl Fn(u32) -> [f32; 4]) -> [f32; 4] {
+    let mut sum = [0.0_f64; 4];
+    for i in 0..len {
+        for (s, c) in sum.iter_mut().zip(at(i)) {
+            *s += c as f64;
+        }
+    }
+    sum.map(|s| (s / len.max(1) as f64) as f32)
+}
+
+fn line_match_fraction(len: u32, reference: [f32; 4], at: &impl Fn(u32) -> [f32; 4]) -> f32 {
+    let matching = (0..len)
+        .filter(|&i| channels_match(at(i), reference))
+        .count();
+    matching as f32 / len.max(1) as f32
+}
+
+/// Thickness and color of a uniform band starting at line 0 and moving
+/// inward. `line(d)` yields an accessor for the line at depth `d`, each `len`
+/// pixels long.
+fn border_band<F: Fn(u32) -> [f32; 4]>(
+    depth: u32,
+    len: u32,
+    line: impl Fn(u32) -> F,
+) -> (u32, [f32; 4]) {
+    let reference = line_mean(len, &line(0));
+    let max_depth = (depth as f32 * BORDER_MAX_FRACTION) as u32;
+    let mut thickness = 0;
+    while thickness < depth
+        && line_match_fraction(len, reference, &line(thickness)) >= BORDER_MIN_UNIFORM
+    {
+        thickness += 1;
+        if thickness > max_depth {
+            return (0, reference);
+        }
+    }
+    let transition = thickness < depth
+        && line_match_fraction(len, reference, &line(thickness)) <= 1.0 - BORDER_MIN_TRANSITION;
+    if thickness < BORDER_MIN_THICKNESS || !transition {
+        (0, reference)
+    } else {
+        (thickness, reference)
+    }
+}
+
+/// Keep a pair of opposite bands only when both sides carry a border of the
+/// same color; a solid block on one side is usually part of the artwork.
+fn paired(a: (u32, [f32; 4]), b: (u32, [f32; 4])) -> (u32, u32) {
+    if a.0 > 0 && b.0 > 0 && channels_match(a.1, b.1) {
+        (a.0, b.0)
+    } else {
+        (0, 0)
+    }
+}
+
+/// Scan inward from each edge for solid frames or letterbox bars.
+fn detect_border(
+    width: u32,
+    height: u32,
+    rgba_at: &impl Fn(u32, u32) -> [f32; 4],
+) -> Option<CropRect> {
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let (top, bottom) = paired(
+        border_band(height, width, |d| move |i| rgba_at(i, d)),
+        border_band(height, width, |d| move |i| rgba_at(i, height - 1 - d)),
+    );
+    let (left, right) = paired(
+        border_band(width, height, |d| move |i| rgba_at(d, i)),
+        border_band(width, height, |d| move |i| rgba_at(width - 1 - d, i)),
+    );
+
+    if top + bottom + left + right == 0 || top + bottom >= height || left + right >= width {
+        return None;
+    }
+    Some(CropRect {
+        x: left,
+        y: top,
+        width: width - left - right,
+        height: height - top - bottom,
+    })
+}
+
+/// Per-cell sampling weights that fall off with luma edge density, so busy
+/// high-contrast regions (usually lettering) contribute fewer samples.
+struct EdgeDensityMap {
+    cols: u32,
+    weights: Vec<f32>,
+}
+
+impl EdgeDensityMap {
+    fn build(width: u32, height: u32, rgb_at: &impl Fn(u32, u32) -> [f32; 3]) -> Self {
+        let luma = |x: u32, y: u32| {
+            let [r, g, b] = rgb_at(x, y);
+            LUMA_R * r + LUMA_G * g + LUMA_B * b
+        };
+        let cols = width.div_ceil(TEXT_CELL).max(1);
+        let rows = height.div_ceil(TEXT_CELL).max(1);
+        let mut edges = vec![0_u32; (cols * rows) as usize];
+        let mut totals = vec![0_u32; (cols * rows) as usize];
+        for y in 0..height {
+            for x in 0..width {
+                let here = luma(x, y);
+                let dx = if x + 1 < width {
+                    luma(x + 1, y) - here
+                } else {
+                    0.0
+                };
+                let dy = if y + 1 < height {
+                    luma(x, y + 1) - here
+                } else {
+                    0.0
+                };
+                let cell = ((y / TEXT_CELL) * cols + x / TEXT_CELL) as usize;
+                totals[cell] += 1;
+                if dx.abs() + dy.abs() > TEXT_EDGE_THRESHOLD {
+                    edges[cell] += 1;
+                }
+            }
+        }
+        let weights = edges
+            .iter()
+            .zip(totals.iter())
+            .map(|(&e, &t)| {
+                let density = e as f32 / t.max(1) as f32;
+                let t = (density - TEXT_DENSITY_START) / (TEXT_DENSITY_FULL - TEXT_DENSITY_START);
+                1.0 - t.clamp(0.0, 1.0) * (1.0 - TEXT_MIN_WEIGHT)
+            })
+            .collect();
+        Self { cols, weights }
+    }
+
+    fn weight(&self, x: u32, y: u32) -> f32 {
+        self.weights[((y / TEXT_CELL) * self.cols + x / TEXT_CELL) as usize]
+    }
+}
+
+/// Standard deviation of the center falloff, relative to the crop half-size.
+const CENTER_SIGMA: f32 = 0.5;
+/// Longest side of the block-averaged image used for saliency.
+const SALIENCY_GRID: u32 = 64;
+/// Minimum weight so non-salient regions still contribute.
+const SALIENCY_FLOOR: f32 = 0.1;
+/// Luma gradient (0-255 scale) at which an edge pixel's weight halves.
+const EDGE_SOFTNESS: f32 = 32.0;
+
+fn grid_weights(
+    crop: CropRect,
+    stride: u32,
+    mode: WeightingMode,
+    rgb_at: &impl Fn(u32, u32) -> [f32; 3],
+) -> Option<Vec<f32>> {
+    let grid = || {
+        (crop.y..crop.y + crop.height)
+            .step_by(stride as usize)
+            .flat_map(move |y| {
+                (crop.x..crop.x + crop.width)
+                    .step_by(stride as usize)
+                    .map(move |x| (x, y))
+            })
+    };
+    match mode {
+        WeightingMode::Uniform => None,
+        WeightingMode::Center => {
+            let half_w = (crop.width as f32 / 2.0).max(1.0);
+            let half_h = (crop.height as f32 / 2.0).max(1.0);
+            let cx = crop.x as f32 + half_w;
+            let cy = crop.y as f32 + half_h;
+            let denom = 2.0 * CENTER_SIGMA * CENTER_SIGMA;
+            Some(
+                grid()
+                    .map(|(x, y)| {
+                        let dx = (x as f32 + 0.5 - cx) / half_w;
+                        let dy = (y as f32 + 0.5 - cy) / half_h;
+                        (-(dx * dx + dy * dy) / denom).exp()
+                    })
+                    .collect(),
+            )
+        }
+        WeightingMode::Saliency => {
+            let map = SaliencyMap::build(crop, rgb_at);
+            Some(grid().map(|(x, y)| map.weight(x, y)).collect())
+        }
+        WeightingMode::EdgeSuppressed => {
+            let luma = |x: u32, y: u32| {
+                let [r, g, b] = rgb_at(x, y);
+                LUMA_R * r + LUMA_G * g + LUMA_B * b
+            };
+            let right = crop.x + crop.width - 1;
+            let bottom = crop.y + crop.height - 1;
+            Some(
+                grid()
+                    .map(|(x, y)| {
+                        let dx =
+                            luma((x + 1).min(right), y) - luma(x.saturating_sub(1).max(crop.x), y);
+                        let dy =
+                            luma(x, (y + 1).min(bottom)) - luma(x, y.saturating_sub(1).max(crop.y));
+                        let g = (dx.abs() + dy.abs()) * 0.5;
+                        1.0 / (1.0 + g / EDGE_SOFTNESS)
+                    })
+                    .collect(),
+            )
+        }
+    }
+}
+
+/// Frequency-tuned saliency (Achanta et al. 2009) on a block-averaged Lab
+/// image: a block is salient in proportion to its distance from the mean color.
+struct SaliencyMap {
+    crop: CropRect,
+    block: u32,
+    cols: u32,
+    weights: Vec<f32>,
+}
+
+impl SaliencyMap {
+    fn build(crop: CropRect, rgb_at: &impl Fn(u32, u32) -> [f32; 3]) -> Self {
+        let block = crop.width.max(crop.height).div_ceil(SALIENCY_GRID).max(1);
+        let cols = crop.width.div_ceil(block);
+        let rows = crop.height.div_ceil(block);
+        let mut labs = Vec::with_capacity((cols * rows) as usize);
+        for by in 0..rows {
+            for bx in 0..cols {
+                let x0 = crop.x + bx * block;
+                let y0 = crop.y + by * block;
+                let x1 = (x0 + block).min(crop.x + crop.width);
+                let y1 = (y0 + block).min(crop.y + crop.height);
+                let mut sum = [0.0_f32; 3];
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        for (s, c) in sum.iter_mut().zip(rgb_at(x, y)) {
+                            *s += c;
+                        }
+                    }
+                }
+                let n = ((x1 - x0) * (y1 - y0)).max(1) as f32;
+                labs.push(crate::color::srgb_to_lab(sum.map(|s| s / n / 255.0)));
+            }
+        }
+        let mut mean = [0.0_f32; 3];
+        for lab in &labs {
+            for (m, c) in mean.iter_mut().zip(lab) {
+                *m += c;
+            }
+        }
+        let mean = mean.map(|m| m / labs.len().max(1) as f32);
+        let distances: Vec<f32> = labs
+            .iter()
+            .map(|lab| crate::color::delta_e_cie76(*lab, mean))
+            .collect();
+        let max = distances.iter().cloned().fold(0.0_f32, f32::max);
+        let weights = distances
+            .iter()
+            .map(|d| {
+                let s = if max > 0.0 { d / max } else { 1.0 };
+                SALIENCY_FLOOR + (1.0 - SALIENCY_FLOOR) * s
+            })
+            .collect();
+        Self {
+            crop,
+            block,
+            cols,
+            weights,
         }
     }
-    rgb
+
+    fn weight(&self, x: u32, y: u32) -> f32 {
+        let bx = (x - self.crop.x) / self.block;
+        let by = (y - self.crop.y) / self.block;
+        self.weights[(by * self.cols + bx) as usize]
+    }
 }
 
-fn sample_pixels(img: &RgbImage, params: &SampleParams) -> Vec<[u8; 3]> {
+/// Output of `sample_grid`.
+struct GridSamples<P> {
+    samples: Vec<P>,
+    weights: Option<Vec<f32>>,
+    transparent_fraction: f32,
+}
+
+/// A grid pixel as seen by `sample_grid`: the (possibly matte-composited)
+/// color, its luma on a 0-255 scale and its original alpha in [0, 1].
+struct GridPixel<P> {
+    pixel: P,
+    lum: f32,
+    alpha: f32,
+}
+
+/// Walk the stride grid inside `region.crop`, keeping pixels at or above
+/// `min_lum` and capping the result with reservoir sampling. Transparency is
+/// handled per `params.alpha_mode`; alpha weighting and text suppression both
+/// thin pixels by seeded acceptance, while `params.weighting` attaches a
+/// weight to every kept sample.
+fn sample_grid<P: Copy>(
+    region: &SampleRegion,
+    params: &SampleParams,
+    fetch: impl Fn(u32, u32) -> GridPixel<P>,
+) -> GridSamples<P> {
     let stride = params.stride.max(1) as usize;
     let min_lum = params.min_lum as f32;
+    let threshold = params.alpha_threshold as f32 / 255.0;
     let max_samples = if params.max_samples == 0 {
         usize::MAX
     } else {
         params.max_samples
     };
 
-    let (width, height) = img.dimensions();
-    let mut samples: Vec<[u8; 3]> =
-        Vec::with_capacity(max_samples.min((width as usize) * (height as usize)));
+    let crop = region.crop;
+    let capacity = max_samples.min((crop.width as usize) * (crop.height as usize));
+    let mut samples: Vec<P> = Vec::with_capacity(capacity);
+    let mut weights: Option<Vec<f32>> = region
+        .grid_weights
+        .as_ref()
+        .map(|_| Vec::with_capacity(capacity));
 
     let mut rng = SmallRng::seed_from_u64(params.seed);
+    // Separate stream so alpha acceptance doesn't perturb reservoir choices.
+    let mut alpha_rng = SmallRng::seed_from_u64(params.seed ^ 0xA1FA_A1FA);
     let mut seen = 0_usize;
+    let mut visited = 0_u64;
+    let mut transparent = 0_u64;
 
-    for y in (0..height as usize).step_by(stride) {
-        for x in (0..width as usize).step_by(stride) {
-            let pixel = img.get_pixel(x as u32, y as u32);
-            let [r, g, b] = pixel.0;
-            let lum = LUMA_R * (r as f32) + LUMA_G * (g as f32) + LUMA_B * (b as f32);
-            if lum < min_lum {
+    for y in (crop.y..crop.y + crop.height).step_by(stride) {
+        for x in (crop.x..crop.x + crop.width).step_by(stride) {
+            let GridPixel { pixel, lum, alpha } = fetch(x, y);
+            visited += 1;
+            if alpha < threshold {
+                transparent += 1;
+            }
+            let mut weight = match params.alpha_mode {
+                AlphaMode::Skip if alpha < threshold => 0.0,
+                AlphaMode::Weight => alpha,
+                _ => 1.0,
+            };
+            if let Some(text) = &region.text {
+                weight *= text.weight(x, y);
+            }
+            let keep = weight >= 1.0 || (weight > 0.0 && alpha_rng.gen::<f32>() < weight);
+            if !keep || lum < min_lum {
                 continue;
             }
             seen += 1;
+            let sample_weight = region.grid_weight(x, y, stride as u32);
             if samples.len() < max_samples {
-                samples.push([r, g, b]);
+                samples.push(pixel);
+                if let Some(w) = weights.as_mut() {
+                    w.push(sample_weight);
+                }
             } else {
                 let idx = rng.gen_range(0..seen);
                 if idx < max_samples {
-                    samples[idx] = [r, g, b];
+                    samples[idx] = pixel;
+                    if let Some(w) = weights.as_mut() {
+                        w[idx] = sample_weight;
+                    }
                 }
             }
         }
     }
 
-    samples
+    let transparent_fraction = if visited == 0 {
+        0.0
+    } else {
+        transparent as f32 / visited as f32
+    };
+    GridSamples {
+        samples,
+        weights,
+        transparent_fraction,
+    }
+}
+
+fn sample_pixels(
+    img: &RgbaImage,
+    region: &SampleRegion,
+    params: &SampleParams,
+) -> GridSamples<[u8; 3]> {
+    let matte = params.alpha_mode == AlphaMode::Matte;
+    sample_grid(region, params, |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let mut rgb = [r, g, b];
+        if matte {
+            for (c, m) in rgb.iter_mut().zip(params.matte) {
+                let blended = *c as u32 * a as u32 + m as u32 * (255 - a as u32);
+                *c = ((blended + 127) / 255) as u8;
+            }
+        }
+        let [r, g, b] = rgb;
+        let lum = LUMA_R * (r as f32) + LUMA_G * (g as f32) + LUMA_B * (b as f32);
+        GridPixel {
+            pixel: rgb,
+            lum,
+            alpha: a as f32 / 255.0,
+        }
+    })
+}
+
+fn sample_pixels_f32(
+    img: &Rgba32FImage,
+    region: &SampleRegion,
+    params: &SampleParams,
+) -> GridSamples<[f32; 3]> {
+    let matte = params.alpha_mode == AlphaMode::Matte;
+    let matte_f = params.matte.map(|c| c as f32 / 255.0);
+    sample_grid(region, params, |x, y| {
+        let [r, g, b, a] = img.get_pixel(x, y).0;
+        let alpha = a.clamp(0.0, 1.0);
+        let mut px = [r, g, b];
+        if matte {
+            for (c, m) in px.iter_mut().zip(matte_f) {
+                *c = *c * alpha + m * (1.0 - alpha);
+            }
+        }
+        // Luma gate uses in-gamut values so wide-gamut colors aren't rejected
+        // for carrying negative channels.
+        let [r, g, b] = px.map(|c| c.clamp(0.0, 1.0));
+        let lum = 255.0 * (LUMA_R * r + LUMA_G * g + LUMA_B * b);
+        GridPixel {
+            pixel: px,
+            lum,
+            alpha,
+        }
+    })
 }
 
 #[cfg(test)]
@@ -167,11 +1105,16 @@ mod tests {
             max_samples: 10_000,
             max_dimension: None,
             seed: 42,
+            ..SampleParams::new(tmp.path())
         };
 
         let result = prepare_samples(&params).expect("sample");
         assert!(result.sampled_pixels > 0);
         assert!(result.sampled_pixels <= 25);
+        let lab = result.samples_lab.expect("lab samples");
+        for (rgb, lab) in result.samples.iter().zip(&lab) {
+            assert_eq!(*lab, crate::color::rgb8_to_lab(*rgb));
+        }
         for rgb in result.samples {
             let lum =
                 LUMA_R * (rgb[0] as f32) + LUMA_G * (rgb[1] as f32) + LUMA_B * (rgb[2] as f32);
@@ -193,6 +1136,7 @@ mod tests {
             max_samples: 50,
             max_dimension: None,
             seed: 7,
+            ..SampleParams::new(tmp.path())
         };
         let result = prepare_samples(&params).expect("sample");
         assert_eq!(result.sampled_pixels, 50);
@@ -212,8 +1156,476 @@ mod tests {
             max_samples: 10_000,
             max_dimension: Some(1024),
             seed: 1,
+            ..SampleParams::new(tmp.path())
         };
         let result = prepare_samples(&params).expect("sample");
         assert!(result.width <= 1024 && result.height <= 1024);
     }
+
+    #[test]
+    fn sixteen_bit_images_use_float_path() {
+        let mut img: image::ImageBuffer<Rgb<u16>, Vec<u16>> = image::ImageBuffer::new(16, 16);
+        for pixel in img.pixels_mut() {
+            *pixel = Rgb([40_000, 1_000, 65_535]);
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+
+        let result = prepare_samples(&SampleParams::new(file.path())).expect("sample");
+        assert_eq!(result.bits_per_channel, 16);
+        let floats = result.samples_f32.expect("float samples");
+        let px = floats[0];
+        let lab = result.samples_lab.expect("lab samples");
+        assert_eq!(lab[0], crate::color::srgb_to_lab(px));
+        assert!((px[0] - 40_000.0 / 65_535.0).abs() < 1e-4);
+        assert!((px[1] - 1_000.0 / 65_535.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn display_p3_profile_is_converted_to_srgb() {
+        use image::codecs::png::PngEncoder;
+        use image::ImageEncoder;
+
+        let mut img = RgbImage::new(8, 8);
+        for pixel in img.pixels_mut() {
+            *pixel = Rgb([255, 0, 0]);
+        }
+        let icc = ColorProfile::new_display_p3().encode().expect("encode icc");
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        let mut encoder = PngEncoder::new(std::fs::File::create(file.path()).expect("create"));
+        encoder.set_icc_profile(icc).expect("icc supported");
+        encoder
+            .write_image(img.as_raw(), 8, 8, image::ExtendedColorType::Rgb8)
+            .expect("write png");
+
+        let result = prepare_samples(&SampleParams::new(file.path())).expect("sample");
+        assert!(result.icc_profile_applied);
+        // P3 red lies outside sRGB: red exceeds 1.0 and green/blue go negative.
+        let px = result.samples_f32.expect("float samples")[0];
+        assert!(px[0] > 1.0, "red {}", px[0]);
+        assert!(px[1] < 0.0, "green {}", px[1]);
+
+        let raw = prepare_samples(&SampleParams {
+            color_management: false,
+            ..SampleParams::new(file.path())
+        })
+        .expect("sample");
+        assert!(!raw.icc_profile_applied);
+        assert_eq!(raw.samples[0], [255, 0, 0]);
+    }
+
+    #[test]
+    fn srgb_primaries_with_another_tone_curve_are_not_srgb() {
+        let srgb = ColorProfile::new_srgb();
+        let encoded = srgb.encode().expect("encode icc");
+        assert!(non_srgb_profile(&encoded).is_none());
+
+        let mut linear = srgb.clone();
+        let gamma = moxcms::curve_from_gamma(1.0);
+        linear.red_trc = Some(gamma.clone());
+        linear.green_trc = Some(gamma.clone());
+        linear.blue_trc = Some(gamma);
+        let encoded = linear.encode().expect("encode icc");
+        assert!(non_srgb_profile(&encoded).is_some());
+    }
+
+    fn write_logo_image() -> NamedTempFile {
+        // Left half opaque orange, right half fully transparent black.
+        let mut img = image::RgbaImage::new(16, 16);
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if x < 8 {
+                image::Rgba([240, 120, 20, 255])
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            };
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+        file
+    }
+
+    #[test]
+    fn transparent_pixels_are_skipped_by_default() {
+        let tmp = write_logo_image();
+        let params = SampleParams {
+            stride: 1,
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert!((result.transparent_fraction - 0.5).abs() < 1e-6);
+        assert_eq!(result.sampled_pixels, 128);
+        assert!(result.samples.iter().all(|&px| px == [240, 120, 20]));
+    }
+
+    #[test]
+    fn downscaling_does_not_bleed_transparent_color_into_edges() {
+        let mut img = image::RgbaImage::new(64, 64);
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if x < 29 {
+                image::Rgba([240, 120, 20, 255])
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            };
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+        for filter in [ResizeFilter::Lanczos3, ResizeFilter::Area] {
+            let params = SampleParams {
+                stride: 1,
+                max_dimension: Some(8),
+                resize_filter: filter,
+                alpha_threshold: 1,
+                ..SampleParams::new(file.path())
+            };
+            let result = prepare_samples(&params).expect("sample");
+            for px in &result.samples {
+                let diff = px
+                    .iter()
+                    .zip([240u8, 120, 20])
+                    .map(|(&a, b)| a.abs_diff(b))
+                    .max()
+                    .unwrap();
+                // Lanczos rings a little at the edge even on opaque input.
+                assert!(diff <= 16, "{filter:?} sampled {px:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn matte_composites_transparent_pixels() {
+        let tmp = write_logo_image();
+        let params = SampleParams {
+            stride: 1,
+            alpha_mode: AlphaMode::Matte,
+            matte: [255, 255, 255],
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert_eq!(result.sampled_pixels, 256);
+        assert!((result.transparent_fraction - 0.5).abs() < 1e-6);
+        let white = result.samples.iter().filter(|&&px| px == [255, 255, 255]);
+        assert_eq!(white.count(), 128);
+    }
+
+    #[test]
+    fn weight_mode_keeps_pixels_in_proportion_to_alpha() {
+        let mut img = image::RgbaImage::new(64, 64);
+        for pixel in img.pixels_mut() {
+            *pixel = image::Rgba([50, 100, 150, 64]);
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+        let params = SampleParams {
+            stride: 1,
+            alpha_mode: AlphaMode::Weight,
+            ..SampleParams::new(file.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        let kept = result.sampled_pixels as f32 / 4096.0;
+        assert!((kept - 64.0 / 255.0).abs() < 0.05, "kept {kept}");
+    }
+
+    #[test]
+    fn letterbox_bars_are_cropped() {
+        let mut img = RgbImage::new(40, 40);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if !(6..34).contains(&y) {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([(x * 6) as u8, 120, (y * 6) as u8])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let params = SampleParams {
+            stride: 1,
+            crop_borders: true,
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert_eq!(
+            result.crop,
+            Some(CropRect {
+                x: 0,
+                y: 6,
+                width: 40,
+                height: 28
+            })
+        );
+        assert!(result.samples.iter().all(|&px| px != [0, 0, 0]));
+
+        let full = prepare_samples(&SampleParams {
+            crop_borders: false,
+            ..params
+        })
+        .expect("sample");
+        assert_eq!(full.crop, None);
+        assert_eq!(full.sampled_pixels, 1600);
+    }
+
+    #[test]
+    fn transparent_padding_is_cropped_as_a_border() {
+        // Opaque art inset in padding whose transparent pixels store noise.
+        let mut img = image::RgbaImage::new(40, 40);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if (4..36).contains(&x) && (4..36).contains(&y) {
+                image::Rgba([(x * 6) as u8, 120, (y * 6) as u8, 255])
+            } else {
+                image::Rgba([(x * 37) as u8, (y * 53) as u8, 200, 0])
+            };
+        }
+        let file = Builder::new().suffix(".png").tempfile().expect("temp file");
+        img.save(file.path()).expect("save image");
+        let params = SampleParams {
+            stride: 1,
+            crop_borders: true,
+            ..SampleParams::new(file.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert_eq!(
+            result.crop,
+            Some(CropRect {
+                x: 4,
+                y: 4,
+                width: 32,
+                height: 32
+            })
+        );
+    }
+
+    #[test]
+    fn one_sided_color_block_is_not_a_border() {
+        let mut img = RgbImage::new(40, 40);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if x < 10 {
+                Rgb([30, 60, 120])
+            } else {
+                Rgb([200, (y * 5) as u8, 90])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let params = SampleParams {
+            crop_borders: true,
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert_eq!(result.crop, None);
+    }
+
+    #[test]
+    fn text_suppression_thins_busy_regions() {
+        // Top half: fine black/white stripes standing in for lettering.
+        let mut img = RgbImage::new(64, 64);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if y < 32 {
+                if x % 2 == 0 {
+                    Rgb([255, 255, 255])
+                } else {
+                    Rgb([0, 0, 0])
+                }
+            } else {
+                Rgb([90, 140, 60])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let params = SampleParams {
+            stride: 1,
+            crop_borders: false,
+            suppress_text: true,
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        let plain = result
+            .samples
+            .iter()
+            .filter(|&&px| px == [90, 140, 60])
+            .count();
+        let busy = result.samples.len() - plain;
+        assert_eq!(plain, 2048);
+        assert!(busy < 2048 / 4, "busy samples {busy}");
+    }
+
+    #[test]
+    fn plain_backdrop_around_subject_is_not_cropped() {
+        let mut img = RgbImage::new(64, 64);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let subject = (24..40).contains(&x) && (24..40).contains(&y);
+            *pixel = if subject {
+                Rgb([230, 40, 30])
+            } else {
+                Rgb([70, 80, 90])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let params = SampleParams {
+            crop_borders: true,
+            ..SampleParams::new(tmp.path())
+        };
+        let result = prepare_samples(&params).expect("sample");
+        assert_eq!(result.crop, None);
+    }
+
+    #[test]
+    fn center_weighting_favors_the_middle() {
+        let mut img = RgbImage::new(33, 33);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = Rgb([(x * 7) as u8, (y * 7) as u8, 100]);
+        }
+        let tmp = write_temp_image(&img);
+        let uniform = prepare_samples(&SampleParams {
+            stride: 1,
+            ..SampleParams::new(tmp.path())
+        })
+        .expect("sample");
+        assert!(uniform.weights.is_none());
+
+        let result = prepare_samples(&SampleParams {
+            stride: 1,
+            weighting: WeightingMode::Center,
+            ..SampleParams::new(tmp.path())
+        })
+        .expect("sample");
+        let weights = result.weights.expect("weights");
+        assert_eq!(weights.len(), result.samples.len());
+        let weight_of = |rgb: [u8; 3]| {
+            let idx = result.samples.iter().position(|&px| px == rgb).unwrap();
+            weights[idx]
+        };
+        let center = weight_of([16 * 7, 16 * 7, 100]);
+        let corner = weight_of([0, 0, 100]);
+        assert!(center > 0.9, "center {center}");
+        assert!(corner < 0.1, "corner {corner}");
+    }
+
+    #[test]
+    fn saliency_weighting_favors_distinct_subject() {
+        let mut img = RgbImage::new(64, 64);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let subject = (24..40).contains(&x) && (24..40).contains(&y);
+            *pixel = if subject {
+                Rgb([230, 40, 30])
+            } else {
+                Rgb([70, 80, 90])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let result = prepare_samples(&SampleParams {
+            stride: 1,
+            weighting: WeightingMode::Saliency,
+            ..SampleParams::new(tmp.path())
+        })
+        .expect("sample");
+        let weights = result.weights.expect("weights");
+        let mean_weight = |rgb: [u8; 3]| {
+            let (sum, n) = result
+                .samples
+                .iter()
+                .zip(weights.iter())
+                .filter(|(&px, _)| px == rgb)
+                .fold((0.0, 0), |(s, n), (_, &w)| (s + w, n + 1));
+            sum / n as f32
+        };
+        assert!(mean_weight([230, 40, 30]) > 3.0 * mean_weight([70, 80, 90]));
+    }
+
+    #[test]
+    fn weighting_mode_parses_cli_names() {
+        assert_eq!(
+            "edge-suppressed".parse::<WeightingMode>(),
+            Ok(WeightingMode::EdgeSuppressed)
+        );
+        assert_eq!("Center".parse::<WeightingMode>(), Ok(WeightingMode::Center));
+        assert!("blur".parse::<WeightingMode>().is_err());
+    }
+
+    #[test]
+    fn jpeg_decodes_scaled_to_target_size() {
+        let mut img = RgbImage::new(1024, 768);
+        for (x, _, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if x < 512 {
+                Rgb([200, 40, 40])
+            } else {
+                Rgb([30, 60, 200])
+            };
+        }
+        let file = Builder::new().suffix(".jpg").tempfile().expect("temp file");
+        img.save(file.path()).expect("save jpeg");
+
+        let result = prepare_samples(&SampleParams {
+            stride: 1,
+            crop_borders: false,
+            target_size: Some(100),
+            ..SampleParams::new(file.path())
+        })
+        .expect("sample");
+        assert_eq!((result.width, result.height), (100, 75));
+        let near = |a: [u8; 3], b: [u8; 3]| a.iter().zip(b).all(|(&x, y)| x.abs_diff(y) <= 12);
+        assert!(near(result.samples[0], [200, 40, 40]));
+        assert!(near(*result.samples.last().unwrap(), [30, 60, 200]));
+    }
+
+    #[test]
+    fn jpeg_max_dimension_alone_decodes_at_full_size() {
+        let mut img = RgbImage::new(512, 384);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = Rgb([(x / 2) as u8, (y / 2) as u8, 90]);
+        }
+        let file = Builder::new().suffix(".jpg").tempfile().expect("temp file");
+        img.save(file.path()).expect("save jpeg");
+
+        let result = prepare_samples(&SampleParams {
+            stride: 1,
+            max_dimension: Some(64),
+            ..SampleParams::new(file.path())
+        })
+        .expect("sample");
+        let full = image::open(file.path()).expect("decode").to_rgba8();
+        let expected = image::imageops::resize(&full, 64, 48, FilterType::Lanczos3);
+        let expected: Vec<[u8; 3]> = expected
+            .pixels()
+            .map(|px| [px.0[0], px.0[1], px.0[2]])
+            .collect();
+        assert_eq!(result.samples, expected);
+    }
+
+    #[test]
+    fn area_filter_averages_source_blocks() {
+        let mut img = RgbImage::new(64, 64);
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = if (x + y) % 2 == 0 {
+                Rgb([0, 0, 0])
+            } else {
+                Rgb([255, 255, 255])
+            };
+        }
+        let tmp = write_temp_image(&img);
+        let result = prepare_samples(&SampleParams {
+            stride: 1,
+            crop_borders: false,
+            target_size: Some(16),
+            resize_filter: ResizeFilter::Area,
+            ..SampleParams::new(tmp.path())
+        })
+        .expect("sample");
+        assert_eq!((result.width, result.height), (16, 16));
+        assert!(result.samples.iter().all(|&px| px == [128, 128, 128]));
+    }
+
+    #[test]
+    fn sample_params_round_trip_through_json() {
+        let params = SampleParams {
+            target_size: Some(256),
+            alpha_mode: AlphaMode::Matte,
+            matte: [10, 20, 30],
+            weighting: WeightingMode::EdgeSuppressed,
+            ..SampleParams::new("cover.jpg")
+        };
+        let json = serde_json::to_string(&params).expect("serialize");
+        assert!(json.contains("\"weighting\":\"edge-suppressed\""));
+        let back: SampleParams = serde_json::from_str(&json).expect("deserialize");
+        assert_eq!(back.path, params.path);
+        assert_eq!(back.target_size, Some(256));
+        assert_eq!(back.alpha_mode, AlphaMode::Matte);
+        assert_eq!(back.matte, [10, 20, 30]);
+        assert_eq!(back.weighting, WeightingMode::EdgeSuppressed);
+    }
 }
@@ -1,5 +1,8 @@
+use std::collections::HashMap;
+
 use rand::{rngs::SmallRng, Rng, SeedableRng};
 use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 #[cfg(feature = "simd")]
 use wide::f32x4;
 
@@ -11,6 +14,69 @@ pub struct KMeansConfig {
     pub seed: u64,
     pub warm_start: Option<Vec<[f32; 3]>>,
     pub mini_batch: Option<usize>,
+    /// Component holding a hue angle in degrees (e.g. 0 for HSL/HSV). Distances on
+    /// that axis wrap at 360° and centroids take the circular mean of the hues.
+    pub hue_axis: Option<usize>,
+    pub algorithm: KMeansAlgorithm,
+    /// Seeding strategy when no warm start is given.
+    pub init: KMeansInit,
+    /// Independent restarts with derived seeds; the lowest-inertia run wins.
+    pub n_init: usize,
+}
+
+/// Assignment strategy for full-batch iterations. Both produce the same
+/// assignments; mini-batch runs always use Lloyd.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KMeansAlgorithm {
+    /// Scan every centroid for every point.
+    Lloyd,
+    /// Hamerly's bounds: one upper and one lower bound per point plus the
+    /// half-distance to each centroid's nearest neighbour skip most scans.
+    #[default]
+    Hamerly,
+}
+
+impl std::str::FromStr for KMeansAlgorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "lloyd" => Ok(KMeansAlgorithm::Lloyd),
+            "hamerly" => Ok(KMeansAlgorithm::Hamerly),
+            other => Err(format!(
+                "unknown k-means algorithm '{other}' (expected lloyd or hamerly)"
+            )),
+        }
+    }
+}
+
+/// Seeding strategy for the initial centroids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum KMeansInit {
+    /// Sequential D² sampling: one pass over the data per centroid.
+    #[default]
+    #[serde(rename = "kmeans++")]
+    PlusPlus,
+    /// k-means|| (Bahmani et al.): a few parallel oversampling rounds pick
+    /// about `2k` candidates per round, which are then reduced to `k` with
+    /// weighted k-means++.
+    #[serde(rename = "parallel")]
+    Parallel,
+}
+
+impl std::str::FromStr for KMeansInit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "kmeans++" | "k-means++" | "plus-plus" | "++" => Ok(KMeansInit::PlusPlus),
+            "kmeans||" | "k-means||" | "parallel" | "||" => Ok(KMeansInit::Parallel),
+            other => Err(format!(
+                "unknown k-means init '{other}' (expected kmeans++ or parallel)"
+            )),
+        }
+    }
 }
 
 impl Default for KMeansConfig {
@@ -22,15 +88,24 @@ impl Default for KMeansConfig {
             seed: 1,
             warm_start: None,
             mini_batch: None,
+            hue_axis: None,
+            algorithm: KMeansAlgorithm::default(),
+            init: KMeansInit::PlusPlus,
+            n_init: 1,
         }
     }
 }
 
+const HUE_PERIOD: f32 = 360.0;
+
 #[derive(Debug, Clone)]
 pub struct KMeansResult {
     pub centroids: Vec<[f32; 3]>,
     pub counts: Vec<usize>,
+    /// Summed point weight per cluster (equal to `counts` for unweighted data).
+    pub weights: Vec<f32>,
     pub iterations: usize,
+    /// Sum of squared distances to the assigned centroid, scaled by point weight.
     pub inertia: f32,
 }
 
@@ -39,6 +114,9 @@ pub struct PointsSoa {
     px: Vec<f32>,
     py: Vec<f32>,
     pz: Vec<f32>,
+    weights: Option<Vec<f32>>,
+    /// How many input samples each point stands for (histogram buckets).
+    sample_counts: Option<Vec<usize>>,
 }
 
 impl PointsSoa {
@@ -51,13 +129,51 @@ impl PointsSoa {
             py.push(p[1]);
             pz.push(p[2]);
         }
-        Self { px, py, pz }
+        Self {
+            px,
+            py,
+            pz,
+            weights: None,
+            sample_counts: None,
+        }
+    }
+
+    /// Points with a non-negative weight each; centroids become weighted means.
+    pub fn from_weighted_points(points: &[[f32; 3]], weights: &[f32]) -> Self {
+        assert_eq!(points.len(), weights.len(), "one weight per point");
+        Self {
+            weights: Some(weights.to_vec()),
+            ..Self::from_points(points)
+        }
+    }
+
+    /// Weighted bucket means that count as their samples in the reported
+    /// per-cluster counts.
+    pub fn from_prequantized(q: &Prequantized) -> Self {
+        Self {
+            sample_counts: Some(q.sample_counts.clone()),
+            ..Self::from_weighted_points(&q.points, &q.weights)
+        }
+    }
+
+    #[inline]
+    pub fn weight(&self, idx: usize) -> f32 {
+        self.weights.as_ref().map_or(1.0, |w| w[idx])
+    }
+
+    #[inline]
+    fn sample_count(&self, idx: usize) -> usize {
+        self.sample_counts.as_ref().map_or(1, |n| n[idx])
     }
 
     pub fn len(&self) -> usize {
         self.px.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.px.is_empty()
+    }
+
     pub fn to_vec(&self) -> Vec<[f32; 3]> {
         self.px
             .iter()
@@ -124,60 +240,233 @@ impl CentroidsSoa {
     }
 }
 
+/// Samples collapsed into histogram buckets, ready for weighted clustering.
+#[derive(Debug, Clone)]
+pub struct Prequantized {
+    /// Weighted mean of the points that fell into each bucket.
+    pub points: Vec<[f32; 3]>,
+    /// Summed sample weight per bucket.
+    pub weights: Vec<f32>,
+    /// Number of samples per bucket.
+    pub sample_counts: Vec<usize>,
+}
+
+impl Prequantized {
+    pub fn unique_buckets(&self) -> usize {
+        self.points.len()
+    }
+}
+
+/// Bucket samples by the top `bits` of each 8-bit RGB channel and merge each
+/// bucket into one weighted point. `points` are the samples already encoded in
+/// the clustering space (one per `rgb` entry); buckets keep first-seen order so
+/// the result is deterministic. Buckets with zero total weight are dropped.
+pub fn prequantize(
+    rgb: &[[u8; 3]],
+    points: &[[f32; 3]],
+    weights: Option<&[f32]>,
+    bits: u8,
+    hue_axis: Option<usize>,
+) -> Prequantized {
+    assert!((1..=8).contains(&bits), "bits must be in 1..=8");
+    assert_eq!(rgb.len(), points.len(), "one point per RGB sample");
+    let shift = 8 - bits;
+
+    #[derive(Default)]
+    struct Bucket {
+        sum: [f64; 3],
+        sum_sin: f64,
+        sum_cos: f64,
+        weight: f64,
+        count: usize,
+    }
+
+    let mut index: HashMap<u32, usize> = HashMap::new();
+    let mut buckets: Vec<Bucket> = Vec::new();
+    for (i, (px, point)) in rgb.iter().zip(points.iter()).enumerate() {
+        let key = ((px[0] >> shift) as u32) << 16
+            | ((px[1] >> shift) as u32) << 8
+            | (px[2] >> shift) as u32;
+        let slot = *index.entry(key).or_insert_with(|| {
+            buckets.push(Bucket::default());
+            buckets.len() - 1
+        });
+        let w = weights.map_or(1.0, |w| w[i]) as f64;
+        let bucket = &mut buckets[slot];
+        for (acc, &c) in bucket.sum.iter_mut().zip(point.iter()) {
+            *acc += w * c as f64;
+        }
+        if let Some(axis) = hue_axis {
+            let (sin_h, cos_h) = (point[axis] as f64).to_radians().sin_cos();
+            bucket.sum_sin += w * sin_h;
+            bucket.sum_cos += w * cos_h;
+        }
+        bucket.weight += w;
+        bucket.count += 1;
+    }
+
+    let mut out = Prequantized {
+        points: Vec::with_capacity(buckets.len()),
+        weights: Vec::with_capacity(buckets.len()),
+        sample_counts: Vec::with_capacity(buckets.len()),
+    };
+    // Buckets whose samples all carry zero weight cannot pull a centroid
+    for bucket in buckets.into_iter().filter(|b| b.weight > 0.0) {
+        let mut mean = bucket.sum.map(|s| (s / bucket.weight) as f32);
+        if let Some(axis) = hue_axis {
+            mean[axis] =
+                circular_mean_degrees(bucket.sum_sin as f32, bucket.sum_cos as f32, mean[axis]);
+        }
+        out.points.push(mean);
+        out.weights.push(bucket.weight as f32);
+        out.sample_counts.push(bucket.count);
+    }
+    out
+}
+
+/// Index of the nearest centroid for every point.
+pub fn assign_points(
+    points: &[[f32; 3]],
+    centroids: &[[f32; 3]],
+    hue_axis: Option<usize>,
+) -> Vec<usize> {
+    let centroids = CentroidsSoa::from_vec(centroids);
+    points
+        .iter()
+        .map(|&[px, py, pz]| match hue_axis {
+            Some(axis) => best_centroid_hue(px, py, pz, &centroids, axis).0,
+            None => best_centroid(px, py, pz, &centroids).0,
+        })
+        .collect()
+}
+
 pub fn run_kmeans(points: &[[f32; 3]], cfg: &KMeansConfig) -> KMeansResult {
     let dataset = PointsSoa::from_points(points);
     run_kmeans_soa(&dataset, cfg)
 }
 
+pub fn run_kmeans_weighted(
+    points: &[[f32; 3]],
+    weights: &[f32],
+    cfg: &KMeansConfig,
+) -> KMeansResult {
+    let dataset = PointsSoa::from_weighted_points(points, weights);
+    run_kmeans_soa(&dataset, cfg)
+}
+
+/// Cluster histogram buckets; `counts` in the result are sample counts, taken
+/// from the same assignment pass that produced the centroids.
+pub fn run_kmeans_prequantized(q: &Prequantized, cfg: &KMeansConfig) -> KMeansResult {
+    let dataset = PointsSoa::from_prequantized(q);
+    run_kmeans_soa(&dataset, cfg)
+}
+
 pub fn run_kmeans_soa(dataset: &PointsSoa, cfg: &KMeansConfig) -> KMeansResult {
     assert!(cfg.k > 0, "k must be > 0");
     assert!(dataset.len() >= cfg.k, "points must be >= k");
 
-    let mut rng = SmallRng::seed_from_u64(cfg.seed);
+    // A warm start fixes the seeds, so restarts would only repeat the same run
+    let runs = if cfg.warm_start.is_some() {
+        1
+    } else {
+        cfg.n_init.max(1)
+    };
+    let mut best = run_once(dataset, cfg, cfg.seed);
+    for run in 1..runs {
+        let seed = cfg.seed ^ (run as u64).wrapping_mul(RESTART_SEED_STEP);
+        let candidate = run_once(dataset, cfg, seed);
+        // Strictly lower keeps the earliest run on ties
+        if candidate.inertia < best.inertia {
+            best = candidate;
+        }
+    }
+    best
+}
+
+/// Odd 64-bit constant (2^64 / golden ratio) used to spread derived seeds.
+const RESTART_SEED_STEP: u64 = 0x9E37_79B9_7F4A_7C15;
+
+fn run_once(dataset: &PointsSoa, cfg: &KMeansConfig, seed: u64) -> KMeansResult {
+    let mut rng = SmallRng::seed_from_u64(seed);
     let mut centroids = if let Some(warm) = &cfg.warm_start {
         assert_eq!(warm.len(), cfg.k, "warm_start length must equal k");
         CentroidsSoa::from_vec(warm)
     } else {
-        kmeans_plus_plus(dataset, cfg.k, &mut rng)
+        match cfg.init {
+            KMeansInit::PlusPlus => kmeans_plus_plus(dataset, cfg.k, cfg.hue_axis, &mut rng),
+            KMeansInit::Parallel => kmeans_parallel(dataset, cfg.k, cfg.hue_axis, &mut rng),
+        }
     };
 
     let mut counts = vec![0usize; cfg.k];
+    let mut weights = vec![0.0f32; cfg.k];
     let mut iterations = 0;
     let mut inertia = 0.0;
+    let mut bounds = match cfg.algorithm {
+        KMeansAlgorithm::Hamerly if cfg.mini_batch.is_none() => {
+            Some(HamerlyBounds::new(dataset, &centroids, cfg.hue_axis))
+        }
+        _ => None,
+    };
 
     while iterations < cfg.max_iters {
         let mini_batch_storage = if let Some(batch_size) = cfg.mini_batch {
             if batch_size > 0 && batch_size < dataset.len() {
-                Some(sample_batch(&dataset, batch_size, &mut rng))
+                Some(sample_batch(dataset, batch_size, &mut rng))
             } else {
                 None
             }
         } else {
             None
         };
-        let working = mini_batch_storage.as_ref().unwrap_or(&dataset);
+        let working = mini_batch_storage.as_ref().unwrap_or(dataset);
 
-        let (partials, step_inertia) = assignment_step(working, &centroids);
+        let (partials, step_inertia) = match bounds.as_mut() {
+            Some(bounds) => {
+                bounds.assign(working, &centroids, cfg.hue_axis);
+                let assigned = &bounds.assigned;
+                assignment_step(working, &centroids, cfg.hue_axis, |idx, px, py, pz| {
+                    let a = assigned[idx];
+                    let (cx, cy, cz) = centroids.component_tuple(a);
+                    (a, squared_distance(px, py, pz, cx, cy, cz, cfg.hue_axis))
+                })
+            }
+            None => assignment_step(working, &centroids, cfg.hue_axis, |_, px, py, pz| match cfg
+                .hue_axis
+            {
+                Some(axis) => best_centroid_hue(px, py, pz, &centroids, axis),
+                None => best_centroid(px, py, pz, &centroids),
+            }),
+        };
         inertia = step_inertia;
+        let previous = bounds.as_ref().map(|_| centroids.clone());
 
         counts.fill(0);
+        weights.fill(0.0);
         let mut shift = 0.0;
         for (idx, part) in partials.into_iter().enumerate() {
-            if part.count == 0 {
+            if part.count == 0 || part.weight <= 0.0 {
                 let rand_idx = rng.gen_range(0..dataset.len());
-                centroids.set_from_soa(idx, &dataset, rand_idx);
+                centroids.set_from_soa(idx, dataset, rand_idx);
                 continue;
             }
-            let inv = 1.0 / part.count as f32;
-            let nx = part.sum_x * inv;
-            let ny = part.sum_y * inv;
-            let nz = part.sum_z * inv;
+            let inv = 1.0 / part.weight;
+            let mut next = [part.sum_x * inv, part.sum_y * inv, part.sum_z * inv];
+            if let Some(axis) = cfg.hue_axis {
+                next[axis] = circular_mean_degrees(part.sum_sin, part.sum_cos, next[axis]);
+            }
+            let [nx, ny, nz] = next;
             let (ox, oy, oz) = centroids.component_tuple(idx);
-            shift += squared_distance_components(ox, oy, oz, nx, ny, nz);
+            shift += squared_distance(ox, oy, oz, nx, ny, nz, cfg.hue_axis);
             centroids.cx[idx] = nx;
             centroids.cy[idx] = ny;
             centroids.cz[idx] = nz;
             counts[idx] = part.count;
+            weights[idx] = part.weight;
+        }
+
+        if let (Some(bounds), Some(previous)) = (bounds.as_mut(), previous) {
+            bounds.centroids_moved(&previous, &centroids, cfg.hue_axis);
         }
 
         iterations += 1;
@@ -189,6 +478,7 @@ pub fn run_kmeans_soa(dataset: &PointsSoa, cfg: &KMeansConfig) -> KMeansResult {
     KMeansResult {
         centroids: centroids.to_vec(),
         counts,
+        weights,
         iterations,
         inertia,
     }
@@ -199,14 +489,25 @@ struct ClusterPartial {
     sum_x: f32,
     sum_y: f32,
     sum_z: f32,
+    sum_sin: f32,
+    sum_cos: f32,
+    weight: f32,
     count: usize,
 }
 
-fn assignment_step(points: &PointsSoa, centroids: &CentroidsSoa) -> (Vec<ClusterPartial>, f32) {
+/// Accumulate per-cluster sums for the centroid update. `assign` maps a point
+/// (index and components) to its centroid and squared distance; the chunked
+/// summation order is fixed so every assignment strategy yields identical sums.
+fn assignment_step(
+    points: &PointsSoa,
+    centroids: &CentroidsSoa,
+    hue_axis: Option<usize>,
+    assign: impl Fn(usize, f32, f32, f32) -> (usize, f32) + Sync,
+) -> (Vec<ClusterPartial>, f32) {
     let k = centroids.len();
     let chunk_size = 1024usize.max(k);
     let total_len = points.len();
-    let chunk_count = (total_len + chunk_size - 1) / chunk_size;
+    let chunk_count = total_len.div_ceil(chunk_size);
 
     let chunk_partials: Vec<(Vec<ClusterPartial>, f32)> = (0..chunk_count)
         .into_par_iter()
@@ -217,13 +518,20 @@ fn assignment_step(points: &PointsSoa, centroids: &CentroidsSoa) -> (Vec<Cluster
             let mut inertia = 0.0f32;
             for idx in start..end {
                 let (px, py, pz) = points.component_tuple(idx);
-                let (best_idx, best_dist) = best_centroid(px, py, pz, centroids);
+                let (best_idx, best_dist) = assign(idx, px, py, pz);
+                let w = points.weight(idx);
                 let entry = &mut partials[best_idx];
-                entry.sum_x += px;
-                entry.sum_y += py;
-                entry.sum_z += pz;
-                entry.count += 1;
-                inertia += best_dist;
+                entry.sum_x += w * px;
+                entry.sum_y += w * py;
+                entry.sum_z += w * pz;
+                if let Some(axis) = hue_axis {
+                    let (sin_h, cos_h) = [px, py, pz][axis].to_radians().sin_cos();
+                    entry.sum_sin += w * sin_h;
+                    entry.sum_cos += w * cos_h;
+                }
+                entry.weight += w;
+                entry.count += points.sample_count(idx);
+                inertia += w * best_dist;
             }
             (partials, inertia)
         })
@@ -234,6 +542,9 @@ fn assignment_step(points: &PointsSoa, centroids: &CentroidsSoa) -> (Vec<Cluster
     let mut acc_x: Vec<f64> = vec![0.0; k];
     let mut acc_y: Vec<f64> = vec![0.0; k];
     let mut acc_z: Vec<f64> = vec![0.0; k];
+    let mut acc_sin: Vec<f64> = vec![0.0; k];
+    let mut acc_cos: Vec<f64> = vec![0.0; k];
+    let mut acc_w: Vec<f64> = vec![0.0; k];
     let mut acc_n: Vec<usize> = vec![0; k];
     let mut total_inertia = 0.0f32;
     for (chunk_partials, chunk_inertia) in chunk_partials {
@@ -241,6 +552,9 @@ fn assignment_step(points: &PointsSoa, centroids: &CentroidsSoa) -> (Vec<Cluster
             acc_x[idx] += chunk_partials[idx].sum_x as f64;
             acc_y[idx] += chunk_partials[idx].sum_y as f64;
             acc_z[idx] += chunk_partials[idx].sum_z as f64;
+            acc_sin[idx] += chunk_partials[idx].sum_sin as f64;
+            acc_cos[idx] += chunk_partials[idx].sum_cos as f64;
+            acc_w[idx] += chunk_partials[idx].weight as f64;
             acc_n[idx] += chunk_partials[idx].count;
         }
         total_inertia += chunk_inertia;
@@ -249,21 +563,171 @@ fn assignment_step(points: &PointsSoa, centroids: &CentroidsSoa) -> (Vec<Cluster
         totals[idx].sum_x = acc_x[idx] as f32;
         totals[idx].sum_y = acc_y[idx] as f32;
         totals[idx].sum_z = acc_z[idx] as f32;
+        totals[idx].sum_sin = acc_sin[idx] as f32;
+        totals[idx].sum_cos = acc_cos[idx] as f32;
+        totals[idx].weight = acc_w[idx] as f32;
         totals[idx].count = acc_n[idx];
     }
 
     (totals, total_inertia)
 }
 
+/// Per-point Hamerly bounds (Hamerly 2010, "Making k-means even faster").
+/// Bounds are Euclidean distances (not squared) so they obey the triangle
+/// inequality; a wrapped hue axis keeps the metric property.
+struct HamerlyBounds {
+    assigned: Vec<usize>,
+    upper: Vec<f32>,
+    lower: Vec<f32>,
+}
+
+impl HamerlyBounds {
+    fn new(points: &PointsSoa, centroids: &CentroidsSoa, hue_axis: Option<usize>) -> Self {
+        let (assigned, (upper, lower)): (Vec<usize>, (Vec<f32>, Vec<f32>)) = (0..points.len())
+            .into_par_iter()
+            .map(|i| {
+                let (px, py, pz) = points.component_tuple(i);
+                let (a, d1, d2) = best_two_centroids(px, py, pz, centroids, hue_axis);
+                (a, (d1, d2))
+            })
+            .unzip();
+        Self {
+            assigned,
+            upper,
+            lower,
+        }
+    }
+
+    fn assign(&mut self, points: &PointsSoa, centroids: &CentroidsSoa, hue_axis: Option<usize>) {
+        let k = centroids.len();
+        // Half the distance from each centroid to its nearest other centroid
+        let half_gap: Vec<f32> = (0..k)
+            .map(|j| {
+                let (jx, jy, jz) = centroids.component_tuple(j);
+                (0..k)
+                    .filter(|&o| o != j)
+                    .map(|o| {
+                        let (ox, oy, oz) = centroids.component_tuple(o);
+                        squared_distance(jx, jy, jz, ox, oy, oz, hue_axis).sqrt()
+                    })
+                    .fold(f32::MAX, f32::min)
+                    * 0.5
+            })
+            .collect();
+
+        self.assigned
+            .par_iter_mut()
+            .zip(self.upper.par_iter_mut())
+            .zip(self.lower.par_iter_mut())
+            .enumerate()
+            .for_each(|(i, ((assigned, upper), lower))| {
+                let bound = half_gap[*assigned].max(*lower);
+                if prunes(*upper, bound) {
+                    return;
+                }
+                let (px, py, pz) = points.component_tuple(i);
+                let (cx, cy, cz) = centroids.component_tuple(*assigned);
+                *upper = squared_distance(px, py, pz, cx, cy, cz, hue_axis).sqrt();
+                if prunes(*upper, bound) {
+                    return;
+                }
+                let (a, d1, d2) = best_two_centroids(px, py, pz, centroids, hue_axis);
+                *assigned = a;
+                *upper = d1;
+                *lower = d2;
+            });
+    }
+
+    /// Loosen the bounds by how far each centroid moved.
+    fn centroids_moved(
+        &mut self,
+        previous: &CentroidsSoa,
+        current: &CentroidsSoa,
+        hue_axis: Option<usize>,
+    ) {
+        let moved: Vec<f32> = (0..current.len())
+            .map(|j| {
+                let (ox, oy, oz) = previous.component_tuple(j);
+                let (nx, ny, nz) = current.component_tuple(j);
+                squared_distance(ox, oy, oz, nx, ny, nz, hue_axis).sqrt()
+            })
+            .collect();
+        let max_moved = moved.iter().cloned().fold(0.0f32, f32::max);
+        self.upper
+            .par_iter_mut()
+            .zip(self.lower.par_iter_mut())
+            .zip(self.assigned.par_iter())
+            .for_each(|((upper, lower), &a)| {
+                *upper += moved[a];
+                *lower -= max_moved;
+            });
+    }
+}
+
+/// Whether an upper bound proves the assigned centroid is still strictly the
+/// nearest. The margin absorbs f32 round-off in the accumulated bounds so
+/// pruning never changes an assignment the full scan would make.
+#[inline]
+fn prunes(upper: f32, bound: f32) -> bool {
+    upper + 1e-5 * (1.0 + upper) < bound
+}
+
+/// Nearest centroid (first index on ties, like `best_centroid`) plus the
+/// Euclidean distances to the nearest and second-nearest centroids.
+fn best_two_centroids(
+    px: f32,
+    py: f32,
+    pz: f32,
+    centroids: &CentroidsSoa,
+    hue_axis: Option<usize>,
+) -> (usize, f32, f32) {
+    let mut best_idx = 0usize;
+    let mut best = f32::MAX;
+    let mut second = f32::MAX;
+    for i in 0..centroids.len() {
+        let d = squared_distance(
+            px,
+            py,
+            pz,
+            centroids.cx[i],
+            centroids.cy[i],
+            centroids.cz[i],
+            hue_axis,
+        );
+        if d < best {
+            second = best;
+            best = d;
+            best_idx = i;
+        } else if d < second {
+            second = d;
+        }
+    }
+    (best_idx, best.sqrt(), second.sqrt())
+}
+
 #[inline]
 fn best_centroid(px: f32, py: f32, pz: f32, centroids: &CentroidsSoa) -> (usize, f32) {
     #[cfg(feature = "simd")]
     {
-        return best_centroid_simd(px, py, pz, centroids);
+        #[cfg(target_arch = "x86_64")]
+        if crate::simd::has_avx2() {
+            // SAFETY: AVX2 support was checked at runtime; SoA columns share a length.
+            return unsafe {
+                crate::simd::avx2::nearest_centroid(
+                    px,
+                    py,
+                    pz,
+                    &centroids.cx,
+                    &centroids.cy,
+                    &centroids.cz,
+                )
+            };
+        }
+        best_centroid_simd(px, py, pz, centroids)
     }
     #[cfg(not(feature = "simd"))]
     {
-        return best_centroid_scalar(px, py, pz, centroids);
+        best_centroid_scalar(px, py, pz, centroids)
     }
 }
 
@@ -303,8 +767,7 @@ fn best_centroid_simd(px: f32, py: f32, pz: f32, centroids: &CentroidsSoa) -> (u
         let dz = pz_v - cz;
         let dist = dx * dx + dy * dy + dz * dz;
         let dist_arr: [f32; LANES] = dist.into();
-        for lane in 0..LANES {
-            let d = dist_arr[lane];
+        for (lane, &d) in dist_arr.iter().enumerate() {
             if d < best_dist {
                 best_dist = d;
                 best_idx = idx + lane;
@@ -331,7 +794,7 @@ fn best_centroid_simd(px: f32, py: f32, pz: f32, centroids: &CentroidsSoa) -> (u
     (best_idx, best_dist)
 }
 
-#[cfg(not(feature = "simd"))]
+#[cfg_attr(all(feature = "simd", not(test)), allow(dead_code))]
 fn best_centroid_scalar(px: f32, py: f32, pz: f32, centroids: &CentroidsSoa) -> (usize, f32) {
     let mut best_idx = 0usize;
     let mut best_dist = f32::MAX;
@@ -352,25 +815,68 @@ fn best_centroid_scalar(px: f32, py: f32, pz: f32, centroids: &CentroidsSoa) ->
     (best_idx, best_dist)
 }
 
-fn kmeans_plus_plus(points: &PointsSoa, k: usize, rng: &mut SmallRng) -> CentroidsSoa {
+fn best_centroid_hue(
+    px: f32,
+    py: f32,
+    pz: f32,
+    centroids: &CentroidsSoa,
+    hue_axis: usize,
+) -> (usize, f32) {
+    let mut best_idx = 0usize;
+    let mut best_dist = f32::MAX;
+    for i in 0..centroids.len() {
+        let d = squared_distance_hue(
+            px,
+            py,
+            pz,
+            centroids.cx[i],
+            centroids.cy[i],
+            centroids.cz[i],
+            hue_axis,
+        );
+        if d < best_dist {
+            best_dist = d;
+            best_idx = i;
+        }
+    }
+    (best_idx, best_dist)
+}
+
+fn kmeans_plus_plus(
+    points: &PointsSoa,
+    k: usize,
+    hue_axis: Option<usize>,
+    rng: &mut SmallRng,
+) -> CentroidsSoa {
     let n = points.len();
     let mut centroids = CentroidsSoa::with_len(k);
     let mut chosen_flags = vec![false; n];
-    let first_idx = rng.gen_range(0..n);
+    let first_idx = match &points.weights {
+        Some(weights) => {
+            let total: f32 = weights.iter().sum();
+            pick_weighted(weights.iter().copied(), total, &chosen_flags, rng)
+                .unwrap_or_else(|| rng.gen_range(0..n))
+        }
+        None => rng.gen_range(0..n),
+    };
     centroids.set_from_soa(0, points, first_idx);
     chosen_flags[first_idx] = true;
 
-    let mut distances = vec![0.0f32; n];
-    for i in 0..n {
-        distances[i] = squared_distance_components(
-            points.px[i],
-            points.py[i],
-            points.pz[i],
-            centroids.cx[0],
-            centroids.cy[0],
-            centroids.cz[0],
-        );
-    }
+    // D² scaled by point weight, so heavy points are proportionally likelier seeds
+    let mut distances: Vec<f32> = (0..n)
+        .map(|i| {
+            points.weight(i)
+                * squared_distance(
+                    points.px[i],
+                    points.py[i],
+                    points.pz[i],
+                    centroids.cx[0],
+                    centroids.cy[0],
+                    centroids.cz[0],
+                    hue_axis,
+                )
+        })
+        .collect();
 
     for centroid_idx in 1..k {
         let mut sum = 0.0;
@@ -380,55 +886,258 @@ fn kmeans_plus_plus(points: &PointsSoa, k: usize, rng: &mut SmallRng) -> Centroi
             }
         }
 
-        let chosen_idx = if sum == 0.0 {
-            // Fallback: pick a random unchosen point
-            let mut idx;
-            loop {
-                idx = rng.gen_range(0..n);
-                if !chosen_flags[idx] {
-                    break;
-                }
-            }
-            idx
-        } else {
-            let mut target = rng.gen::<f32>() * sum;
-            let mut idx = 0;
-            for (i, dist) in distances.iter().enumerate() {
-                if chosen_flags[i] {
-                    continue;
+        let chosen_idx = pick_weighted(distances.iter().copied(), sum, &chosen_flags, rng)
+            .unwrap_or_else(|| {
+                // Fallback: pick a random unchosen point
+                let mut idx;
+                loop {
+                    idx = rng.gen_range(0..n);
+                    if !chosen_flags[idx] {
+                        break;
+                    }
                 }
-                target -= *dist;
-                if target <= 0.0 {
-                    idx = i;
-                    break;
-                }
-            }
-            idx
-        };
+                idx
+            });
 
         centroids.set_from_soa(centroid_idx, points, chosen_idx);
         chosen_flags[chosen_idx] = true;
 
-        for i in 0..n {
-            if chosen_flags[i] {
-                distances[i] = 0.0;
-                continue;
+        let (cx, cy, cz) = centroids.component_tuple(centroid_idx);
+        update_min_distances(
+            points,
+            [cx, cy, cz],
+            hue_axis,
+            &chosen_flags,
+            &mut distances,
+        );
+    }
+
+    centroids
+}
+
+/// Oversampling rounds for k-means||. Bahmani et al. report diminishing returns
+/// past a handful; two (Spark MLlib's default) already beat k-means++ seeds.
+const PARALLEL_INIT_ROUNDS: usize = 2;
+/// Points per independently seeded RNG stream in k-means|| rounds. Fixed so the
+/// draws do not depend on how rayon splits the work.
+const PARALLEL_INIT_CHUNK: usize = 4096;
+
+fn kmeans_parallel(
+    points: &PointsSoa,
+    k: usize,
+    hue_axis: Option<usize>,
+    rng: &mut SmallRng,
+) -> CentroidsSoa {
+    let n = points.len();
+    // With no centers yet there is no D² to weight by: as in the paper, the
+    // first candidate is drawn uniformly (by point weight when weighted).
+    let first_idx = match &points.weights {
+        Some(weights) => {
+            let total: f32 = weights.iter().sum();
+            pick_weighted(weights.iter().copied(), total, &vec![false; n], rng)
+                .unwrap_or_else(|| rng.gen_range(0..n))
+        }
+        None => rng.gen_range(0..n),
+    };
+    let mut candidates = vec![first_idx];
+    let mut distances = vec![f32::MAX; n];
+    update_nearest_candidate(points, &candidates, hue_axis, &mut distances);
+
+    let oversample = 2.0 * k as f64;
+    let stream_seed: u64 = rng.gen();
+    for round in 0..PARALLEL_INIT_ROUNDS {
+        let cost = chunked_sum(&distances);
+        if cost <= 0.0 {
+            break;
+        }
+        let round_seed = stream_seed ^ (round as u64 + 1).wrapping_mul(RESTART_SEED_STEP);
+        let picked: Vec<usize> = distances
+            .par_chunks(PARALLEL_INIT_CHUNK)
+            .enumerate()
+            .map(|(chunk_idx, chunk)| {
+                let mut chunk_rng = SmallRng::seed_from_u64(round_seed ^ chunk_idx as u64);
+                let base = chunk_idx * PARALLEL_INIT_CHUNK;
+                chunk
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(offset, &d)| {
+                        let p = oversample * d as f64 / cost;
+                        // Draw for every point so the stream stays aligned
+                        let draw = chunk_rng.gen::<f64>();
+                        (d > 0.0 && draw < p).then_some(base + offset)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .flatten()
+            .collect();
+        if picked.is_empty() {
+            continue;
+        }
+        update_nearest_candidate(points, &picked, hue_axis, &mut distances);
+        candidates.extend(picked);
+    }
+
+    if candidates.len() <= k {
+        // Too few distinct points were drawn to reduce from; plain k-means++
+        // fills the remaining seeds, duplicating points if it must.
+        return kmeans_plus_plus(points, k, hue_axis, rng);
+    }
+
+    // Weight each candidate by the point mass closest to it, then reduce the
+    // small weighted candidate set to k seeds with k-means++.
+    let candidate_points: Vec<[f32; 3]> = candidates
+        .iter()
+        .map(|&i| [points.px[i], points.py[i], points.pz[i]])
+        .collect();
+    let candidate_soa = CentroidsSoa::from_vec(&candidate_points);
+    let mass = points
+        .px
+        .par_chunks(PARALLEL_INIT_CHUNK)
+        .enumerate()
+        .map(|(chunk_idx, chunk)| {
+            let base = chunk_idx * PARALLEL_INIT_CHUNK;
+            let mut mass = vec![0.0f64; candidate_points.len()];
+            for i in base..base + chunk.len() {
+                let (px, py, pz) = points.component_tuple(i);
+                let (nearest, _) = match hue_axis {
+                    Some(axis) => best_centroid_hue(px, py, pz, &candidate_soa, axis),
+                    None => best_centroid(px, py, pz, &candidate_soa),
+                };
+                mass[nearest] += points.weight(i) as f64;
+            }
+            mass
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .fold(vec![0.0f64; candidate_points.len()], |mut acc, part| {
+            for (a, m) in acc.iter_mut().zip(part) {
+                *a += m;
+            }
+            acc
+        });
+    let mass: Vec<f32> = mass.into_iter().map(|m| m as f32).collect();
+    let reduced = PointsSoa::from_weighted_points(&candidate_points, &mass);
+    kmeans_plus_plus(&reduced, k, hue_axis, rng)
+}
+
+/// Lower each point's weighted D² distance to its nearest of `new_seeds`.
+fn update_nearest_candidate(
+    points: &PointsSoa,
+    new_seeds: &[usize],
+    hue_axis: Option<usize>,
+    distances: &mut [f32],
+) {
+    distances
+        .par_chunks_mut(PARALLEL_INIT_CHUNK)
+        .enumerate()
+        .for_each(|(chunk_idx, chunk)| {
+            let base = chunk_idx * PARALLEL_INIT_CHUNK;
+            for (offset, dist) in chunk.iter_mut().enumerate() {
+                let i = base + offset;
+                let (px, py, pz) = points.component_tuple(i);
+                for &seed in new_seeds {
+                    let (cx, cy, cz) = points.component_tuple(seed);
+                    let d = points.weight(i) * squared_distance(px, py, pz, cx, cy, cz, hue_axis);
+                    if d < *dist {
+                        *dist = d;
+                    }
+                }
             }
-            let dist = squared_distance_components(
+        });
+}
+
+/// Sum in fixed-size chunks merged in order, so the result is independent of
+/// the thread count.
+fn chunked_sum(values: &[f32]) -> f64 {
+    values
+        .par_chunks(PARALLEL_INIT_CHUNK)
+        .map(|chunk| chunk.iter().map(|&v| v as f64).sum::<f64>())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .sum()
+}
+
+/// Fold a newly chosen seed into each point's weighted D² distance.
+fn update_min_distances(
+    points: &PointsSoa,
+    centroid: [f32; 3],
+    hue_axis: Option<usize>,
+    chosen: &[bool],
+    distances: &mut [f32],
+) {
+    #[cfg(all(feature = "simd", target_arch = "x86_64"))]
+    if hue_axis.is_none() && crate::simd::has_avx2() {
+        // SAFETY: AVX2 support was checked at runtime and all columns have
+        // `points.len()` entries. Chosen points are seeds themselves, already at
+        // distance 0, so a plain running minimum matches the scalar loop.
+        unsafe {
+            crate::simd::avx2::update_min_distances(
+                &points.px,
+                &points.py,
+                &points.pz,
+                points.weights.as_deref(),
+                centroid,
+                distances,
+            )
+        };
+        return;
+    }
+    update_min_distances_scalar(points, centroid, hue_axis, chosen, distances);
+}
+
+fn update_min_distances_scalar(
+    points: &PointsSoa,
+    centroid: [f32; 3],
+    hue_axis: Option<usize>,
+    chosen: &[bool],
+    distances: &mut [f32],
+) {
+    for i in 0..points.len() {
+        if chosen[i] {
+            distances[i] = 0.0;
+            continue;
+        }
+        let dist = points.weight(i)
+            * squared_distance(
                 points.px[i],
                 points.py[i],
                 points.pz[i],
-                centroids.cx[centroid_idx],
-                centroids.cy[centroid_idx],
-                centroids.cz[centroid_idx],
+                centroid[0],
+                centroid[1],
+                centroid[2],
+                hue_axis,
             );
-            if dist < distances[i] {
-                distances[i] = dist;
-            }
+        if dist < distances[i] {
+            distances[i] = dist;
         }
     }
+}
 
-    centroids
+/// Draw an unchosen index with probability proportional to its mass. Returns
+/// `None` when the unchosen mass is zero.
+fn pick_weighted(
+    mass: impl Iterator<Item = f32>,
+    total: f32,
+    chosen: &[bool],
+    rng: &mut SmallRng,
+) -> Option<usize> {
+    if total <= 0.0 {
+        return None;
+    }
+    let mut target = rng.gen::<f32>() * total;
+    let mut last = None;
+    for (i, m) in mass.enumerate() {
+        if chosen[i] || m <= 0.0 {
+            continue;
+        }
+        last = Some(i);
+        target -= m;
+        if target <= 0.0 {
+            return Some(i);
+        }
+    }
+    // Float round-off can leave a sliver of target; fall back to the last candidate
+    last
 }
 
 fn sample_batch(points: &PointsSoa, size: usize, rng: &mut SmallRng) -> PointsSoa {
@@ -437,6 +1146,8 @@ fn sample_batch(points: &PointsSoa, size: usize, rng: &mut SmallRng) -> PointsSo
             px: Vec::new(),
             py: Vec::new(),
             pz: Vec::new(),
+            weights: None,
+            sample_counts: None,
         };
     }
     if size >= points.len() {
@@ -445,13 +1156,30 @@ fn sample_batch(points: &PointsSoa, size: usize, rng: &mut SmallRng) -> PointsSo
     let mut px = Vec::with_capacity(size);
     let mut py = Vec::with_capacity(size);
     let mut pz = Vec::with_capacity(size);
+    let mut weights = points.weights.as_ref().map(|_| Vec::with_capacity(size));
+    let mut sample_counts = points
+        .sample_counts
+        .as_ref()
+        .map(|_| Vec::with_capacity(size));
     for _ in 0..size {
         let idx = rng.gen_range(0..points.len());
         px.push(points.px[idx]);
         py.push(points.py[idx]);
         pz.push(points.pz[idx]);
+        if let Some(w) = weights.as_mut() {
+            w.push(points.weight(idx));
+        }
+        if let Some(n) = sample_counts.as_mut() {
+            n.push(points.sample_count(idx));
+        }
+    }
+    PointsSoa {
+        px,
+        py,
+        pz,
+        weights,
+        sample_counts,
     }
-    PointsSoa { px, py, pz }
 }
 
 #[inline]
@@ -462,6 +1190,54 @@ fn squared_distance_components(px: f32, py: f32, pz: f32, cx: f32, cy: f32, cz:
     dx * dx + dy * dy + dz * dz
 }
 
+#[inline]
+fn wrap_hue_delta(delta: f32) -> f32 {
+    let d = delta.abs() % HUE_PERIOD;
+    d.min(HUE_PERIOD - d)
+}
+
+#[inline]
+fn squared_distance_hue(
+    px: f32,
+    py: f32,
+    pz: f32,
+    cx: f32,
+    cy: f32,
+    cz: f32,
+    hue_axis: usize,
+) -> f32 {
+    let mut delta = [px - cx, py - cy, pz - cz];
+    delta[hue_axis] = wrap_hue_delta(delta[hue_axis]);
+    delta[0] * delta[0] + delta[1] * delta[1] + delta[2] * delta[2]
+}
+
+#[inline]
+#[allow(clippy::too_many_arguments)]
+fn squared_distance(
+    px: f32,
+    py: f32,
+    pz: f32,
+    cx: f32,
+    cy: f32,
+    cz: f32,
+    hue_axis: Option<usize>,
+) -> f32 {
+    match hue_axis {
+        Some(axis) => squared_distance_hue(px, py, pz, cx, cy, cz, axis),
+        None => squared_distance_components(px, py, pz, cx, cy, cz),
+    }
+}
+
+/// Mean direction of a set of hues given their summed unit vectors. Falls back to
+/// the arithmetic mean when the hues cancel out and no direction dominates.
+#[inline]
+fn circular_mean_degrees(sum_sin: f32, sum_cos: f32, fallback: f32) -> f32 {
+    if sum_sin.hypot(sum_cos) < 1e-4 {
+        return fallback;
+    }
+    sum_sin.atan2(sum_cos).to_degrees().rem_euclid(HUE_PERIOD)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -480,6 +1256,7 @@ mod tests {
             seed: 42,
             warm_start: None,
             mini_batch: None,
+            ..KMeansConfig::default()
         };
         let result = run_kmeans(&points, &cfg);
         assert_eq!(result.centroids.len(), 2);
@@ -498,6 +1275,7 @@ mod tests {
             seed: 2,
             warm_start: Some(vec![[0.5, 0.5, 0.5]]),
             mini_batch: None,
+            ..KMeansConfig::default()
         };
         let result = run_kmeans(&points, &cfg);
         assert_eq!(result.centroids[0], [0.0, 0.0, 0.0]);
@@ -517,6 +1295,7 @@ mod tests {
             seed: 7,
             warm_start: None,
             mini_batch: Some(256),
+            ..KMeansConfig::default()
         };
         let result = run_kmeans(&points, &cfg);
         assert_eq!(result.centroids.len(), 2);
@@ -538,6 +1317,7 @@ mod tests {
             seed: 12345,
             warm_start: None,
             mini_batch: None,
+            ..KMeansConfig::default()
         };
         let r1 = run_kmeans(&points, &cfg);
         let r2 = run_kmeans(&points, &cfg);
@@ -550,4 +1330,431 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn hue_axis_wraps_red_cluster() {
+        // Red hues straddling 0°/360° in HSL, plus a separate green cluster.
+        let mut points = Vec::new();
+        for i in 0..100 {
+            let jitter = (i % 5) as f32;
+            points.push([356.0 + jitter * 0.5, 0.8, 0.5]);
+            points.push([1.0 + jitter * 0.5, 0.8, 0.5]);
+            points.push([120.0 + jitter, 0.6, 0.4]);
+        }
+        let cfg = KMeansConfig {
+            k: 2,
+            max_iters: 20,
+            tol: 1e-4,
+            seed: 3,
+            warm_start: None,
+            mini_batch: None,
+            hue_axis: Some(0),
+            ..KMeansConfig::default()
+        };
+        let result = run_kmeans(&points, &cfg);
+        let red = result
+            .centroids
+            .iter()
+            .zip(result.counts.iter())
+            .find(|(_, &count)| count == 200)
+            .map(|(c, _)| c)
+            .expect("red hues should form a single cluster");
+        assert!(wrap_hue_delta(red[0]) < 5.0, "red centroid hue {}", red[0]);
+    }
+
+    #[test]
+    fn circular_mean_handles_wraparound() {
+        let hues = [350.0f32, 10.0];
+        let (s, c) = hues.iter().fold((0.0, 0.0), |(s, c), h| {
+            let (sin_h, cos_h) = h.to_radians().sin_cos();
+            (s + sin_h, c + cos_h)
+        });
+        let mean = circular_mean_degrees(s, c, 180.0);
+        assert!(wrap_hue_delta(mean) < 1e-3, "mean {mean}");
+    }
+
+    #[test]
+    fn weighted_centroid_is_weighted_mean() {
+        let points = vec![[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]];
+        let cfg = KMeansConfig {
+            k: 1,
+            max_iters: 5,
+            tol: 1e-6,
+            seed: 1,
+            warm_start: None,
+            mini_batch: None,
+            ..KMeansConfig::default()
+        };
+        let result = run_kmeans_weighted(&points, &[1.0, 3.0], &cfg);
+        for c in result.centroids[0] {
+            assert!((c - 0.75).abs() < 1e-6, "centroid component {c}");
+        }
+        assert_eq!(result.counts, vec![2]);
+        assert!((result.weights[0] - 4.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn integer_weights_match_duplicated_points() {
+        let base = [
+            [0.1, 0.2, 0.3],
+            [0.15, 0.25, 0.3],
+            [0.8, 0.7, 0.6],
+            [0.85, 0.75, 0.65],
+            [0.5, 0.9, 0.1],
+        ];
+        let weights = [3.0, 1.0, 2.0, 1.0, 4.0];
+        let mut duplicated = Vec::new();
+        for (p, &w) in base.iter().zip(weights.iter()) {
+            for _ in 0..w as usize {
+                duplicated.push(*p);
+            }
+        }
+        let cfg = KMeansConfig {
+            k: 2,
+            max_iters: 20,
+            tol: 1e-6,
+            seed: 9,
+            warm_start: Some(vec![[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]]),
+            mini_batch: None,
+            ..KMeansConfig::default()
+        };
+        let weighted = run_kmeans_weighted(&base, &weights, &cfg);
+        let plain = run_kmeans(&duplicated, &cfg);
+        assert!((weighted.inertia - plain.inertia).abs() < 1e-5);
+        for (a, b) in weighted.centroids.iter().zip(plain.centroids.iter()) {
+            for j in 0..3 {
+                assert!((a[j] - b[j]).abs() < 1e-6);
+            }
+        }
+        let plain_weights: Vec<f32> = plain.counts.iter().map(|&c| c as f32).collect();
+        assert_eq!(weighted.weights, plain_weights);
+    }
+
+    #[test]
+    fn weighted_seeding_skips_zero_weight_points() {
+        let mut points = Vec::new();
+        let mut weights = Vec::new();
+        for i in 0..50 {
+            let t = i as f32 / 50.0;
+            points.push([t, 0.0, 0.0]);
+            weights.push(1.0);
+            // Far-away outliers that carry no weight must never seed a cluster.
+            points.push([100.0 + t, 100.0, 100.0]);
+            weights.push(0.0);
+        }
+        let dataset = PointsSoa::from_weighted_points(&points, &weights);
+        for seed in 0..10 {
+            let mut rng = SmallRng::seed_from_u64(seed);
+            let centroids = kmeans_plus_plus(&dataset, 4, None, &mut rng);
+            assert!(centroids.cx.iter().all(|&x| x < 1.0), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn weighted_runs_are_deterministic() {
+        let mut points = Vec::new();
+        let mut weights = Vec::new();
+        for i in 0..3000 {
+            let t = (i as f32) / 3000.0;
+            points.push([t, (t * 7.0).sin(), (t * 3.0).cos()]);
+            weights.push(0.1 + (i % 7) as f32 * 0.3);
+        }
+        let cfg = KMeansConfig {
+            k: 5,
+            max_iters: 25,
+            tol: 1e-6,
+            seed: 77,
+            warm_start: None,
+            mini_batch: None,
+            ..KMeansConfig::default()
+        };
+        let r1 = run_kmeans_weighted(&points, &weights, &cfg);
+        let r2 = run_kmeans_weighted(&points, &weights, &cfg);
+        assert_eq!(r1.counts, r2.counts);
+        assert_eq!(r1.centroids, r2.centroids);
+        assert_eq!(r1.inertia.to_bits(), r2.inertia.to_bits());
+    }
+
+    #[test]
+    fn prequantize_merges_nearby_colors() {
+        let rgb = vec![[200, 10, 10], [201, 12, 9], [10, 200, 10], [200, 10, 10]];
+        let points: Vec<[f32; 3]> = rgb.iter().map(|p| p.map(|c| c as f32 / 255.0)).collect();
+        let q = prequantize(&rgb, &points, None, 5, None);
+        assert_eq!(q.unique_buckets(), 2);
+        assert_eq!(q.sample_counts, vec![3, 1]);
+        assert_eq!(q.weights, vec![3.0, 1.0]);
+        let expected_r = (200.0 + 201.0 + 200.0) / 3.0 / 255.0;
+        assert!((q.points[0][0] - expected_r).abs() < 1e-6);
+
+        let weighted = prequantize(&rgb, &points, Some(&[0.5, 0.5, 2.0, 0.0]), 5, None);
+        assert_eq!(weighted.weights, vec![1.0, 2.0]);
+        assert_eq!(weighted.sample_counts, vec![3, 1]);
+    }
+
+    #[test]
+    fn prequantize_averages_hue_circularly() {
+        let rgb = vec![[255, 0, 1], [255, 1, 0]];
+        let points = vec![[359.0, 1.0, 0.5], [1.0, 1.0, 0.5]];
+        let q = prequantize(&rgb, &points, None, 5, Some(0));
+        assert_eq!(q.unique_buckets(), 1);
+        assert!(
+            wrap_hue_delta(q.points[0][0]) < 1e-3,
+            "hue {}",
+            q.points[0][0]
+        );
+    }
+
+    #[test]
+    fn prequantized_clustering_tracks_full_clustering() {
+        let mut rgb = Vec::new();
+        for i in 0..6000u32 {
+            let jitter = (i % 6) as u8;
+            rgb.push(match i % 3 {
+                0 => [200 + jitter, 30, 40],
+                1 => [20, 120 + jitter, 200],
+                _ => [240, 230, 210 + jitter],
+            });
+        }
+        let points: Vec<[f32; 3]> = rgb.iter().map(|p| p.map(|c| c as f32 / 255.0)).collect();
+        let cfg = KMeansConfig {
+            k: 3,
+            seed: 5,
+            tol: 1e-6,
+            ..KMeansConfig::default()
+        };
+        let full = run_kmeans(&points, &cfg);
+        let q = prequantize(&rgb, &points, None, 6, None);
+        assert!(q.unique_buckets() < 20);
+        let fast = run_kmeans_prequantized(&q, &cfg);
+        for c in &fast.centroids {
+            let nearest = full
+                .centroids
+                .iter()
+                .map(|f| squared_distance_components(c[0], c[1], c[2], f[0], f[1], f[2]))
+                .fold(f32::MAX, f32::min);
+            assert!(nearest < 1e-4, "centroid {c:?} drifted");
+        }
+
+        let assignments = assign_points(&q.points, &fast.centroids, None);
+        let mut counts = vec![0usize; 3];
+        for (&cluster, &n) in assignments.iter().zip(q.sample_counts.iter()) {
+            counts[cluster] += n;
+        }
+        assert_eq!(counts, fast.counts);
+        counts.sort_unstable();
+        assert_eq!(counts, vec![2000, 2000, 2000]);
+    }
+
+    fn assert_same_run(a: &KMeansResult, b: &KMeansResult) {
+        assert_eq!(a.iterations, b.iterations);
+        assert_eq!(a.counts, b.counts);
+        assert_eq!(a.centroids, b.centroids);
+        assert!((a.inertia - b.inertia).abs() <= 1e-4 * a.inertia.max(1.0));
+    }
+
+    #[test]
+    fn hamerly_matches_lloyd() {
+        let mut rng = SmallRng::seed_from_u64(99);
+        let mut points = Vec::new();
+        for i in 0..6000 {
+            let center = [
+                (i % 7) as f32 * 10.0,
+                (i % 5) as f32 * 8.0,
+                (i % 3) as f32 * 6.0,
+            ];
+            points.push(center.map(|c| c + rng.gen::<f32>() * 9.0));
+        }
+        let weights: Vec<f32> = (0..points.len()).map(|i| 0.5 + (i % 4) as f32).collect();
+        let lloyd = KMeansConfig {
+            k: 30,
+            max_iters: 60,
+            tol: 1e-6,
+            seed: 4,
+            algorithm: KMeansAlgorithm::Lloyd,
+            ..KMeansConfig::default()
+        };
+        let hamerly = KMeansConfig {
+            algorithm: KMeansAlgorithm::Hamerly,
+            ..lloyd.clone()
+        };
+        assert_same_run(&run_kmeans(&points, &lloyd), &run_kmeans(&points, &hamerly));
+        assert_same_run(
+            &run_kmeans_weighted(&points, &weights, &lloyd),
+            &run_kmeans_weighted(&points, &weights, &hamerly),
+        );
+    }
+
+    #[test]
+    fn hamerly_matches_lloyd_on_hue_axis() {
+        let mut rng = SmallRng::seed_from_u64(5);
+        let points: Vec<[f32; 3]> = (0..3000)
+            .map(|_| [rng.gen::<f32>() * 360.0, rng.gen::<f32>(), rng.gen::<f32>()])
+            .collect();
+        let lloyd = KMeansConfig {
+            k: 12,
+            max_iters: 40,
+            tol: 1e-6,
+            seed: 8,
+            hue_axis: Some(0),
+            algorithm: KMeansAlgorithm::Lloyd,
+            ..KMeansConfig::default()
+        };
+        let hamerly = KMeansConfig {
+            algorithm: KMeansAlgorithm::Hamerly,
+            ..lloyd.clone()
+        };
+        assert_same_run(&run_kmeans(&points, &lloyd), &run_kmeans(&points, &hamerly));
+    }
+
+    fn random_points(n: usize, seed: u64) -> Vec<[f32; 3]> {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        (0..n)
+            .map(|_| {
+                [
+                    rng.gen::<f32>() * 100.0,
+                    rng.gen::<f32>() * 50.0 - 25.0,
+                    rng.gen(),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn best_centroid_dispatch_matches_scalar() {
+        let points = random_points(2000, 11);
+        for k in [1, 3, 8, 13, 30] {
+            let mut centroids = CentroidsSoa::from_vec(&random_points(k, k as u64));
+            if k > 4 {
+                // Duplicate centroid: ties must resolve to the lower index.
+                centroids.cx[k - 1] = centroids.cx[1];
+                centroids.cy[k - 1] = centroids.cy[1];
+                centroids.cz[k - 1] = centroids.cz[1];
+            }
+            for &[px, py, pz] in &points {
+                assert_eq!(
+                    best_centroid(px, py, pz, &centroids),
+                    best_centroid_scalar(px, py, pz, &centroids)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn seed_distance_update_matches_scalar() {
+        let raw = random_points(1003, 21);
+        let weights: Vec<f32> = (0..raw.len()).map(|i| (i % 5) as f32 * 0.5).collect();
+        for dataset in [
+            PointsSoa::from_points(&raw),
+            PointsSoa::from_weighted_points(&raw, &weights),
+        ] {
+            let mut chosen = vec![false; dataset.len()];
+            let mut fast = vec![f32::MAX; dataset.len()];
+            let mut scalar = fast.clone();
+            for &seed_idx in &[0usize, 517, 1002] {
+                chosen[seed_idx] = true;
+                let (cx, cy, cz) = dataset.component_tuple(seed_idx);
+                update_min_distances(&dataset, [cx, cy, cz], None, &chosen, &mut fast);
+                update_min_distances_scalar(&dataset, [cx, cy, cz], None, &chosen, &mut scalar);
+                assert_eq!(fast, scalar);
+            }
+        }
+    }
+
+    #[test]
+    fn parallel_init_is_independent_of_thread_count() {
+        let points = random_points(20_000, 21);
+        let weights: Vec<f32> = (0..points.len()).map(|i| 1.0 + (i % 3) as f32).collect();
+        let dataset = PointsSoa::from_weighted_points(&points, &weights);
+        let seeds_with = |threads: usize| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("thread pool");
+            pool.install(|| {
+                let mut rng = SmallRng::seed_from_u64(17);
+                kmeans_parallel(&dataset, 16, None, &mut rng).to_vec()
+            })
+        };
+        let single = seeds_with(1);
+        assert_eq!(single.len(), 16);
+        assert_eq!(single, seeds_with(4));
+    }
+
+    #[test]
+    fn parallel_init_separates_distinct_clusters() {
+        let mut rng = SmallRng::seed_from_u64(3);
+        let centers = [
+            [0.0, 0.0, 0.0],
+            [50.0, 0.0, 0.0],
+            [0.0, 50.0, 0.0],
+            [0.0, 0.0, 50.0],
+        ];
+        let points: Vec<[f32; 3]> = (0..4000)
+            .map(|i| centers[i % 4].map(|c: f32| c + rng.gen::<f32>()))
+            .collect();
+        let cfg = KMeansConfig {
+            k: 4,
+            init: KMeansInit::Parallel,
+            ..KMeansConfig::default()
+        };
+        let result = run_kmeans(&points, &cfg);
+        assert_eq!(result.counts, vec![1000; 4]);
+        assert_eq!(run_kmeans(&points, &cfg).centroids, result.centroids);
+    }
+
+    #[test]
+    fn parallel_init_handles_fewer_distinct_points_than_k() {
+        let points = vec![[1.0, 2.0, 3.0]; 10];
+        let cfg = KMeansConfig {
+            k: 3,
+            init: KMeansInit::Parallel,
+            ..KMeansConfig::default()
+        };
+        let result = run_kmeans(&points, &cfg);
+        assert_eq!(result.centroids.len(), 3);
+        assert_eq!(result.counts.iter().sum::<usize>(), 10);
+    }
+
+    #[test]
+    fn restarts_keep_lowest_inertia_run() {
+        let points = random_points(3000, 31);
+        let single = KMeansConfig {
+            k: 12,
+            max_iters: 5,
+            seed: 9,
+            ..KMeansConfig::default()
+        };
+        let restarts = KMeansConfig {
+            n_init: 6,
+            ..single.clone()
+        };
+        let first = run_kmeans(&points, &single);
+        let best = run_kmeans(&points, &restarts);
+        assert!(best.inertia <= first.inertia);
+        assert_eq!(run_kmeans(&points, &restarts).centroids, best.centroids);
+        let inertias: Vec<f32> = (0..6u64)
+            .map(|run| {
+                let seed = 9 ^ run.wrapping_mul(RESTART_SEED_STEP);
+                run_kmeans(
+                    &points,
+                    &KMeansConfig {
+                        seed,
+                        ..single.clone()
+                    },
+                )
+                .inertia
+            })
+            .collect();
+        assert_eq!(
+            best.inertia,
+            inertias.iter().copied().fold(f32::MAX, f32::min)
+        );
+    }
+
+    #[test]
+    fn init_parses_cli_names() {
+        assert_eq!("kmeans++".parse::<KMeansInit>(), Ok(KMeansInit::PlusPlus));
+        assert_eq!("Parallel".parse::<KMeansInit>(), Ok(KMeansInit::Parallel));
+        assert!("random".parse::<KMeansInit>().is_err());
+    }
 }
@@ -1,6 +1,30 @@
 // rmpc Dynamic Theme Generator Library
 // Extracted from color-abstract-via-multidim-KMeans project
 
+pub mod audit;
 pub mod color;
+pub mod generator;
 pub mod image_pipeline;
 pub mod kmeans;
+pub mod preview;
+pub mod render;
+pub mod report;
+pub mod roles;
+#[cfg(feature = "simd")]
+mod simd;
+
+pub use generator::{GenerateError, Theme, ThemeConfig, ThemeGenerator};
+
+/// Arithmetic path the SIMD kernels take on this CPU: `avx2`, `wide`
+/// (portable 4-lane vectors) or `scalar` when built without `simd`.
+#[cfg(feature = "simd")]
+pub fn simd_dispatch() -> &'static str {
+    simd::dispatch()
+}
+
+/// Arithmetic path the SIMD kernels take on this CPU: `avx2`, `wide`
+/// (portable 4-lane vectors) or `scalar` when built without `simd`.
+#[cfg(not(feature = "simd"))]
+pub fn simd_dispatch() -> &'static str {
+    "scalar"
+}
@@ -0,0 +1,847 @@
+//! Mock renderings of the rmpc layout from solved role assignments, so a theme
+//! can be judged without loading it into rmpc.
+//!
+//! [`mock_screen`] lays out the panes of the rendered template (header rows, tab
+//! bar, song table, progress bar, scrollbar) as a grid of styled cells, reading
+//! each style back from the RON that [`crate::render::theme_ron`] renders.
+//! [`terminal_preview`] renders it with truecolor escapes and
+//! [`preview_image`] rasterizes it with an embedded 5×7 bitmap font and
+//! places it next to the cover art and the cluster palette.
+
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb, RgbImage};
+
+use crate::audit::{self, AuditEntry};
+use crate::color;
+use crate::render;
+use crate::roles::{ColorCluster, ColorRole, RgbValue, RoleAssignment};
+
+/// Terminal size of the mockup, in cells.
+pub const SCREEN_COLUMNS: usize = 80;
+pub const SCREEN_ROWS: usize = 24;
+
+/// Cell size in font pixels: a 5×7 glyph plus one column and three rows of spacing.
+const CELL_WIDTH: u32 = 6;
+const CELL_HEIGHT: u32 = 10;
+/// Integer upscale applied to the font when rasterizing the PNG preview.
+const PREVIEW_SCALE: u32 = 2;
+const PREVIEW_PADDING: u32 = 16;
+const SWATCH_HEIGHT: u32 = 48;
+
+const TABS: [&str; 6] = [
+    "Queue",
+    "Directories",
+    "Artists",
+    "Albums",
+    "Playlists",
+    "Search",
+];
+
+/// Sample queue: (artist, title, album, duration).
+const SONGS: [(&str, &str, &str, &str); 14] = [
+    ("M83", "Intro", "Hurry Up, We're Dreaming", "5:22"),
+    ("M83", "Midnight City", "Hurry Up, We're Dreaming", "4:03"),
+    ("M83", "Reunion", "Hurry Up, We're Dreaming", "3:55"),
+    ("M83", "Wait", "Hurry Up, We're Dreaming", "5:43"),
+    ("Beach House", "Myth", "Bloom", "4:18"),
+    ("Beach House", "Wild", "Bloom", "4:57"),
+    ("Beach House", "Lazuli", "Bloom", "5:02"),
+    (
+        "Boards of Canada",
+        "Reach for the Dead",
+        "Tomorrow's Harvest",
+        "4:48",
+    ),
+    (
+        "Boards of Canada",
+        "Palace Posy",
+        "Tomorrow's Harvest",
+        "4:04",
+    ),
+    ("Tycho", "Awake", "Awake", "4:43"),
+    ("Tycho", "Montana", "Awake", "4:54"),
+    ("Tycho", "L", "Awake", "4:34"),
+    ("Slowdive", "Alison", "Souvlaki", "3:50"),
+    ("Slowdive", "When the Sun Hits", "Souvlaki", "4:46"),
+];
+/// Queue index of the song that is playing and of the row under the cursor.
+const PLAYING_ROW: usize = 10;
+const SELECTED_ROW: usize = 4;
+/// Playback position shown in the header and the progress bar.
+const ELAPSED: &str = "1:42";
+const ELAPSED_FRACTION: f32 = 102.0 / 294.0;
+
+/// Foreground, background and boldness of one cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellStyle {
+    pub fg: [u8; 3],
+    pub bg: [u8; 3],
+    pub bold: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: CellStyle,
+}
+
+/// A terminal-sized grid of styled cells.
+#[derive(Debug, Clone)]
+pub struct MockScreen {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl MockScreen {
+    fn new(width: usize, height: usize, style: CellStyle) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell { ch: ' ', style }; width * height],
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.width + x]
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width)
+    }
+
+    fn set(&mut self, x: usize, y: usize, ch: char, style: CellStyle) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = Cell { ch, style };
+        }
+    }
+
+    /// Write `text` starting at column `x`; returns the column after it.
+    fn put(&mut self, x: usize, y: usize, text: &str, style: CellStyle) -> usize {
+        let mut column = x;
+        for ch in text.chars() {
+            self.set(column, y, ch, style);
+            column += 1;
+        }
+        column
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, ch: char, style: CellStyle) {
+        for column in x..x + width {
+            self.set(column, y, ch, style);
+        }
+    }
+
+    /// Draw a single-line box with its corners at (x, y) and (x + w - 1, y + h - 1).
+    fn frame(&mut self, x: usize, y: usize, width: usize, height: usize, style: CellStyle) {
+        let right = x + width - 1;
+        let bottom = y + height - 1;
+        self.fill(x + 1, y, width - 2, '─', style);
+        self.fill(x + 1, bottom, width - 2, '─', style);
+        for row in y + 1..bottom {
+            self.set(x, row, '│', style);
+            self.set(right, row, '│', style);
+        }
+        self.set(x, y, '┌', style);
+        self.set(right, y, '┐', style);
+        self.set(x, bottom, '└', style);
+        self.set(right, bottom, '┘', style);
+    }
+}
+
+/// Styles the template assigns to each part of the layout.
+struct Styles {
+    text: CellStyle,
+    text_bold: CellStyle,
+    header_accent: CellStyle,
+    frame: CellStyle,
+    inactive: CellStyle,
+    active_tab: CellStyle,
+    selected_row: CellStyle,
+    playing_row: CellStyle,
+    elapsed: CellStyle,
+}
+
+fn rgb(value: RgbValue) -> [u8; 3] {
+    [value.r, value.g, value.b]
+}
+
+impl Styles {
+    /// Styles of the theme rendered from `assignments`, looked up by their path
+    /// in the RON so the mockup cannot drift from the template.
+    fn new(assignments: &[RoleAssignment]) -> Self {
+        let ron = render::theme_ron(assignments, true);
+        let entries = audit::audit_theme(&ron).expect("rendered theme parses");
+        let style = |path: &str| {
+            let entry: &AuditEntry = entries
+                .iter()
+                .find(|entry| entry.style == path)
+                .unwrap_or_else(|| panic!("template has no {path}"));
+            CellStyle {
+                fg: entry.fg,
+                bg: entry.bg,
+                bold: entry.bold,
+            }
+        };
+        Self {
+            text: style("text_color"),
+            text_bold: style("header.rows[1].right[0].kind.active_style"),
+            header_accent: style("header.rows[0].left[1].style"),
+            frame: style("borders_style"),
+            inactive: style("tab_bar.inactive_style"),
+            active_tab: style("tab_bar.active_style"),
+            // rmpc draws the row under the cursor with current_item_style and the
+            // playing song with highlighted_item_style
+            selected_row: style("current_item_style"),
+            playing_row: style("highlighted_item_style"),
+            elapsed: style("progress_bar.elapsed_style"),
+        }
+    }
+}
+
+/// Lay out the rmpc panes of the rendered template on an 80×24 grid.
+///
+/// # Panics
+///
+/// Panics if `assignments` lacks one of the core roles; the output of
+/// [`crate::roles::map_colors_to_roles`] always has them all.
+pub fn mock_screen(assignments: &[RoleAssignment], scrollbar_enabled: bool) -> MockScreen {
+    let styles = Styles::new(assignments);
+    let (width, height) = (SCREEN_COLUMNS, SCREEN_ROWS);
+    let mut screen = MockScreen::new(width, height, styles.text);
+
+    // Header, row 1: status badge, title, volume
+    screen.put(0, 0, "[Playing]", styles.header_accent);
+    let title = SONGS[PLAYING_ROW].1;
+    screen.put((width - title.len()) / 2, 0, title, styles.text_bold);
+    let volume = "Volume: 80%";
+    screen.put(width - volume.len(), 0, volume, styles.text);
+
+    // Header, row 2: position, artist - album, playback states
+    screen.put(
+        0,
+        1,
+        &format!("{ELAPSED} / {} (320 kbps)", SONGS[PLAYING_ROW].3),
+        styles.text,
+    );
+    let (artist, album) = (SONGS[PLAYING_ROW].0, SONGS[PLAYING_ROW].2);
+    let center = artist.len() + 3 + album.len();
+    let column = screen.put((width - center) / 2, 1, artist, styles.header_accent);
+    let column = screen.put(column, 1, " - ", styles.text);
+    screen.put(column, 1, album, styles.text);
+    let states = [("Repeat", true), ("Random", true), ("Single", false)];
+    let states_width =
+        states.iter().map(|(name, _)| name.len()).sum::<usize>() + 3 * (states.len() - 1);
+    let mut column = width - states_width;
+    for (index, (name, on)) in states.iter().enumerate() {
+        if index > 0 {
+            column = screen.put(column, 1, " / ", styles.frame);
+        }
+        column = screen.put(
+            column,
+            1,
+            name,
+            if *on { styles.text_bold } else { styles.frame },
+        );
+    }
+
+    // Tab bar
+    screen.frame(0, 2, width, 3, styles.frame);
+    let mut column = 1;
+    for (index, tab) in TABS.iter().enumerate() {
+        if index > 0 {
+            column = screen.put(column, 3, "│", styles.frame);
+        }
+        let style = if index == 0 {
+            styles.active_tab
+        } else {
+            styles.inactive
+        };
+        column = screen.put(column, 3, &format!(" {tab} "), style);
+    }
+
+    // Song table
+    let top = 5;
+    let bottom = height - 2;
+    screen.frame(0, top, width, bottom - top + 1, styles.frame);
+    let inner = width - 2 - usize::from(scrollbar_enabled);
+    let widths = [inner * 20 / 100, inner * 35 / 100, inner * 30 / 100];
+    let duration_width = inner - widths.iter().sum::<usize>();
+    let row_text = |screen: &mut MockScreen, y: usize, cells: [&str; 4], style: CellStyle| {
+        screen.fill(1, y, inner, ' ', style);
+        let mut column = 1;
+        for (text, width) in cells.iter().zip(widths) {
+            screen.put(column, y, &clip(text, width - 1), style);
+            column += width;
+        }
+        let duration = clip(cells[3], duration_width);
+        screen.put(
+            column + duration_width - duration.chars().count(),
+            y,
+            &duration,
+            style,
+        );
+    };
+    row_text(
+        &mut screen,
+        top + 1,
+        ["Artist", "Title", "Album", "Duration"],
+        styles.text_bold,
+    );
+    screen.fill(1, top + 2, inner, '─', styles.frame);
+    let first_song_row = top + 3;
+    for (index, (artist, title, album, duration)) in SONGS.iter().enumerate() {
+        let y = first_song_row + index;
+        if y >= bottom {
+            break;
+        }
+        let style = match index {
+            SELECTED_ROW => styles.selected_row,
+            PLAYING_ROW => styles.playing_row,
+            _ => styles.text,
+        };
+        row_text(&mut screen, y, [artist, title, album, duration], style);
+    }
+
+    // Scrollbar: ends, thumb and track in the frame color inside the right border
+    if scrollbar_enabled {
+        let x = width - 2;
+        let (first, last) = (top + 1, bottom - 1);
+        for y in first..=last {
+            let ch = match y - first {
+                0 => '▲',
+                offset if y == last && offset > 0 => '▼',
+                1..=4 => '█',
+                _ => '│',
+            };
+            screen.set(x, y, ch, styles.frame);
+        }
+    }
+
+    // Progress bar: [=====>    ]
+    let y = height - 1;
+    let track = width - 2;
+    let elapsed = ((track as f32) * ELAPSED_FRACTION).round() as usize;
+    screen.set(0, y, '[', styles.frame);
+    screen.fill(1, y, elapsed, '=', styles.elapsed);
+    screen.set(1 + elapsed, y, '>', styles.frame);
+    screen.fill(
+        2 + elapsed,
+        y,
+        track.saturating_sub(elapsed + 1),
+        ' ',
+        styles.frame,
+    );
+    screen.set(width - 1, y, ']', styles.frame);
+
+    screen
+}
+
+/// Truncate `text` to `width` characters, ending in "..." when shortened.
+fn clip(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let keep = width.saturating_sub(3);
+    text.chars()
+        .take(keep)
+        .chain("...".chars())
+        .take(width)
+        .collect()
+}
+
+/// Rasterize `screen` with the embedded font, `scale` image pixels per font pixel.
+pub fn render_screen(screen: &MockScreen, scale: u32) -> RgbImage {
+    let (cell_w, cell_h) = (CELL_WIDTH * scale, CELL_HEIGHT * scale);
+    let mut image = RgbImage::new(screen.width as u32 * cell_w, screen.height as u32 * cell_h);
+    for (y, row) in screen.rows().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            draw_cell(
+                &mut image,
+                x as u32 * cell_w,
+                y as u32 * cell_h,
+                *cell,
+                scale,
+            );
+        }
+    }
+    image
+}
+
+fn fill_rect(image: &mut RgbImage, x: u32, y: u32, w: u32, h: u32, color: [u8; 3]) {
+    for py in y..(y + h).min(image.height()) {
+        for px in x..(x + w).min(image.width()) {
+            image.put_pixel(px, py, Rgb(color));
+        }
+    }
+}
+
+/// Arms of a box-drawing character: (up, down, left, right).
+fn box_arms(ch: char) -> Option<(bool, bool, bool, bool)> {
+    Some(match ch {
+        '─' => (false, false, true, true),
+        '│' => (true, true, false, false),
+        '┌' => (false, true, false, true),
+        '┐' => (false, true, true, false),
+        '└' => (true, false, false, true),
+        '┘' => (true, false, true, false),
+        _ => return None,
+    })
+}
+
+fn draw_cell(image: &mut RgbImage, x0: u32, y0: u32, cell: Cell, scale: u32) {
+    let (cell_w, cell_h) = (CELL_WIDTH * scale, CELL_HEIGHT * scale);
+    let Cell { ch, style } = cell;
+    fill_rect(image, x0, y0, cell_w, cell_h, style.bg);
+
+    if let Some((up, down, left, right)) = box_arms(ch) {
+        // Lines run through the cell center so neighbouring cells join up
+        let (cx, cy) = (x0 + cell_w / 2 - scale / 2, y0 + cell_h / 2 - scale / 2);
+        if up {
+            fill_rect(image, cx, y0, scale, cy - y0 + scale, style.fg);
+        }
+        if down {
+            fill_rect(image, cx, cy, scale, y0 + cell_h - cy, style.fg);
+        }
+        if left {
+            fill_rect(image, x0, cy, cx - x0 + scale, scale, style.fg);
+        }
+        if right {
+            fill_rect(image, cx, cy, x0 + cell_w - cx, scale, style.fg);
+        }
+        return;
+    }
+
+    match ch {
+        '█' => fill_rect(image, x0, y0, cell_w, cell_h, style.fg),
+        '▲' | '▼' => {
+            // Isosceles triangle over the middle five font pixels
+            for row in 0..5 {
+                let span = if ch == '▲' { row } else { 4 - row };
+                fill_rect(
+                    image,
+                    x0 + (2 - span / 2) * scale,
+                    y0 + (2 + row) * scale,
+                    (span / 2 * 2 + 1) * scale,
+                    scale,
+                    style.fg,
+                );
+            }
+        }
+        _ => {
+            // Descender glyphs start at the x-height and hang below the baseline
+            let top = if matches!(ch, 'g' | 'p' | 'q' | 'y') {
+                3
+            } else {
+                1
+            };
+            for (row, bits) in glyph(ch).iter().enumerate() {
+                for column in 0..5u32 {
+                    if bits & (0x10 >> column) == 0 {
+                        continue;
+                    }
+                    let px = x0 + column * scale;
+                    let py = y0 + (row as u32 + top) * scale;
+                    // Bold is drawn by smearing each pixel one font pixel to the right
+                    let w = if style.bold { 2 * scale } else { scale };
+                    fill_rect(image, px, py, w, scale, style.fg);
+                }
+            }
+        }
+    }
+}
+
+/// Render `screen` as lines of 24-bit ANSI escape sequences.
+pub fn render_ansi(screen: &MockScreen) -> String {
+    let mut out = String::new();
+    for row in screen.rows() {
+        let mut current = None;
+        for cell in row {
+            if current != Some(cell.style) {
+                let CellStyle { fg, bg, bold } = cell.style;
+                out.push_str(&format!(
+                    "\x1b[{}38;2;{};{};{};48;2;{};{};{}m",
+                    if bold { "0;1;" } else { "0;" },
+                    fg[0],
+                    fg[1],
+                    fg[2],
+                    bg[0],
+                    bg[1],
+                    bg[2]
+                ));
+                current = Some(cell.style);
+            }
+            out.push(cell.ch);
+        }
+        out.push_str("\x1b[0m\n");
+    }
+    out
+}
+
+fn ansi_swatch(color: [u8; 3], width: usize) -> String {
+    format!(
+        "\x1b[48;2;{};{};{}m{}\x1b[0m",
+        color[0],
+        color[1],
+        color[2],
+        " ".repeat(width)
+    )
+}
+
+/// Truecolor terminal preview: the mock screen, then a swatch strip of the
+/// clusters (hex and share) and one line per role with its hex, contrast against
+/// background and text, and origin.
+pub fn terminal_preview(
+    clusters: &[ColorCluster],
+    assignments: &[RoleAssignment],
+    scrollbar_enabled: bool,
+) -> String {
+    let mut out = render_ansi(&mock_screen(assignments, scrollbar_enabled));
+
+    out.push_str("\nClusters\n");
+    for row in clusters.chunks(4) {
+        for cluster in row {
+            let rgb = rgb(cluster.rgb);
+            out.push_str(&format!(
+                "{} {} {:>5.1}%   ",
+                ansi_swatch(rgb, 4),
+                color::rgb_to_hex(rgb),
+                cluster.share * 100.0
+            ));
+        }
+        out.push('\n');
+    }
+
+    let lab = |role: ColorRole| assignments.iter().find(|a| a.role == role).map(|a| a.lab);
+    let (bg, text) = (lab(ColorRole::Background), lab(ColorRole::Text));
+    let ratio = |against: Option<[f32; 3]>, itself: bool, lab: [f32; 3]| match against {
+        Some(other) if !itself => format!("{:>5.2}:1", color::calculate_contrast_ratio(lab, other)),
+        _ => format!("{:>7}", "-"),
+    };
+    out.push_str(&format!(
+        "\nRoles\n     {:<14} {:<7}  {:>7}  {:>7}  {}\n",
+        "ROLE", "HEX", "VS BG", "VS TEXT", "ORIGIN"
+    ));
+    for assignment in assignments {
+        let origin = match (&assignment.origin, assignment.source_cluster_index) {
+            (Some(origin), Some(index)) => format!("{origin} #{index}"),
+            (Some(origin), None) => origin.clone(),
+            (None, Some(index)) => format!("cluster #{index}"),
+            (None, None) => "-".to_string(),
+        };
+        out.push_str(&format!(
+            "{} {:<14} {}  {}  {}  {}\n",
+            ansi_swatch(rgb(assignment.rgb), 4),
+            format!("{:?}", assignment.role),
+            assignment.hex,
+            ratio(bg, assignment.role == ColorRole::Background, assignment.lab),
+            ratio(text, assignment.role == ColorRole::Text, assignment.lab),
+            origin
+        ));
+    }
+    out
+}
+
+/// Compose the mockup, the cover art and the cluster palette into one image:
+/// cover on the left, mockup on the right, and below them a strip of cluster
+/// swatches whose widths follow each cluster's `share`.
+pub fn preview_image(
+    cover: &DynamicImage,
+    clusters: &[ColorCluster],
+    assignments: &[RoleAssignment],
+    scrollbar_enabled: bool,
+) -> RgbImage {
+    let screen = render_screen(&mock_screen(assignments, scrollbar_enabled), PREVIEW_SCALE);
+    let background = screen.get_pixel(0, 0).0;
+    let side = screen.height();
+    let pad = PREVIEW_PADDING;
+
+    let width = pad + side + pad + screen.width() + pad;
+    let height = pad + side + pad + SWATCH_HEIGHT + pad;
+    let mut canvas = RgbImage::from_pixel(width, height, Rgb(background));
+
+    // Cover art, fitted into a square next to the mockup and composited over the
+    // theme background
+    let thumb = cover.resize(side, side, FilterType::Triangle).to_rgba8();
+    let (tx, ty) = (
+        pad + (side - thumb.width()) / 2,
+        pad + (side - thumb.height()) / 2,
+    );
+    for (x, y, pixel) in thumb.enumerate_pixels() {
+        let alpha = f32::from(pixel[3]) / 255.0;
+        let blend = |c: usize| {
+            (f32::from(pixel[c]) * alpha + f32::from(background[c]) * (1.0 - alpha)).round() as u8
+        };
+        canvas.put_pixel(tx + x, ty + y, Rgb([blend(0), blend(1), blend(2)]));
+    }
+
+    image::imageops::replace(
+        &mut canvas,
+        &screen,
+        i64::from(pad + side + pad),
+        i64::from(pad),
+    );
+
+    // Swatches: cumulative share decides each edge so rounding never leaves gaps.
+    // A frame-colored outline keeps a background-colored cluster visible.
+    let strip_width = width - 2 * pad;
+    fill_rect(
+        &mut canvas,
+        pad - PREVIEW_SCALE,
+        pad + side + pad - PREVIEW_SCALE,
+        strip_width + 2 * PREVIEW_SCALE,
+        SWATCH_HEIGHT + 2 * PREVIEW_SCALE,
+        Styles::new(assignments).frame.fg,
+    );
+    let total: f64 = clusters.iter().map(|c| c.share).sum();
+    if total > 0.0 {
+        let mut cumulative = 0.0;
+        let mut left = 0;
+        for cluster in clusters {
+            cumulative += cluster.share;
+            let right = ((cumulative / total) * f64::from(strip_width)).round() as u32;
+            fill_rect(
+                &mut canvas,
+                pad + left,
+                pad + side + pad,
+                right.saturating_sub(left),
+                SWATCH_HEIGHT,
+                rgb(cluster.rgb),
+            );
+            left = right.max(left);
+        }
+    }
+
+    canvas
+}
+
+/// Glyph rows for `ch`, top to bottom; bit 4 is the leftmost column. Characters
+/// outside printable ASCII render as '?'.
+fn glyph(ch: char) -> &'static [u8; 7] {
+    let index = match ch {
+        ' '..='~' => ch as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    };
+    &FONT_5X7[index]
+}
+
+/// 5×7 bitmap font for printable ASCII (0x20..=0x7e). 'g', 'p', 'q' and 'y'
+/// are stored from the x-height down and drawn two rows lower.
+#[rustfmt::skip]
+const FONT_5X7: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04], // '!'
+    [0x0a, 0x0a, 0x0a, 0x00, 0x00, 0x00, 0x00], // '"'
+    [0x0a, 0x0a, 0x1f, 0x0a, 0x1f, 0x0a, 0x0a], // '#'
+    [0x04, 0x0f, 0x14, 0x0e, 0x05, 0x1e, 0x04], // '$'
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // '%'
+    [0x0c, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0d], // '&'
+    [0x04, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // "'"
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // '('
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // ')'
+    [0x00, 0x04, 0x15, 0x0e, 0x15, 0x04, 0x00], // '*'
+    [0x00, 0x04, 0x04, 0x1f, 0x04, 0x04, 0x00], // '+'
+    [0x00, 0x00, 0x00, 0x00, 0x0c, 0x04, 0x08], // ','
+    [0x00, 0x00, 0x00, 0x1f, 0x00, 0x00, 0x00], // '-'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c], // '.'
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // '/'
+    [0x0e, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0e], // '0'
+    [0x04, 0x0c, 0x04, 0x04, 0x04, 0x04, 0x0e], // '1'
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1f], // '2'
+    [0x1f, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0e], // '3'
+    [0x02, 0x06, 0x0a, 0x12, 0x1f, 0x02, 0x02], // '4'
+    [0x1f, 0x10, 0x1e, 0x01, 0x01, 0x11, 0x0e], // '5'
+    [0x06, 0x08, 0x10, 0x1e, 0x11, 0x11, 0x0e], // '6'
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // '7'
+    [0x0e, 0x11, 0x11, 0x0e, 0x11, 0x11, 0x0e], // '8'
+    [0x0e, 0x11, 0x11, 0x0f, 0x01, 0x02, 0x0c], // '9'
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x00], // ':'
+    [0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x04, 0x08], // ';'
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // '<'
+    [0x00, 0x00, 0x1f, 0x00, 0x1f, 0x00, 0x00], // '='
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // '>'
+    [0x0e, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // '?'
+    [0x0e, 0x11, 0x01, 0x0d, 0x15, 0x15, 0x0e], // '@'
+    [0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // 'A'
+    [0x1e, 0x11, 0x11, 0x1e, 0x11, 0x11, 0x1e], // 'B'
+    [0x0e, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0e], // 'C'
+    [0x1c, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1c], // 'D'
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x1f], // 'E'
+    [0x1f, 0x10, 0x10, 0x1e, 0x10, 0x10, 0x10], // 'F'
+    [0x0e, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0f], // 'G'
+    [0x11, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11], // 'H'
+    [0x0e, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // 'I'
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0c], // 'J'
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // 'K'
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1f], // 'L'
+    [0x11, 0x1b, 0x15, 0x15, 0x11, 0x11, 0x11], // 'M'
+    [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11], // 'N'
+    [0x0e, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // 'O'
+    [0x1e, 0x11, 0x11, 0x1e, 0x10, 0x10, 0x10], // 'P'
+    [0x0e, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0d], // 'Q'
+    [0x1e, 0x11, 0x11, 0x1e, 0x14, 0x12, 0x11], // 'R'
+    [0x0f, 0x10, 0x10, 0x0e, 0x01, 0x01, 0x1e], // 'S'
+    [0x1f, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // 'T'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0e], // 'U'
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0a, 0x04], // 'V'
+    [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0a], // 'W'
+    [0x11, 0x11, 0x0a, 0x04, 0x0a, 0x11, 0x11], // 'X'
+    [0x11, 0x11, 0x0a, 0x04, 0x04, 0x04, 0x04], // 'Y'
+    [0x1f, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1f], // 'Z'
+    [0x0e, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0e], // '['
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // '\\'
+    [0x0e, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0e], // ']'
+    [0x04, 0x0a, 0x11, 0x00, 0x00, 0x00, 0x00], // '^'
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f], // '_'
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // '`'
+    [0x00, 0x00, 0x0e, 0x01, 0x0f, 0x11, 0x0f], // 'a'
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x1e], // 'b'
+    [0x00, 0x00, 0x0e, 0x10, 0x10, 0x11, 0x0e], // 'c'
+    [0x01, 0x01, 0x0d, 0x13, 0x11, 0x11, 0x0f], // 'd'
+    [0x00, 0x00, 0x0e, 0x11, 0x1f, 0x10, 0x0e], // 'e'
+    [0x06, 0x09, 0x08, 0x1c, 0x08, 0x08, 0x08], // 'f'
+    [0x0f, 0x11, 0x11, 0x11, 0x0f, 0x01, 0x0e], // 'g'
+    [0x10, 0x10, 0x16, 0x19, 0x11, 0x11, 0x11], // 'h'
+    [0x04, 0x00, 0x0c, 0x04, 0x04, 0x04, 0x0e], // 'i'
+    [0x02, 0x00, 0x06, 0x02, 0x02, 0x12, 0x0c], // 'j'
+    [0x10, 0x10, 0x12, 0x14, 0x18, 0x14, 0x12], // 'k'
+    [0x0c, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0e], // 'l'
+    [0x00, 0x00, 0x1a, 0x15, 0x15, 0x11, 0x11], // 'm'
+    [0x00, 0x00, 0x16, 0x19, 0x11, 0x11, 0x11], // 'n'
+    [0x00, 0x00, 0x0e, 0x11, 0x11, 0x11, 0x0e], // 'o'
+    [0x1e, 0x11, 0x11, 0x11, 0x1e, 0x10, 0x10], // 'p'
+    [0x0f, 0x11, 0x11, 0x11, 0x0f, 0x01, 0x01], // 'q'
+    [0x00, 0x00, 0x16, 0x19, 0x10, 0x10, 0x10], // 'r'
+    [0x00, 0x00, 0x0e, 0x10, 0x0e, 0x01, 0x1e], // 's'
+    [0x08, 0x08, 0x1c, 0x08, 0x08, 0x09, 0x06], // 't'
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x13, 0x0d], // 'u'
+    [0x00, 0x00, 0x11, 0x11, 0x11, 0x0a, 0x04], // 'v'
+    [0x00, 0x00, 0x11, 0x11, 0x15, 0x15, 0x0a], // 'w'
+    [0x00, 0x00, 0x11, 0x0a, 0x04, 0x0a, 0x11], // 'x'
+    [0x11, 0x11, 0x11, 0x11, 0x0f, 0x01, 0x0e], // 'y'
+    [0x00, 0x00, 0x1f, 0x02, 0x04, 0x08, 0x1f], // 'z'
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // '{'
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // '|'
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // '}'
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::{map_colors_to_roles, sample_palette, RoleOptions};
+
+    fn palette() -> (Vec<ColorCluster>, Vec<RoleAssignment>) {
+        let clusters = sample_palette();
+        let (roles, _, _) = map_colors_to_roles(&clusters, &RoleOptions::default(), false);
+        (clusters, roles)
+    }
+
+    fn role(roles: &[RoleAssignment], role: ColorRole) -> [u8; 3] {
+        rgb(roles.iter().find(|a| a.role == role).unwrap().rgb)
+    }
+
+    #[test]
+    fn mock_screen_uses_template_role_styles() {
+        let (_, roles) = palette();
+        let screen = mock_screen(&roles, true);
+        assert_eq!((screen.width, screen.height), (SCREEN_COLUMNS, SCREEN_ROWS));
+
+        let bg = role(&roles, ColorRole::Background);
+        let active = role(&roles, ColorRole::ActiveItem);
+        let highlight = role(&roles, ColorRole::HighlightText);
+        let frame = role(&roles, ColorRole::Frame);
+
+        // Badge, active tab, selected and playing rows, borders, progress, scrollbar
+        assert_eq!(
+            screen.cell(0, 0).style.fg,
+            role(&roles, ColorRole::HeaderAccent)
+        );
+        assert_eq!(screen.cell(2, 3).style.bg, active);
+        assert_eq!(screen.cell(2, 3).style.fg, highlight);
+        let selected = screen.cell(2, 8 + SELECTED_ROW).style;
+        assert_eq!(
+            (selected.fg, selected.bg, selected.bold),
+            (highlight, active, true)
+        );
+        let playing = screen.cell(2, 8 + PLAYING_ROW).style;
+        assert_eq!(
+            (playing.fg, playing.bg, playing.bold),
+            (highlight, active, true)
+        );
+        assert_eq!(screen.cell(0, 5).ch, '┌');
+        assert_eq!(screen.cell(0, 5).style.fg, frame);
+        assert_eq!(screen.cell(0, 5).style.bg, bg);
+        assert_eq!(screen.cell(1, SCREEN_ROWS - 1).style.fg, active);
+        assert_eq!(screen.cell(SCREEN_COLUMNS - 2, 6).ch, '▲');
+
+        let without = mock_screen(&roles, false);
+        assert!(without
+            .rows()
+            .all(|row| row.iter().all(|cell| cell.ch != '▲')));
+    }
+
+    #[test]
+    fn preview_places_cover_screen_and_share_sized_swatches() {
+        let (clusters, roles) = palette();
+        let cover = DynamicImage::ImageRgb8(RgbImage::from_pixel(40, 20, Rgb([255, 0, 0])));
+        let image = preview_image(&cover, &clusters, &roles, true);
+
+        let side = SCREEN_ROWS as u32 * CELL_HEIGHT * PREVIEW_SCALE;
+        let screen_width = SCREEN_COLUMNS as u32 * CELL_WIDTH * PREVIEW_SCALE;
+        let pad = PREVIEW_PADDING;
+        assert_eq!(image.width(), 3 * pad + side + screen_width);
+        assert_eq!(image.height(), 3 * pad + side + SWATCH_HEIGHT);
+
+        // Wide cover is letterboxed in the middle of its square
+        assert_eq!(
+            image.get_pixel(pad + side / 2, pad + side / 2).0,
+            [255, 0, 0]
+        );
+        let bg = role(&roles, ColorRole::Background);
+        assert_eq!(image.get_pixel(pad + side / 2, pad + 2).0, bg);
+
+        // First cluster covers 55% of the strip, the second starts after it
+        let strip = image.width() - 2 * pad;
+        let y = 2 * pad + side + SWATCH_HEIGHT / 2;
+        assert_eq!(image.get_pixel(pad + strip / 2, y).0, rgb(clusters[0].rgb));
+        assert_eq!(
+            image.get_pixel(pad + strip * 6 / 10, y).0,
+            rgb(clusters[1].rgb)
+        );
+    }
+
+    #[test]
+    fn terminal_preview_prints_screen_and_role_swatches() {
+        let (clusters, roles) = palette();
+        let out = terminal_preview(&clusters, &roles, true);
+        let lines: Vec<&str> = out.lines().collect();
+
+        // Selected row is bold highlight text on the active background
+        let [r, g, b] = role(&roles, ColorRole::HighlightText);
+        let [ar, ag, ab] = role(&roles, ColorRole::ActiveItem);
+        assert!(lines[8 + SELECTED_ROW]
+            .contains(&format!("\x1b[0;1;38;2;{r};{g};{b};48;2;{ar};{ag};{ab}m")));
+        assert!(lines[..SCREEN_ROWS]
+            .iter()
+            .all(|line| line.ends_with("\x1b[0m")));
+
+        let text = roles.iter().find(|a| a.role == ColorRole::Text).unwrap();
+        let text_line = lines
+            .iter()
+            .find(|line| line.contains(" Text "))
+            .expect("text role line");
+        assert!(text_line.contains(&text.hex));
+        assert!(text_line.contains(":1"));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains(&color::rgb_to_hex(rgb(clusters[0].rgb)))));
+    }
+
+    #[test]
+    fn glyphs_cover_printable_ascii() {
+        assert_eq!(glyph(' '), &[0; 7]);
+        assert_eq!(glyph('A'), &[0x0e, 0x11, 0x11, 0x1f, 0x11, 0x11, 0x11]);
+        assert_eq!(glyph('é'), glyph('?'));
+    }
+}
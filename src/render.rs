@@ -0,0 +1,241 @@
+//! Renderers that turn solved role assignments into theme files.
+
+use crate::roles::{ColorRole, RoleAssignment};
+
+/// Generate RON theme file content from role assignments
+pub fn theme_ron(assignments: &[RoleAssignment], scrollbar_enabled: bool) -> String {
+    // Find role assignments
+    let bg = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::Background)
+        .unwrap();
+    let text = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::Text)
+        .unwrap();
+    let accent = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::Accent)
+        .unwrap();
+    let active = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::ActiveItem)
+        .unwrap();
+    let inactive = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::InactiveItem)
+        .unwrap();
+    let highlight_text_role = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::HighlightText)
+        .unwrap();
+    let frame = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::Frame)
+        .unwrap();
+    let header_accent = assignments
+        .iter()
+        .find(|a| a.role == ColorRole::HeaderAccent)
+        .unwrap();
+    let status = |role: ColorRole| {
+        assignments
+            .iter()
+            .find(|a| a.role == role)
+            .map_or(accent.hex.as_str(), |a| a.hex.as_str())
+    };
+
+    let scrollbar_block = if scrollbar_enabled {
+        format!(
+            "    scrollbar: (\n        symbols: [\"│\", \"█\", \"▲\", \"▼\"],\n        track_style: (fg: \"{}\", bg: \"{}\"),\n        ends_style: (fg: \"{}\", bg: \"{}\"),\n        thumb_style: (fg: \"{}\", bg: \"{}\"),\n    ),\n",
+            frame.hex, bg.hex, frame.hex, bg.hex, frame.hex, bg.hex
+        )
+    } else {
+        "    scrollbar: None,\n".to_string()
+    };
+
+    format!(
+        r##"#![enable(implicit_some)]
+#![enable(unwrap_newtypes)]
+#![enable(unwrap_variant_newtypes)]
+(
+    default_album_art_path: None,
+    show_song_table_header: true,
+    draw_borders: true,
+    format_tag_separator: " | ",
+    browser_column_widths: [20, 38, 42],
+    background_color: "{bg}",
+    text_color: "{text}",
+    header_background_color: "{bg}",
+    modal_background_color: "{bg}",
+    modal_backdrop: false,
+    preview_label_style: (fg: "{header_accent}", bg: "{bg}"),
+    preview_metadata_group_style: (fg: "{header_accent}", bg: "{bg}", modifiers: "Bold"),
+    tab_bar: (
+        enabled: true,
+        active_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
+        inactive_style: (fg: "{inactive}", bg: "{bg}"),
+    ),
+    highlighted_item_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
+    current_item_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
+    borders_style: (fg: "{frame}"),
+    highlight_border_style: (fg: "{frame}"),
+    symbols: (
+        song: "",
+        dir: "",
+        playlist: "P",
+        marker: "M",
+        ellipsis: "...",
+        song_style: None,
+        dir_style: None,
+        playlist_style: None,
+    ),
+    level_styles: (
+        info: (fg: "{info}", bg: "{bg}"),
+        warn: (fg: "{warn}", bg: "{bg}"),
+        error: (fg: "{error}", bg: "{bg}"),
+        debug: (fg: "{debug}", bg: "{bg}"),
+        trace: (fg: "{trace}", bg: "{bg}"),
+    ),
+    progress_bar: (
+        symbols: ["[", "=", ">", " ", "]"],
+        track_style: (fg: "{frame}", bg: "{bg}"),
+        elapsed_style: (fg: "{active}", bg: "{bg}"),
+        thumb_style: (fg: "{frame}", bg: "{bg}"),
+    ),
+{scrollbar}
+    song_table_format: [
+        (
+            prop: (kind: Property(Artist),
+                default: (kind: Text("Unknown"))
+            ),
+            width: "20%",
+        ),
+        (
+            prop: (kind: Property(Title),
+                default: (kind: Text("Unknown"))
+            ),
+            width: "35%",
+        ),
+        (
+            // Keep Album column styling neutral so row-level styles (e.g., playing-not-selected
+            // foreground) can apply uniformly across all columns. Content inherits the row/table
+            // style without forcing its own fg/bg here.
+            prop: (kind: Property(Album),
+                default: (kind: Text("Unknown Album"))
+            ),
+            width: "30%",
+        ),
+        (
+            prop: (kind: Property(Duration),
+                default: (kind: Text("-"))
+            ),
+            width: "15%",
+            alignment: Right,
+        ),
+    ],
+    components: {{}},
+    layout: Split(
+        direction: Vertical,
+        panes: [
+            (
+                pane: Pane(Header),
+                size: "2",
+            ),
+            (
+                pane: Pane(Tabs),
+                size: "3",
+            ),
+            (
+                pane: Pane(TabContent),
+                size: "100%",
+            ),
+            (
+                pane: Pane(ProgressBar),
+                size: "1",
+            ),
+        ],
+    ),
+    header: (
+        rows: [
+            (
+                left: [
+                    (kind: Text("["), style: (fg: "{header_accent}", modifiers: "Bold")),
+                    (kind: Property(Status(StateV2(playing_label: "Playing", paused_label: "Paused", stopped_label: "Stopped"))), style: (fg: "{header_accent}", modifiers: "Bold")),
+                    (kind: Text("]"), style: (fg: "{header_accent}", modifiers: "Bold"))
+                ],
+                center: [
+                    (kind: Property(Song(Title)), style: (modifiers: "Bold"),
+                        default: (kind: Text("No Song"), style: (modifiers: "Bold"))
+                    )
+                ],
+                right: [
+                    (kind: Property(Widget(ScanStatus)), style: (fg: "{text}")),
+                    (kind: Property(Widget(Volume)), style: (fg: "{text}"))
+                ]
+            ),
+            (
+                left: [
+                    (kind: Property(Status(Elapsed))),
+                    (kind: Text(" / ")),
+                    (kind: Property(Status(Duration))),
+                    (kind: Text(" (")),
+                    (kind: Property(Status(Bitrate))),
+                    (kind: Text(" kbps)"))
+                ],
+                center: [
+                    (kind: Property(Song(Artist)), style: (fg: "{header_accent}", modifiers: "Bold"),
+                        default: (kind: Text("Unknown"), style: (fg: "{header_accent}", modifiers: "Bold"))
+                    ),
+                    (kind: Text(" - ")), 
+                    (kind: Property(Song(Album)),
+                        default: (kind: Text("Unknown Album"))
+                    )
+                ],
+                right: [
+                    (
+                        kind: Property(Widget(States(
+                            active_style: (fg: "{text}", modifiers: "Bold"),
+                            separator_style: (fg: "{frame}")))
+                        ),
+                        style: (fg: "{frame}")
+                    ),
+                ]
+            ),
+        ],
+    ),
+    browser_song_format: [
+        (
+            kind: Group([
+                (kind: Property(Track)),
+                (kind: Text(" ")), 
+            ])
+        ),
+        (
+            kind: Group([
+                (kind: Property(Artist)),
+                (kind: Text(" - ")), 
+                (kind: Property(Title)),
+            ]),
+            default: (kind: Property(Filename))
+        ),
+    ],
+    lyrics: (
+        timestamp: false
+    )
+)
+"##,
+        bg = bg.hex,
+        text = text.hex,
+        active = active.hex,
+        inactive = inactive.hex,
+        highlight_text = highlight_text_role.hex,
+        frame = frame.hex,
+        header_accent = header_accent.hex,
+        info = status(ColorRole::Info),
+        warn = status(ColorRole::Warn),
+        error = status(ColorRole::Error),
+        debug = status(ColorRole::Debug),
+        trace = status(ColorRole::Trace),
+        scrollbar = scrollbar_block,
+    )
+}
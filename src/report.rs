@@ -0,0 +1,463 @@
+//! Self-contained HTML report of one generator run, for reviewing a bad theme
+//! without digging through the `--debug` JSON.
+//!
+//! The report shows the cover, a Lab scatter of the pixel samples and cluster
+//! centroids, the accent/active candidates and top pairs with every guardrail
+//! marked pass/relaxed/fail, the relaxation stage the solver settled on, and the
+//! final palette. The cover is inlined as a base64 PNG and the plot as SVG, so
+//! the file opens offline and can be attached to a PR discussion.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+use thiserror::Error;
+
+use crate::color;
+use crate::roles::{
+    ColorCluster, ColorRole, DebugOutput, GuardrailCheck, PairCandidateDebug, PairwiseDebug,
+    PairwiseDebugEntry, RgbValue, RoleAssignment, SolverStage,
+};
+
+#[derive(Debug, Error)]
+pub enum ReportError {
+    #[error("failed to encode cover thumbnail")]
+    Thumbnail(#[from] image::ImageError),
+}
+
+/// Longest side of the embedded cover thumbnail, in pixels.
+const THUMBNAIL_SIZE: u32 = 320;
+
+const STYLE: &str = "
+body { font: 14px/1.4 system-ui, sans-serif; margin: 2em; color: #222; background: #fafafa; }
+h1 { font-size: 1.5em; } h2 { font-size: 1.2em; margin-top: 2em; }
+.top { display: flex; gap: 2em; align-items: flex-start; flex-wrap: wrap; }
+table { border-collapse: collapse; margin: 0.5em 0; }
+th, td { border: 1px solid #ddd; padding: 2px 8px; text-align: left; white-space: nowrap; }
+th { background: #eee; }
+.swatch { display: inline-block; width: 1.6em; height: 1em; border: 1px solid #888; vertical-align: middle; }
+.pass { background: #dff3df; } .relaxed { background: #fff2c6; } .fail { background: #f8d4d4; }
+.waived { color: #888; } .winner { font-weight: bold; }
+.strip { display: flex; height: 32px; border: 1px solid #888; max-width: 1000px; }
+.used { font-weight: bold; } .unused { color: #888; }
+code { background: #eee; padding: 0 3px; }
+";
+
+/// Everything the report draws from a single run.
+pub struct ReportInput<'a> {
+    /// Image path or name shown in the title.
+    pub source: &'a str,
+    pub cover: &'a DynamicImage,
+    pub clusters: &'a [ColorCluster],
+    pub roles: &'a [RoleAssignment],
+    /// Diagnostics from a run with `ThemeConfig::debug` set.
+    pub debug: &'a DebugOutput,
+}
+
+/// Render the report as a single HTML document.
+pub fn html_report(input: &ReportInput) -> Result<String, ReportError> {
+    let mut out = String::new();
+    out.push_str(&format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>rmpc-theme-gen report: {source}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+         <h1>rmpc-theme-gen report: <code>{source}</code></h1>\n",
+        source = escape(input.source)
+    ));
+
+    out.push_str("<div class=\"top\">\n");
+    out.push_str(&format!(
+        "<img alt=\"cover\" src=\"data:image/png;base64,{}\">\n",
+        cover_thumbnail(input.cover)?
+    ));
+    out.push_str(&lab_scatter(&input.debug.samples, input.clusters));
+    out.push_str("</div>\n");
+
+    out.push_str("<h2>Final palette</h2>\n");
+    out.push_str(&palette_section(input.clusters, input.roles));
+
+    match &input.debug.pairwise {
+        Some(pairwise) => {
+            out.push_str("<h2>Relaxation stage</h2>\n");
+            out.push_str(&stage_section(pairwise));
+            out.push_str("<h2>Accent/active pairs</h2>\n");
+            out.push_str(&pairs_table(pairwise));
+            out.push_str("<h2>Accent candidates</h2>\n");
+            out.push_str(&candidate_table(&pairwise.accent_candidates, |c| {
+                c.accent_checks()
+            }));
+            out.push_str("<h2>Active candidates</h2>\n");
+            out.push_str(&candidate_table(&pairwise.active_candidates, |c| {
+                c.active_checks()
+            }));
+        }
+        None => out.push_str("<p>No solver diagnostics were recorded for this run.</p>\n"),
+    }
+
+    out.push_str(
+        "<p>Cells: <span class=\"pass\">strict pass</span> \
+         <span class=\"relaxed\">relaxed pass</span> <span class=\"fail\">fail</span> \
+         <span class=\"waived\">hue check waived for a near-neutral color</span></p>\n\
+         </body>\n</html>\n",
+    );
+    Ok(out)
+}
+
+fn cover_thumbnail(cover: &DynamicImage) -> Result<String, ReportError> {
+    let thumbnail = cover.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let mut png = Vec::new();
+    thumbnail.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+    Ok(base64(&png))
+}
+
+/// Oblique projection of CIELAB: a* runs left to right, L* up, and b* recedes
+/// at 30° so the plot reads as a box seen from the front.
+fn project(lab: [f32; 3]) -> (f32, f32) {
+    const ORIGIN: (f32, f32) = (260.0, 410.0);
+    let (depth_x, depth_y) = (
+        0.8 * 30f32.to_radians().cos(),
+        0.8 * 30f32.to_radians().sin(),
+    );
+    let [l, a, b] = lab;
+    (
+        ORIGIN.0 + a * 1.6 + b * depth_x,
+        ORIGIN.1 - l * 3.2 - b * depth_y,
+    )
+}
+
+fn lab_scatter(samples: &[[u8; 3]], clusters: &[ColorCluster]) -> String {
+    let mut svg = String::from(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"520\" height=\"460\" \
+         viewBox=\"0 0 520 460\" role=\"img\">\n<title>CIELAB scatter of samples and centroids</title>\n\
+         <rect width=\"520\" height=\"460\" fill=\"#fff\" stroke=\"#ccc\"/>\n",
+    );
+
+    // Floor square at L* = 0, the L* axis and the a*/b* axes through it
+    let line = |svg: &mut String, from: [f32; 3], to: [f32; 3], stroke: &str| {
+        let ((x1, y1), (x2, y2)) = (project(from), project(to));
+        svg.push_str(&format!(
+            "<line x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" y2=\"{y2:.1}\" stroke=\"{stroke}\"/>\n"
+        ));
+    };
+    let corners = [
+        [0.0, -100.0, -100.0],
+        [0.0, 100.0, -100.0],
+        [0.0, 100.0, 100.0],
+        [0.0, -100.0, 100.0],
+    ];
+    for (i, corner) in corners.iter().enumerate() {
+        line(&mut svg, *corner, corners[(i + 1) % 4], "#ddd");
+    }
+    line(&mut svg, [0.0, -100.0, 0.0], [0.0, 100.0, 0.0], "#999");
+    line(&mut svg, [0.0, 0.0, -100.0], [0.0, 0.0, 100.0], "#999");
+    line(&mut svg, [0.0, 0.0, 0.0], [100.0, 0.0, 0.0], "#999");
+    for (label, at) in [
+        ("a*", [0.0, 108.0, 0.0]),
+        ("b*", [0.0, 0.0, 112.0]),
+        ("L*", [104.0, 0.0, 0.0]),
+    ] {
+        let (x, y) = project(at);
+        svg.push_str(&format!(
+            "<text x=\"{x:.1}\" y=\"{y:.1}\" font-size=\"12\" fill=\"#666\">{label}</text>\n"
+        ));
+    }
+
+    // Far points first so nearer ones paint over them
+    let mut points: Vec<([f32; 3], [u8; 3])> = samples
+        .iter()
+        .map(|&rgb| (color::rgb8_to_lab(rgb), rgb))
+        .collect();
+    points.sort_by(|a, b| b.0[2].total_cmp(&a.0[2]));
+    for (lab, rgb) in points {
+        let (x, y) = project(lab);
+        svg.push_str(&format!(
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"2\" fill=\"{}\" fill-opacity=\"0.6\"/>\n",
+            color::rgb_to_hex(rgb)
+        ));
+    }
+
+    let mut centroids: Vec<&ColorCluster> = clusters.iter().collect();
+    centroids.sort_by(|a, b| b.lab[2].total_cmp(&a.lab[2]));
+    for cluster in centroids {
+        let (x, y) = project(cluster.lab);
+        let hex = color::rgb_to_hex(rgb(cluster.rgb));
+        svg.push_str(&format!(
+            "<circle cx=\"{x:.1}\" cy=\"{y:.1}\" r=\"{:.1}\" fill=\"{hex}\" stroke=\"#000\">\
+             <title>{hex} share {:.1}% L* {:.0} a* {:.0} b* {:.0}</title></circle>\n",
+            3.0 + 22.0 * cluster.share.sqrt(),
+            cluster.share * 100.0,
+            cluster.lab[0],
+            cluster.lab[1],
+            cluster.lab[2]
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn palette_section(clusters: &[ColorCluster], roles: &[RoleAssignment]) -> String {
+    let mut out = String::from("<div class=\"strip\">");
+    for cluster in clusters {
+        let hex = color::rgb_to_hex(rgb(cluster.rgb));
+        out.push_str(&format!(
+            "<div style=\"flex: {:.4}; background: {hex}\" title=\"{hex} {:.1}%\"></div>",
+            cluster.share,
+            cluster.share * 100.0
+        ));
+    }
+    out.push_str("</div>\n");
+
+    let lab = |role: ColorRole| roles.iter().find(|a| a.role == role).map(|a| a.lab);
+    let (bg, text) = (lab(ColorRole::Background), lab(ColorRole::Text));
+    let ratio = |against: Option<[f32; 3]>, itself: bool, lab: [f32; 3]| match against {
+        Some(other) if !itself => format!("{:.2}:1", color::calculate_contrast_ratio(lab, other)),
+        _ => "-".to_string(),
+    };
+    out.push_str(
+        "<table>\n<tr><th></th><th>Role</th><th>Hex</th><th>vs bg</th><th>vs text</th>\
+         <th>Origin</th><th>Cluster</th></tr>\n",
+    );
+    for assignment in roles {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            swatch(&assignment.hex),
+            assignment.role,
+            assignment.hex,
+            ratio(bg, assignment.role == ColorRole::Background, assignment.lab),
+            ratio(text, assignment.role == ColorRole::Text, assignment.lab),
+            escape(assignment.origin.as_deref().unwrap_or("-")),
+            assignment
+                .source_cluster_index
+                .map_or("-".to_string(), |index| format!("#{index}"))
+        ));
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+fn stage_section(pairwise: &PairwiseDebug) -> String {
+    let mut out = String::from("<ol>\n");
+    for SolverStage { name, description } in &pairwise.stages {
+        let class = if *name == pairwise.pass_mode {
+            "used"
+        } else {
+            "unused"
+        };
+        out.push_str(&format!(
+            "<li class=\"{class}\"><code>{name}</code>: {}</li>\n",
+            escape(description)
+        ));
+    }
+    out.push_str(&format!(
+        "</ol>\n<p>The solver settled on <code>{}</code> after evaluating {} accent/active pairs.</p>\n",
+        escape(&pairwise.pass_mode),
+        pairwise.evaluated_pairs
+    ));
+    out
+}
+
+fn check_cell(check: &GuardrailCheck) -> String {
+    if check.waived {
+        return format!("<td class=\"waived\">{:.1} (n/a)</td>", check.value);
+    }
+    let (class, relation, floor) = if check.passes_strict() {
+        ("pass", "≥", check.strict_min)
+    } else if check.passes_relaxed() {
+        ("relaxed", "≥", check.relaxed_min)
+    } else {
+        ("fail", "<", check.relaxed_min)
+    };
+    format!(
+        "<td class=\"{class}\">{:.2} {relation} {:.1}</td>",
+        check.value, floor
+    )
+}
+
+fn pairs_table(pairwise: &PairwiseDebug) -> String {
+    let mut rows: Vec<(bool, &PairwiseDebugEntry)> = vec![(true, &pairwise.winning_pair)];
+    rows.extend(
+        pairwise
+            .top_pairs
+            .iter()
+            .filter(|pair| {
+                (&pair.accent_hex, &pair.active_hex)
+                    != (
+                        &pairwise.winning_pair.accent_hex,
+                        &pairwise.winning_pair.active_hex,
+                    )
+            })
+            .map(|pair| (false, pair)),
+    );
+
+    let mut out = String::from("<table>\n<tr><th>Accent</th><th>Active</th>");
+    for check in pairwise.winning_pair.guardrail_checks() {
+        out.push_str(&format!("<th>{}</th>", escape(check.name)));
+    }
+    out.push_str("</tr>\n");
+    for (winner, pair) in rows {
+        out.push_str(&format!(
+            "<tr{}><td>{} {} <small>{}</small></td><td>{} {} <small>{}</small></td>",
+            if winner { " class=\"winner\"" } else { "" },
+            swatch(&pair.accent_hex),
+            pair.accent_hex,
+            escape(&pair.accent_origin),
+            swatch(&pair.active_hex),
+            pair.active_hex,
+            escape(&pair.active_origin)
+        ));
+        for check in pair.guardrail_checks() {
+            out.push_str(&check_cell(&check));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n<p>The chosen pair is in bold; the rest are the top-scoring pairs of the stage that produced it.</p>\n");
+    out
+}
+
+fn candidate_table(
+    candidates: &[PairCandidateDebug],
+    checks: impl Fn(&PairCandidateDebug) -> [GuardrailCheck; 2],
+) -> String {
+    // Raw clusters before adjusted and synthetic colors, then by score
+    let mut ranked: Vec<&PairCandidateDebug> = candidates.iter().collect();
+    ranked.sort_by(|a, b| {
+        a.provenance_rank
+            .cmp(&b.provenance_rank)
+            .then(b.base_score.total_cmp(&a.base_score))
+    });
+
+    let mut out = String::from(
+        "<table>\n<tr><th>#</th><th></th><th>Hex</th><th>Origin</th><th>Rank</th>\
+         <th>Score</th><th>vs bg</th><th>vs text</th></tr>\n",
+    );
+    for (index, candidate) in ranked.iter().enumerate() {
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td>",
+            index + 1,
+            swatch(&candidate.hex),
+            candidate.hex,
+            escape(&candidate.origin),
+            candidate.provenance_rank,
+            candidate.base_score
+        ));
+        for check in checks(candidate) {
+            out.push_str(&check_cell(&check));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+fn swatch(hex: &str) -> String {
+    format!(
+        "<span class=\"swatch\" style=\"background: {}\"></span>",
+        escape(hex)
+    )
+}
+
+fn rgb(value: RgbValue) -> [u8; 3] {
+    [value.r, value.g, value.b]
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Standard base64 with padding.
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::roles::{map_colors_to_roles, sample_palette, CvdMode, Preset, RoleOptions};
+    use image::{Rgb, RgbImage};
+
+    #[test]
+    fn base64_matches_rfc_4648_vectors() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn report_embeds_cover_plot_stage_and_guardrail_tables() {
+        let clusters = sample_palette();
+        let (roles, pairwise, roles_debug) =
+            map_colors_to_roles(&clusters, &RoleOptions::default(), true);
+        let pass_mode = pairwise.as_ref().expect("debug").pass_mode.clone();
+        let debug = DebugOutput {
+            pairwise,
+            roles: roles_debug,
+            samples: vec![[24, 26, 38], [214, 92, 64]],
+        };
+        let cover = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([1, 2, 3])));
+        let html = html_report(&ReportInput {
+            source: "covers/<a&b>.png",
+            cover: &cover,
+            clusters: &clusters,
+            roles: &roles,
+            debug: &debug,
+        })
+        .expect("report");
+
+        assert!(html.contains("<code>covers/&lt;a&amp;b&gt;.png</code>"));
+        assert!(html.contains("src=\"data:image/png;base64,iVBORw0KGgo"));
+        // Two samples and four centroids in the scatter
+        assert_eq!(html.matches("<circle").count(), 6);
+        assert!(html.contains(&format!("<li class=\"used\"><code>{pass_mode}</code>")));
+        assert!(html.contains("<th>accent/bg</th>"));
+        assert!(html.contains("<h2>Active candidates</h2>"));
+        for role in &roles {
+            assert!(html.contains(&role.hex));
+        }
+        // Nothing is fetched when the file is opened
+        assert!(!html.contains("<link") && !html.contains("src=\"http"));
+    }
+
+    #[test]
+    fn stage_list_shows_the_floors_of_the_run() {
+        let clusters = sample_palette();
+        let options = RoleOptions {
+            preset: Preset::builtin("high-contrast").expect("builtin preset"),
+            cvd: Some(CvdMode::Deutan),
+            ..RoleOptions::default()
+        };
+        let (_, pairwise, _) = map_colors_to_roles(&clusters, &options, true);
+        let html = stage_section(&pairwise.expect("debug"));
+        assert!(html.contains("<code>strict</code>: accent/bg 7.0:1"));
+        assert!(html.contains("active/text 7.0:1"));
+        assert_eq!(html.matches("ΔE 20 under deutan simulation").count(), 2);
+        assert!(html.contains("otherwise over every pair"));
+    }
+}
@@ -1,88 +1,49 @@
-use std::cmp::Ordering;
+use std::collections::BTreeMap;
 use std::env;
-use std::path::PathBuf;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use anyhow::{Context, Result};
-use clap::Parser;
-use serde::Serialize;
-
-mod color;
-mod image_pipeline;
-mod kmeans;
-
-use crate::image_pipeline::{prepare_samples, SampleParams};
-use crate::kmeans::{run_kmeans, KMeansConfig};
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
-enum ColorRole {
-    Background,
-    Text,
-    Accent,
-    Border,
-    ActiveItem,
-    HighlightText,
-    Frame,
-    InactiveItem,
-    ProgressBar,
-    Scrollbar,
-    HeaderAccent,
-}
-
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct RoleAssignment {
-    role: ColorRole,
-    rgb: RgbValue,
-    hsv: [f32; 3],
-    lab: [f32; 3],
-    hex: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    source_cluster_index: Option<usize>,
-    confidence: f32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    contrast_against_background: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    contrast_against_text: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    origin: Option<String>,
-}
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use rmpc_theme_gen::audit::{audit_theme, AuditLevel, PairKind};
+use rmpc_theme_gen::color;
+use rmpc_theme_gen::generator::ClusteringParams;
+use rmpc_theme_gen::image_pipeline::{
+    AlphaMode, CropRect, ResizeFilter, SampleParams, WeightingMode,
+};
+use rmpc_theme_gen::kmeans::{KMeansAlgorithm, KMeansInit};
+use rmpc_theme_gen::preview::{preview_image, terminal_preview};
+use rmpc_theme_gen::report::{html_report, ReportInput};
+use rmpc_theme_gen::roles::{
+    ColorCluster, CvdMode, DebugOutput, GuardrailParams, PinConflict, Preset, RoleAssignment,
+    RolePins, RoleSolver,
+};
+use rmpc_theme_gen::{ThemeConfig, ThemeGenerator};
 
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
-const ACCENT_BG_MIN: f32 = 4.5;
-const ACCENT_TEXT_MIN: f32 = 4.5;
-const ACTIVE_BG_MIN: f32 = 3.5;
-const ACTIVE_BG_RELAXED_MIN: f32 = 3.5;
-const ACTIVE_TEXT_MIN: f32 = 4.5;
-const PEER_CONTRAST_MIN: f32 = 4.5;
-const PEER_DELTA_E_MIN: f32 = 25.0;
-const BRIGHTNESS_SEPARATION_MIN: f32 = 25.0;
-const RELAXED_PEER_CONTRAST_MIN: f32 = 3.5;
-const RELAXED_PEER_DELTA_E_MIN: f32 = 20.0;
-const HIGHLIGHT_TEXT_MIN_CONTRAST: f32 = 4.5;
-const HIGHLIGHT_TEXT_BG_MIN: f32 = 1.5;
-const FRAME_BG_MIN: f32 = 3.0;
-const FRAME_TEXT_MIN: f32 = 2.0;
-const MIN_CHROMA_THRESHOLD: f32 = 0.05;
-const MIN_HUE_DELTA_ACTIVE_BG: f32 = 30.0;
-const MIN_HUE_DELTA_ACTIVE_TEXT: f32 = 30.0;
-const MIN_HUE_DELTA_ACCENT_ACTIVE: f32 = 25.0;
-const MIN_HUE_DELTA_ACCENT_BG: f32 = 20.0;
-
 #[derive(Parser, Debug)]
 #[command(name = "rmpc-theme-gen", version = APP_VERSION)]
 #[command(about = "Generate rmpc theme from album art", long_about = None)]
+#[command(subcommand_negates_reqs = true, args_conflicts_with_subcommands = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Path to album art image
-    #[arg(short, long)]
-    image: PathBuf,
+    #[arg(short, long, required = true)]
+    image: Option<PathBuf>,
 
     /// Number of color clusters to extract
     #[arg(short, long, default_value = "30")]
     k: usize,
 
-    /// Color space for clustering (CIELAB, RGB, HSL, HSV, YUV, CIELUV)
+    /// Color space for clustering (CIELAB, RGB, HSL, HSV, YUV, CIELUV, OKLAB, OKLCH,
+    /// LINEAR-RGB, JZAZBZ, CAM16-UCS)
     #[arg(short, long, default_value = "CIELAB")]
     space: String,
 
@@ -98,27 +59,145 @@ struct Args {
     #[arg(long)]
     disable_scrollbar: bool,
 
+    /// Skip embedded ICC profile conversion and sample raw pixel values
+    #[arg(long)]
+    no_color_management: bool,
+
+    /// How transparent pixels are sampled: skip, weight (by alpha) or matte
+    #[arg(long, default_value = "skip")]
+    alpha: AlphaMode,
+
+    /// Alpha (0-255) below which a pixel counts as transparent
+    #[arg(long, default_value_t = 128)]
+    alpha_threshold: u8,
+
+    /// Background color (#RRGGBB) used with --alpha matte
+    #[arg(long, default_value = "#000000", value_parser = parse_hex_color)]
+    matte: [u8; 3],
+
+    /// Crop uniform frames, letterbox bars and transparent padding before sampling
+    #[arg(long)]
+    crop_borders: bool,
+
+    /// Down-weight busy high-contrast regions such as cover typography
+    #[arg(long)]
+    suppress_text: bool,
+
+    /// Per-pixel sample weighting: uniform, center, saliency or edge-suppressed
+    #[arg(long, default_value = "uniform")]
+    weighting: WeightingMode,
+
+    /// Bucket samples into a histogram with this many bits per RGB channel (5-6 is
+    /// typical) and cluster the weighted buckets instead of every sample
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=8))]
+    quantize_bits: Option<u8>,
+
+    /// K-means assignment strategy: hamerly (bounds skip most distance checks) or
+    /// lloyd (plain scan); both give identical results
+    #[arg(long, default_value = "hamerly")]
+    kmeans: KMeansAlgorithm,
+
+    /// Centroid seeding: kmeans++ or parallel (k-means||, multi-threaded)
+    #[arg(long, default_value = "kmeans++")]
+    kmeans_init: KMeansInit,
+
+    /// Run k-means this many times with derived seeds and keep the lowest-inertia result
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..=64))]
+    n_init: u32,
+
+    /// Shrink the image so its longest side is about this many pixels before
+    /// sampling; JPEGs are downscaled during decode
+    #[arg(long, value_parser = clap::value_parser!(u32).range(16..))]
+    target_size: Option<u32>,
+
+    /// Downscale filter: lanczos3 or area (box average, faster)
+    #[arg(long, default_value = "lanczos3")]
+    resize_filter: ResizeFilter,
+
+    /// Role solver: greedy (pick roles one after another) or global (beam search
+    /// over complete assignments)
+    #[arg(long, default_value = "greedy")]
+    solver: RoleSolver,
+
+    /// Keep accent, active, background and highlight text distinguishable under
+    /// simulated color vision deficiency: protan, deutan, tritan or all
+    #[arg(long)]
+    cvd: Option<CvdMode>,
+
+    /// Fix a role to a color (ROLE=#RRGGBB) and solve the others around it; ROLE=auto
+    /// unpins a role set in --config. Repeatable
+    #[arg(long = "pin", value_name = "ROLE=COLOR", value_parser = parse_pin)]
+    pins: Vec<(String, Option<[u8; 3]>)>,
+
+    /// Named preset: vivid, muted, pastel, monochrome, high-contrast, or one
+    /// defined under "presets" in --config
+    #[arg(long)]
+    preset: Option<String>,
+
+    /// JSON config file with "pins" and "presets" sections; --pin overrides its pins
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print a truecolor mockup of the themed UI and the cluster and role swatches
+    /// to stderr
+    #[arg(long)]
+    preview_terminal: bool,
+
+    /// Write a self-contained HTML report of the clustering and solver decisions
+    #[arg(long)]
+    report: Option<PathBuf>,
+
     /// Emit debug diagnostics (can also be set via RMPC_THEME_DEBUG=1)
     #[arg(long)]
     debug: bool,
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
-#[serde(rename_all = "camelCase")]
-struct RgbValue {
-    r: u8,
-    g: u8,
-    b: u8,
-}
-
-#[derive(Debug, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct ColorCluster {
-    rgb: RgbValue,
-    hsv: [f32; 3],
-    lab: [f32; 3],
-    count: usize,
-    share: f64,
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Regenerate a theme from the provenance block of a previous JSON output and
+    /// verify the RON is byte-identical
+    Replay {
+        /// JSON analysis written by an earlier run
+        output: PathBuf,
+
+        /// Existing theme file to compare byte-for-byte with the regenerated one
+        #[arg(long)]
+        theme: Option<PathBuf>,
+
+        /// Also write the regenerated theme to this path
+        #[arg(long)]
+        theme_output: Option<PathBuf>,
+
+        /// Replay even when the recorded Cargo features or SIMD dispatch path
+        /// differ from this build
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check every fg/bg pair of a rendered theme against WCAG contrast levels and
+    /// exit non-zero if any pair fails
+    Audit {
+        /// Theme file (RON) to audit
+        theme: PathBuf,
+
+        /// WCAG level the theme must meet: aa or aaa
+        #[arg(long, default_value = "aa")]
+        level: AuditLevel,
+
+        /// Also report APCA lightness contrast (Lc) and fail pairs below Lc 60 for
+        /// text or Lc 30 for borders and bars
+        #[arg(long)]
+        apca: bool,
+    },
+    /// Render a PNG mockup of the rmpc layout in the theme of a previous JSON
+    /// output, next to its cover art and cluster palette
+    Preview {
+        /// JSON analysis written by an earlier run
+        analysis: PathBuf,
+
+        /// PNG file to write
+        #[arg(short, long)]
+        output: PathBuf,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -127,2132 +206,500 @@ struct ThemeGenOutput {
     version: String,
     clusters: Vec<ColorCluster>,
     role_assignments: Vec<RoleAssignment>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pin_conflicts: Vec<PinConflict>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cvd_conflicts: Vec<PinConflict>,
     total_samples: usize,
     iterations: usize,
     duration_ms: f64,
     color_space: String,
-    scrollbar_enabled: bool,
+    bits_per_channel: u8,
+    icc_profile_applied: bool,
+    transparent_fraction: f32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    debug: Option<DebugOutput>,
-}
-
-#[derive(Debug, Serialize, Default)]
-#[serde(rename_all = "camelCase")]
-struct DebugOutput {
+    crop: Option<CropRect>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pairwise: Option<PairwiseDebug>,
+    unique_buckets: Option<usize>,
+    scrollbar_enabled: bool,
+    provenance: Provenance,
     #[serde(skip_serializing_if = "Option::is_none")]
-    roles: Option<RolesDebug>,
+    debug: Option<DebugOutput>,
 }
 
-#[derive(Debug, Serialize)]
+/// Everything that determines the generated theme. Generation reads its settings
+/// from here only, so `replay` can rebuild a run from a saved JSON output.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RolesDebug {
-    highlight_text: RoleDebugEntry,
-    frame: RoleDebugEntry,
-    playing_row: RoleDebugEntry,
-}
-
-#[derive(Debug, Serialize)]
+struct Provenance {
+    crate_version: String,
+    features: Vec<String>,
+    /// SIMD path taken at runtime (`avx2`, `wide` or `scalar`); absent in
+    /// recordings made before it was tracked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    simd_dispatch: Option<String>,
+    input: InputFingerprint,
+    #[serde(flatten)]
+    config: ThemeConfig,
+    guardrails: GuardrailParams,
+    /// SHA-256 of the generated RON theme.
+    #[serde(default)]
+    theme_sha256: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct RoleDebugEntry {
-    hex: String,
-    origin: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    contrast_against_active: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    contrast_against_background: Option<f32>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    contrast_against_text: Option<f32>,
-}
-
-/// Select background color: prefer most dominant with reasonable saturation/lightness
-fn select_background(clusters: &[ColorCluster]) -> (usize, f32) {
-    // Prefer dominant colors with moderate properties
-    for (idx, cluster) in clusters.iter().enumerate() {
-        let s = cluster.hsv[1];
-        let l = cluster.lab[0];
-
-        // Good background: low-mid saturation, reasonable lightness
-        if s < 0.4 && l > 15.0 && l < 85.0 {
-            return (idx, 0.9);
-        }
+struct InputFingerprint {
+    sha256: String,
+    bytes: u64,
+}
+
+impl InputFingerprint {
+    fn of(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("Failed to read image {}", path.display()))?;
+        Ok(Self {
+            sha256: sha256_hex(&data),
+            bytes: data.len() as u64,
+        })
     }
-
-    // Fallback: most dominant color regardless of properties
-    (0, 0.5)
 }
 
-/// Select text color: highest contrast against background
-fn select_text_color(clusters: &[ColorCluster], bg_lab: [f32; 3]) -> (usize, f32) {
-    let mut best_idx = 0;
-    let mut best_contrast = 0.0;
-
-    for (idx, cluster) in clusters.iter().enumerate() {
-        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
-        if contrast > best_contrast {
-            best_contrast = contrast;
-            best_idx = idx;
-        }
+fn enabled_features() -> Vec<String> {
+    let mut features = Vec::new();
+    if cfg!(feature = "simd") {
+        features.push("simd".to_string());
     }
-
-    // Check if we meet WCAG AA standard (4.5:1)
-    let confidence = if best_contrast >= 4.5 { 0.9 } else { 0.6 };
-    (best_idx, confidence)
-}
-
-#[derive(Clone, Debug)]
-struct Candidate {
-    index: usize,
-    score: f32,
-}
-
-fn sort_candidates_desc(candidates: &mut Vec<Candidate>) {
-    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+    features
 }
 
-#[derive(Clone, Debug)]
-struct RoleColorCandidate {
-    lab: [f32; 3],
-    source_cluster_index: Option<usize>,
-    origin_label: String,
-    provenance_rank: u8,
-    base_score: f32,
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
 }
 
-impl RoleColorCandidate {
-    fn confidence(&self) -> f32 {
-        match self.provenance_rank {
-            0 => {
-                if self.base_score > 2.0 {
-                    0.9
-                } else {
-                    0.75
-                }
-            }
-            1 => 0.7,
-            _ => 0.45,
-        }
-    }
+/// The part of a saved JSON output that `replay` needs.
+#[derive(Debug, Deserialize)]
+struct RecordedOutput {
+    provenance: Provenance,
 }
 
-#[derive(Clone, Debug)]
-struct PairwiseMetrics {
-    accent_bg: f32,
-    accent_text: f32,
-    accent_active: f32,
-    active_bg: f32,
-    active_text: f32,
-    delta_e: f32,
-    accent_l: f32,
-    active_l: f32,
-    accent_hue: f32,
-    accent_chroma: f32,
-    active_hue: f32,
-    active_chroma: f32,
-    background_hue: f32,
-    background_chroma: f32,
-    text_hue: f32,
-    text_chroma: f32,
+/// Settings read from `--config`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+struct ConfigFile {
+    pins: RolePins,
+    /// User presets by name; these shadow built-in presets of the same name.
+    presets: BTreeMap<String, Preset>,
 }
 
-impl PairwiseMetrics {
-    fn min_contrast(&self) -> f32 {
-        self.accent_bg
-            .min(self.accent_text)
-            .min(self.accent_active)
-            .min(self.active_bg)
-            .min(self.active_text)
-    }
-
-    fn brightness_separation(&self) -> f32 {
-        (self.accent_l - self.active_l).abs()
+impl ConfigFile {
+    fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid config {}", path.display()))
     }
 
-    fn avg_contrast(&self) -> f32 {
-        (self.accent_bg + self.accent_text + self.accent_active + self.active_bg + self.active_text)
-            / 5.0
-    }
-}
-
-fn hue_separation_ok(h1: f32, c1: f32, h2: f32, c2: f32, min_delta: f32) -> bool {
-    if c1 < MIN_CHROMA_THRESHOLD || c2 < MIN_CHROMA_THRESHOLD {
-        return true;
+    fn preset(&self, name: &str) -> Result<Preset> {
+        if let Some(preset) = self.presets.get(name).copied().or(Preset::builtin(name)) {
+            return Ok(preset);
+        }
+        let known: Vec<&str> = Preset::BUILTIN_NAMES
+            .into_iter()
+            .chain(self.presets.keys().map(String::as_str))
+            .collect();
+        anyhow::bail!(
+            "Unknown preset '{name}' (expected one of {})",
+            known.join(", ")
+        )
     }
-    color::delta_hue_degrees(h1, h2) >= min_delta
 }
 
-fn oklch_to_lab(l: f32, c: f32, h_deg: f32) -> [f32; 3] {
-    let oklch = [l, c, h_deg];
-    let oklab = color::oklch_to_oklab(oklch);
-    let rgb = color::oklab_to_rgb8(oklab);
-    color::rgb8_to_lab(rgb)
+fn parse_hex_color(value: &str) -> std::result::Result<[u8; 3], String> {
+    color::hex_to_rgb(value).ok_or_else(|| format!("invalid hex color '{value}'"))
 }
 
-fn derive_active_with_hue(
-    background_lab: [f32; 3],
-    text_lab: [f32; 3],
-    accent_lab: [f32; 3],
-    bg_oklch: [f32; 3],
-    text_oklch: [f32; 3],
-    accent_oklch: [f32; 3],
-) -> Option<([f32; 3], String)> {
-    // Choose a target hue: prefer accent hue if distinct from background; otherwise flip background hue
-    let use_accent = hue_separation_ok(
-        accent_oklch[2],
-        accent_oklch[1],
-        bg_oklch[2],
-        bg_oklch[1],
-        MIN_HUE_DELTA_ACTIVE_BG,
-    );
-    let target_hue = if use_accent {
-        accent_oklch[2]
+fn parse_pin(value: &str) -> std::result::Result<(String, Option<[u8; 3]>), String> {
+    let (role, color) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected ROLE=COLOR, got '{value}'"))?;
+    let color = if color.trim().eq_ignore_ascii_case("auto") {
+        None
     } else {
-        (bg_oklch[2] + 180.0) % 360.0
+        Some(parse_hex_color(color.trim())?)
     };
-    let base_chroma = bg_oklch[1].max(accent_oklch[1]).max(0.06).min(0.18);
-
-    // Try darkening first (usually safer for highlight backgrounds), then lightening
-    let directions = [-1.0f32, 1.0f32];
-    for step in 1..=12 {
-        let delta = step as f32 * 3.0;
-        for &dir in &directions {
-            let l = (bg_oklch[0] + dir * delta).clamp(0.0, 1.0);
-            let lab = oklch_to_lab(l, base_chroma, target_hue);
-            let c_bg = color::calculate_contrast_ratio(lab, background_lab);
-            if c_bg < ACTIVE_BG_MIN {
-                continue;
-            }
-            let c_text = color::calculate_contrast_ratio(lab, text_lab);
-            if c_text < ACTIVE_TEXT_MIN {
-                continue;
-            }
-            // Enforce hue separation against background and text if both sides have chroma
-            if !hue_separation_ok(
-                target_hue,
-                base_chroma,
-                bg_oklch[2],
-                bg_oklch[1],
-                MIN_HUE_DELTA_ACTIVE_BG,
-            ) {
-                continue;
-            }
-            if !hue_separation_ok(
-                target_hue,
-                base_chroma,
-                text_oklch[2],
-                text_oklch[1],
-                MIN_HUE_DELTA_ACTIVE_TEXT,
-            ) {
-                continue;
-            }
-            let origin = if use_accent {
-                format!(
-                    "accent_hue:{:.1} L{:+.1}",
-                    target_hue,
-                    l * 100.0 - background_lab[0]
-                )
-            } else {
-                format!(
-                    "bg_hue_rotated:{:.1} L{:+.1}",
-                    target_hue,
-                    l * 100.0 - background_lab[0]
-                )
-            };
-            return Some((lab, origin));
-        }
-    }
-    None
-}
-
-#[derive(Clone, Debug)]
-struct PairwiseResult {
-    accent: RoleColorCandidate,
-    active: RoleColorCandidate,
-    metrics: PairwiseMetrics,
-    provenance_score: u8,
-}
-
-#[derive(Debug, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct PairwiseDebugEntry {
-    accent_hex: String,
-    accent_origin: String,
-    active_hex: String,
-    active_origin: String,
-    accent_bg: f32,
-    accent_text: f32,
-    accent_active: f32,
-    active_bg: f32,
-    active_text: f32,
-    delta_e: f32,
-    min_contrast: f32,
-    brightness_separation: f32,
-    provenance_score: u8,
-    accent_hue: f32,
-    accent_chroma: f32,
-    active_hue: f32,
-    active_chroma: f32,
-    background_hue: f32,
-    background_chroma: f32,
-    text_hue: f32,
-    text_chroma: f32,
-}
-
-#[derive(Debug, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct PairwiseDebug {
-    evaluated_pairs: usize,
-    pass_mode: String,
-    winning_pair: PairwiseDebugEntry,
-    top_pairs: Vec<PairwiseDebugEntry>,
-    accent_candidates: Vec<PairCandidateDebug>,
-    active_candidates: Vec<PairCandidateDebug>,
+    // Reject unknown roles at parse time so clap reports them against --pin
+    RolePins::default().set(role, color)?;
+    Ok((role.to_string(), color))
 }
 
-#[derive(Debug, Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-struct PairCandidateDebug {
-    hex: String,
-    origin: String,
-    provenance_rank: u8,
-    base_score: f32,
+struct Generated {
+    output: ThemeGenOutput,
+    theme_ron: String,
 }
 
-/// Rank accent color candidates: high saturation with good contrast
-fn rank_accent_candidates(
-    clusters: &[ColorCluster],
-    bg_lab: [f32; 3],
-    used_indices: &[usize],
-) -> Vec<Candidate> {
-    let mut candidates = Vec::new();
-
-    for (idx, cluster) in clusters.iter().enumerate() {
-        if used_indices.contains(&idx) {
-            continue;
-        }
+/// Run the generator using only the settings recorded in `provenance`.
+fn generate(provenance: &Provenance, debug_enabled: bool) -> Result<Generated> {
+    let start = Instant::now();
+    let config = ThemeConfig {
+        debug: debug_enabled,
+        ..provenance.config.clone()
+    };
+    let image = &config.sampling.path;
+    let theme = ThemeGenerator::new(config.clone())
+        .generate(image)
+        .with_context(|| format!("Failed to generate theme from {}", image.display()))?;
+    let theme_ron = theme.to_ron();
+    let provenance = Provenance {
+        theme_sha256: sha256_hex(theme_ron.as_bytes()),
+        ..provenance.clone()
+    };
 
-        let s = cluster.hsv[1];
-        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
-        let score = s * 2.0 + (contrast / 21.0) * 3.0;
+    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
 
-        if contrast > 1.5 {
-            candidates.push(Candidate { index: idx, score });
-        }
-    }
+    let output = ThemeGenOutput {
+        version: APP_VERSION.to_string(),
+        clusters: theme.clusters,
+        role_assignments: theme.roles,
+        pin_conflicts: theme.pin_conflicts,
+        cvd_conflicts: theme.cvd_conflicts,
+        total_samples: theme.total_samples,
+        iterations: theme.iterations,
+        duration_ms,
+        color_space: config.clustering.space.clone(),
+        bits_per_channel: theme.bits_per_channel,
+        icc_profile_applied: theme.icc_profile_applied,
+        transparent_fraction: theme.transparent_fraction,
+        crop: theme.crop,
+        unique_buckets: theme.unique_buckets,
+        scrollbar_enabled: theme.scrollbar_enabled,
+        provenance,
+        debug: theme.debug,
+    };
 
-    sort_candidates_desc(&mut candidates);
-    candidates
+    Ok(Generated { output, theme_ron })
 }
 
-/// Select border color: mid-saturation, distinct from background
-fn select_border_color(
-    clusters: &[ColorCluster],
-    bg_lab: [f32; 3],
-    used_indices: &[usize],
-) -> (usize, f32) {
-    let mut best_idx = 0;
-    let mut best_score = 0.0;
-
-    for (idx, cluster) in clusters.iter().enumerate() {
-        if used_indices.contains(&idx) {
-            continue;
-        }
-
-        let s = cluster.hsv[1];
-        let delta_e = color::delta_e_cie76(bg_lab, cluster.lab);
-
-        // Prefer mid-saturation with good perceptual distance
-        let score = if s >= 0.2 && s <= 0.6 {
-            delta_e / 100.0 + s
-        } else {
-            delta_e / 100.0
+impl Provenance {
+    fn from_args(args: &Args, image: &Path) -> Result<Self> {
+        // Record an absolute path so the run can be replayed from any directory
+        let path = std::fs::canonicalize(image).unwrap_or_else(|_| image.to_path_buf());
+        let config = match &args.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
         };
-
-        if score > best_score && delta_e > 20.0 {
-            best_score = score;
-            best_idx = idx;
-        }
-    }
-
-    let confidence = if best_score > 0.5 { 0.8 } else { 0.5 };
-    (best_idx, confidence)
-}
-
-/// Rank active item color candidates: bright and saturated
-fn rank_active_item_candidates(
-    clusters: &[ColorCluster],
-    bg_lab: [f32; 3],
-    used_indices: &[usize],
-) -> Vec<Candidate> {
-    let mut candidates = Vec::new();
-
-    for (idx, cluster) in clusters.iter().enumerate() {
-        if used_indices.contains(&idx) {
-            continue;
-        }
-
-        let s = cluster.hsv[1];
-        let v = cluster.hsv[2];
-        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
-        let score = v + s + (contrast / 21.0);
-
-        if v > 0.4 {
-            candidates.push(Candidate { index: idx, score });
+        let mut pins = config.pins;
+        for (role, color) in &args.pins {
+            pins.set(role, *color).map_err(anyhow::Error::msg)?;
         }
-    }
-
-    sort_candidates_desc(&mut candidates);
-    candidates
-}
-
-#[derive(Clone, Copy)]
-struct GuardrailConfig {
-    min_contrast_bg: f32,
-    min_contrast_text: f32,
-    min_contrast_peer: Option<f32>,
-    min_delta_e_peer: Option<f32>,
-    adjust_step: f32,
-    max_adjust_steps: usize,
-}
-
-#[derive(Clone, Copy)]
-struct PairwiseGuardrails {
-    min_accent_vs_bg: f32,
-    min_accent_vs_text: f32,
-    min_active_vs_bg: f32,
-    min_active_vs_text: f32,
-    min_peer_contrast: f32,
-    min_peer_delta_e: f32,
-    min_brightness_separation: f32,
-}
-
-fn contrast_metrics(lab: [f32; 3], bg_lab: [f32; 3], text_lab: [f32; 3]) -> (f32, f32) {
-    let contrast_bg = color::calculate_contrast_ratio(lab, bg_lab);
-    let contrast_text = color::calculate_contrast_ratio(lab, text_lab);
-    (contrast_bg, contrast_text)
-}
-
-fn meets_guardrails(
-    contrast_bg: f32,
-    contrast_text: f32,
-    config: &GuardrailConfig,
-    peer_contrast: Option<f32>,
-    peer_delta_e: Option<f32>,
-) -> bool {
-    if contrast_bg < config.min_contrast_bg || contrast_text < config.min_contrast_text {
-        return false;
-    }
-
-    if let Some(min_peer_contrast) = config.min_contrast_peer {
-        if peer_contrast.map_or(true, |actual| actual < min_peer_contrast) {
-            return false;
-        }
-    }
-
-    if let Some(min_peer_delta_e) = config.min_delta_e_peer {
-        if peer_delta_e.map_or(true, |actual| actual < min_peer_delta_e) {
-            return false;
-        }
-    }
-
-    true
-}
-
-fn lab_close(a: [f32; 3], b: [f32; 3], tol: f32) -> bool {
-    color::delta_e_cie76(a, b) < tol
-}
-
-fn collect_adjusted_variants(
-    base_lab: [f32; 3],
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    config: &GuardrailConfig,
-    peer_lab: Option<[f32; 3]>,
-) -> Vec<([f32; 3], f32)> {
-    let mut variants = Vec::new();
-
-    for direction in [-1.0f32, 1.0f32] {
-        let mut candidate = base_lab;
-        for _ in 0..config.max_adjust_steps {
-            candidate[0] = (candidate[0] + direction * config.adjust_step).clamp(0.0, 100.0);
-            let (contrast_bg, contrast_text) = contrast_metrics(candidate, bg_lab, text_lab);
-            let peer_metrics = peer_lab.map(|peer| {
-                (
-                    color::calculate_contrast_ratio(candidate, peer),
-                    color::delta_e_cie76(candidate, peer),
-                )
-            });
-            let (peer_contrast, peer_delta_e) = peer_metrics.unwrap_or((f32::NAN, f32::NAN));
-            if meets_guardrails(
-                contrast_bg,
-                contrast_text,
-                config,
-                peer_lab.map(|_| peer_contrast),
-                peer_lab.map(|_| peer_delta_e),
-            ) {
-                if !variants
-                    .iter()
-                    .any(|(existing, _)| lab_close(*existing, candidate, 0.5))
-                {
-                    variants.push((candidate, candidate[0] - base_lab[0]));
-                }
-                break;
-            }
-        }
-    }
-
-    variants
-}
-
-fn push_candidate_if_unique(
-    candidates: &mut Vec<RoleColorCandidate>,
-    candidate: RoleColorCandidate,
-) {
-    if !candidates
-        .iter()
-        .any(|existing| lab_close(existing.lab, candidate.lab, 0.5))
-    {
-        candidates.push(candidate);
-    }
-}
-
-fn cmp_f32_desc(a: f32, b: f32) -> Ordering {
-    match (a.is_nan(), b.is_nan()) {
-        (true, true) => Ordering::Equal,
-        (true, false) => Ordering::Less,
-        (false, true) => Ordering::Greater,
-        (false, false) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
-    }
-}
-
-fn synthesize_color_between(
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    config: &GuardrailConfig,
-    peer_lab: Option<[f32; 3]>,
-) -> Option<[f32; 3]> {
-    let mut t = -0.3f32;
-    while t <= 1.3 {
-        let candidate = [
-            bg_lab[0] + (text_lab[0] - bg_lab[0]) * t,
-            bg_lab[1] + (text_lab[1] - bg_lab[1]) * t,
-            bg_lab[2] + (text_lab[2] - bg_lab[2]) * t,
-        ];
-        let (contrast_bg, contrast_text) = contrast_metrics(candidate, bg_lab, text_lab);
-        let peer_metrics = peer_lab.map(|peer| {
-            (
-                color::calculate_contrast_ratio(candidate, peer),
-                color::delta_e_cie76(candidate, peer),
-            )
-        });
-        let (peer_contrast, peer_delta_e) = peer_metrics.unwrap_or((f32::NAN, f32::NAN));
-        if meets_guardrails(
-            contrast_bg,
-            contrast_text,
-            config,
-            peer_lab.map(|_| peer_contrast),
-            peer_lab.map(|_| peer_delta_e),
-        ) {
-            return Some(candidate);
-        }
-        t += 0.05;
-    }
-    None
-}
-
-fn build_accent_candidates(
-    clusters: &[ColorCluster],
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    used_indices: &[usize],
-) -> Vec<RoleColorCandidate> {
-    let guard = GuardrailConfig {
-        min_contrast_bg: ACCENT_BG_MIN,
-        min_contrast_text: ACCENT_TEXT_MIN,
-        min_contrast_peer: None,
-        min_delta_e_peer: None,
-        adjust_step: 4.0,
-        max_adjust_steps: 12,
-    };
-
-    let ranked = rank_accent_candidates(clusters, bg_lab, used_indices);
-    let mut results: Vec<RoleColorCandidate> = Vec::new();
-
-    for candidate in ranked.iter().take(12) {
-        let cluster = &clusters[candidate.index];
-        let (contrast_bg, contrast_text) = contrast_metrics(cluster.lab, bg_lab, text_lab);
-        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: cluster.lab,
-                    source_cluster_index: Some(candidate.index),
-                    origin_label: format!("cluster:{}", candidate.index),
-                    provenance_rank: 0,
-                    base_score: candidate.score,
+        let preset = match &args.preset {
+            Some(name) => config.preset(name)?,
+            None => Preset::default(),
+        };
+        Ok(Self {
+            crate_version: APP_VERSION.to_string(),
+            features: enabled_features(),
+            simd_dispatch: Some(rmpc_theme_gen::simd_dispatch().to_string()),
+            input: InputFingerprint::of(&path)?,
+            config: ThemeConfig {
+                sampling: SampleParams {
+                    path,
+                    stride: 4,
+                    min_lum: 0,
+                    max_samples: 300_000,
+                    max_dimension: Some(3200),
+                    target_size: args.target_size,
+                    resize_filter: args.resize_filter,
+                    seed: 1,
+                    color_management: !args.no_color_management,
+                    alpha_mode: args.alpha,
+                    alpha_threshold: args.alpha_threshold,
+                    matte: args.matte,
+                    crop_borders: args.crop_borders,
+                    suppress_text: args.suppress_text,
+                    weighting: args.weighting,
                 },
-            );
-        }
-
-        for (adjusted, delta_l) in
-            collect_adjusted_variants(cluster.lab, bg_lab, text_lab, &guard, None)
-        {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: adjusted,
-                    source_cluster_index: Some(candidate.index),
-                    origin_label: format!("adjusted:{}:{:+.1}", candidate.index, delta_l),
-                    provenance_rank: 1,
-                    base_score: candidate.score * 0.85,
+                clustering: ClusteringParams {
+                    space: args.space.clone(),
+                    k: args.k,
+                    max_iters: 40,
+                    tol: 1e-3,
+                    seed: 1,
+                    algorithm: args.kmeans,
+                    init: args.kmeans_init,
+                    n_init: args.n_init as usize,
+                    quantize_bits: args.quantize_bits,
                 },
-            );
-        }
-    }
-
-    if let Some(lab) = synthesize_color_between(bg_lab, text_lab, &guard, None) {
-        push_candidate_if_unique(
-            &mut results,
-            RoleColorCandidate {
-                lab,
-                source_cluster_index: None,
-                origin_label: "synthetic:midline".to_string(),
-                provenance_rank: 2,
-                base_score: 0.5,
+                solver: args.solver,
+                cvd: args.cvd,
+                pins,
+                preset,
+                scrollbar_enabled: !args.disable_scrollbar,
+                debug: false,
             },
-        );
-    }
-
-    for l in [25.0f32, 75.0f32] {
-        let candidate_lab = [l, 0.0, 0.0];
-        let (contrast_bg, contrast_text) = contrast_metrics(candidate_lab, bg_lab, text_lab);
-        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: candidate_lab,
-                    source_cluster_index: None,
-                    origin_label: format!("synthetic:gray-{:.0}", l),
-                    provenance_rank: 2,
-                    base_score: 0.35,
-                },
-            );
-        }
+            guardrails: GuardrailParams::current(),
+            theme_sha256: String::new(),
+        })
     }
-
-    results
 }
 
-fn build_active_candidates(
-    clusters: &[ColorCluster],
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    used_indices: &[usize],
-) -> Vec<RoleColorCandidate> {
-    let guard = GuardrailConfig {
-        min_contrast_bg: ACTIVE_BG_MIN,
-        min_contrast_text: ACTIVE_TEXT_MIN,
-        min_contrast_peer: None,
-        min_delta_e_peer: None,
-        adjust_step: 4.0,
-        max_adjust_steps: 12,
-    };
-
-    let ranked = rank_active_item_candidates(clusters, bg_lab, used_indices);
-    let mut results: Vec<RoleColorCandidate> = Vec::new();
-
-    for candidate in ranked.iter().take(12) {
-        let cluster = &clusters[candidate.index];
-        let (contrast_bg, contrast_text) = contrast_metrics(cluster.lab, bg_lab, text_lab);
-        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: cluster.lab,
-                    source_cluster_index: Some(candidate.index),
-                    origin_label: format!("cluster:{}", candidate.index),
-                    provenance_rank: 0,
-                    base_score: candidate.score,
-                },
-            );
-        }
-
-        for (adjusted, delta_l) in
-            collect_adjusted_variants(cluster.lab, bg_lab, text_lab, &guard, None)
-        {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: adjusted,
-                    source_cluster_index: Some(candidate.index),
-                    origin_label: format!("adjusted:{}:{:+.1}", candidate.index, delta_l),
-                    provenance_rank: 1,
-                    base_score: candidate.score * 0.8,
-                },
-            );
-        }
-    }
-
-    if let Some(lab) = synthesize_color_between(bg_lab, text_lab, &guard, None) {
-        push_candidate_if_unique(
-            &mut results,
-            RoleColorCandidate {
-                lab,
-                source_cluster_index: None,
-                origin_label: "synthetic:midline".to_string(),
-                provenance_rank: 2,
-                base_score: 0.4,
-            },
-        );
+fn write_theme(theme_path: &Path, theme_ron: &str) -> Result<()> {
+    // Ensure parent directory exists
+    if let Some(parent) = theme_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
     }
 
-    for l in [30.0f32, 50.0f32, 70.0f32] {
-        let candidate_lab = [l, 0.0, 0.0];
-        let (contrast_bg, contrast_text) = contrast_metrics(candidate_lab, bg_lab, text_lab);
-        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
-            push_candidate_if_unique(
-                &mut results,
-                RoleColorCandidate {
-                    lab: candidate_lab,
-                    source_cluster_index: None,
-                    origin_label: format!("synthetic:gray-{:.0}", l),
-                    provenance_rank: 2,
-                    base_score: 0.35,
-                },
-            );
-        }
-    }
-
-    results
-}
-
-fn candidate_list_for_debug(candidates: &[RoleColorCandidate]) -> Vec<PairCandidateDebug> {
-    candidates
-        .iter()
-        .map(|candidate| {
-            let rgb = color::lab_to_rgb8(candidate.lab);
-            PairCandidateDebug {
-                hex: color::rgb_to_hex(rgb),
-                origin: candidate.origin_label.clone(),
-                provenance_rank: candidate.provenance_rank,
-                base_score: candidate.base_score,
-            }
-        })
-        .collect()
-}
+    std::fs::write(theme_path, theme_ron)
+        .with_context(|| format!("Failed to write theme to {}", theme_path.display()))?;
 
-struct HighlightCandidate {
-    lab: [f32; 3],
-    origin: String,
-    preference: u8,
-    confidence: f32,
-    contrast_active: f32,
-    contrast_background: f32,
-    delta_from_text: f32,
+    eprintln!("Theme written to: {}", theme_path.display());
+    Ok(())
 }
 
-fn select_highlight_text(
-    text_assignment: &RoleAssignment,
-    accent_assignment: &RoleAssignment,
-    active_assignment: &RoleAssignment,
-    background_lab: [f32; 3],
-) -> (RoleAssignment, RoleDebugEntry) {
-    let text_lab = text_assignment.lab;
-    let accent_lab = accent_assignment.lab;
-    let active_lab = active_assignment.lab;
-
-    let mut candidates: Vec<HighlightCandidate> = Vec::new();
-
-    let mut add_candidate = |lab: [f32; 3], origin: String, preference: u8, confidence: f32| {
-        let contrast_active = color::calculate_contrast_ratio(lab, active_lab);
-        let contrast_background = color::calculate_contrast_ratio(lab, background_lab);
-        if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
-            && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
-        {
-            let delta_from_text = color::delta_e_cie76(lab, text_lab);
-            candidates.push(HighlightCandidate {
-                lab,
-                origin,
-                preference,
-                confidence,
-                contrast_active,
-                contrast_background,
-                delta_from_text,
-            });
-        }
-    };
-
-    // Original text color
-    add_candidate(
-        text_lab,
-        "text".to_string(),
-        0,
-        text_assignment.confidence.max(0.85),
-    );
-
-    // Adjusted text (lighter/darker)
-    for direction in [-1.0f32, 1.0f32] {
-        let mut candidate = text_lab;
-        let mut steps_taken = 0usize;
-        loop {
-            if steps_taken >= 8 {
-                break;
-            }
-            steps_taken += 1;
-            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
-            let contrast_active = color::calculate_contrast_ratio(candidate, active_lab);
-            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
-            if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
-                && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
-            {
-                let delta = candidate[0] - text_lab[0];
-                add_candidate(candidate, format!("text_adjusted:{:+.1}", delta), 1, 0.75);
-                break;
-            }
-        }
+fn load_provenance(output_path: &Path) -> Result<Provenance> {
+    let json = std::fs::read_to_string(output_path)
+        .with_context(|| format!("Failed to read {}", output_path.display()))?;
+    let recorded: RecordedOutput = serde_json::from_str(&json).with_context(|| {
+        format!(
+            "{} is not a theme-gen output with a provenance block",
+            output_path.display()
+        )
+    })?;
+    Ok(recorded.provenance)
+}
+
+/// Regenerate the theme recorded in `output_path` and check it matches.
+fn replay(
+    output_path: &Path,
+    theme: Option<&Path>,
+    theme_output: Option<&Path>,
+    force: bool,
+) -> Result<()> {
+    let provenance = load_provenance(output_path)?;
+
+    if provenance.crate_version != APP_VERSION {
+        eprintln!(
+            "warning: recorded with rmpc-theme-gen {}, replaying with {}",
+            provenance.crate_version, APP_VERSION
+        );
     }
-
-    // Accent as fallback
-    add_candidate(accent_lab, "accent".to_string(), 2, 0.65);
-
-    // Adjusted accent
-    for direction in [-1.0f32, 1.0f32] {
-        let mut candidate = accent_lab;
-        let mut steps_taken = 0usize;
-        loop {
-            if steps_taken >= 6 {
-                break;
-            }
-            steps_taken += 1;
-            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
-            let contrast_active = color::calculate_contrast_ratio(candidate, active_lab);
-            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
-            if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
-                && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
-            {
-                let delta = candidate[0] - accent_lab[0];
-                add_candidate(candidate, format!("accent_adjusted:{:+.1}", delta), 3, 0.65);
-                break;
-            }
+    // Different arithmetic can move centroids, so a match would prove nothing
+    let mut mismatches = Vec::new();
+    if provenance.features != enabled_features() {
+        mismatches.push(format!(
+            "features [{}], replaying with [{}]",
+            provenance.features.join(", "),
+            enabled_features().join(", ")
+        ));
+    }
+    let dispatch = rmpc_theme_gen::simd_dispatch();
+    match provenance.simd_dispatch.as_deref() {
+        Some(recorded) if recorded != dispatch => mismatches.push(format!(
+            "SIMD dispatch {recorded}, replaying with {dispatch}"
+        )),
+        None if provenance.features.iter().any(|f| f == "simd") => mismatches.push(format!(
+            "an unrecorded SIMD dispatch, replaying with {dispatch}"
+        )),
+        _ => {}
+    }
+    for mismatch in &mismatches {
+        if force {
+            eprintln!("warning: recorded with {mismatch}");
+        } else {
+            anyhow::bail!("Recorded with {mismatch} (pass --force to replay anyway)");
         }
     }
-
-    // Black / White fallback
-    for (rgb, name) in [
-        ([0, 0, 0], "synthetic:black"),
-        ([255, 255, 255], "synthetic:white"),
-    ] {
-        let lab = color::rgb8_to_lab(rgb);
-        add_candidate(lab, name.to_string(), 4, 0.6);
+    if provenance.guardrails != GuardrailParams::current() {
+        anyhow::bail!("Guardrail thresholds in this build differ from the recorded run");
     }
-
-    if candidates.is_empty() {
-        let assignment = role_assignment_from_lab(
-            ColorRole::HighlightText,
-            text_lab,
-            None,
-            0.5,
-            Some("fallback:text"),
-            Some(active_lab),
-            None,
+    let input = InputFingerprint::of(&provenance.config.sampling.path)?;
+    if input != provenance.input {
+        anyhow::bail!(
+            "Input image {} changed since the recorded run (sha256 {} recorded, {} now)",
+            provenance.config.sampling.path.display(),
+            provenance.input.sha256,
+            input.sha256
         );
-        let debug = RoleDebugEntry {
-            hex: assignment.hex.clone(),
-            origin: "fallback:text".to_string(),
-            contrast_against_active: Some(color::calculate_contrast_ratio(text_lab, active_lab)),
-            contrast_against_background: Some(color::calculate_contrast_ratio(
-                text_lab,
-                background_lab,
-            )),
-            contrast_against_text: None,
-        };
-        return (assignment, debug);
     }
 
-    candidates.sort_by(|a, b| {
-        a.preference
-            .cmp(&b.preference)
-            .then_with(|| cmp_f32_desc(a.contrast_active, b.contrast_active))
-            .then_with(|| {
-                a.delta_from_text
-                    .partial_cmp(&b.delta_from_text)
-                    .unwrap_or(Ordering::Equal)
-            })
-    });
-
-    let winner = &candidates[0];
-    let assignment = role_assignment_from_lab(
-        ColorRole::HighlightText,
-        winner.lab,
-        None,
-        winner.confidence,
-        Some(winner.origin.as_str()),
-        Some(active_lab),
-        None,
-    );
-    let debug = RoleDebugEntry {
-        hex: assignment.hex.clone(),
-        origin: winner.origin.clone(),
-        contrast_against_active: Some(winner.contrast_active),
-        contrast_against_background: Some(winner.contrast_background),
-        contrast_against_text: Some(color::calculate_contrast_ratio(winner.lab, text_lab)),
-    };
-
-    (assignment, debug)
-}
-
-struct FrameCandidate {
-    lab: [f32; 3],
-    origin: String,
-    preference: u8,
-    confidence: f32,
-    contrast_background: f32,
-    contrast_text: f32,
-    delta_from_accent: f32,
-}
-
-fn derive_active_from_background(
-    background_lab: [f32; 3],
-    accent_lab: [f32; 3],
-) -> Option<([f32; 3], String)> {
-    let directions = [-1.0f32, 1.0f32];
-    for step in 1..=12 {
-        let delta = step as f32 * 3.0;
-        for &direction in &directions {
-            let mut candidate = background_lab;
-            candidate[0] = (candidate[0] + direction * delta).clamp(0.0, 100.0);
-            let contrast_bg = color::calculate_contrast_ratio(candidate, background_lab);
-            let contrast_accent = color::calculate_contrast_ratio(candidate, accent_lab);
-            // Also ensure body text will be readable on the active background later
-            // We can't compute c_text here (we don't have text_lab), so we enforce it in derive_active_with_hue
-            if contrast_bg >= ACTIVE_BG_MIN && contrast_accent >= ACCENT_BG_MIN {
-                let origin = if direction < 0.0 {
-                    format!("background_adjusted:-{:.1}", delta)
-                } else {
-                    format!("background_adjusted:+{:.1}", delta)
-                };
-                return Some((candidate, origin));
-            }
-        }
+    let generated = generate(&provenance, false)?;
+    if let Some(path) = theme_output {
+        write_theme(path, &generated.theme_ron)?;
     }
-    None
-}
-
-fn select_frame_color(
-    accent_assignment: &RoleAssignment,
-    background_lab: [f32; 3],
-    text_lab: [f32; 3],
-) -> (RoleAssignment, RoleDebugEntry) {
-    let accent_lab = accent_assignment.lab;
-
-    let mut candidates: Vec<FrameCandidate> = Vec::new();
-
-    let mut add_candidate = |lab: [f32; 3], origin: String, preference: u8, confidence: f32| {
-        let contrast_background = color::calculate_contrast_ratio(lab, background_lab);
-        let contrast_text = color::calculate_contrast_ratio(lab, text_lab);
-        if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
-            let delta_from_accent = color::delta_e_cie76(lab, accent_lab);
-            candidates.push(FrameCandidate {
-                lab,
-                origin,
-                preference,
-                confidence,
-                contrast_background,
-                contrast_text,
-                delta_from_accent,
-            });
-        }
-    };
-
-    add_candidate(
-        accent_lab,
-        "accent".to_string(),
-        0,
-        accent_assignment.confidence.max(0.85),
-    );
-
-    for direction in [-1.0f32, 1.0f32] {
-        let mut candidate = accent_lab;
-        let mut steps_taken = 0usize;
-        loop {
-            if steps_taken >= 6 {
-                break;
-            }
-            steps_taken += 1;
-            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
-            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
-            let contrast_text = color::calculate_contrast_ratio(candidate, text_lab);
-            if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
-                let delta = candidate[0] - accent_lab[0];
-                add_candidate(candidate, format!("accent_adjusted:{:+.1}", delta), 1, 0.8);
-                break;
-            }
+    if let Some(path) = theme {
+        let existing = std::fs::read(path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        if existing != generated.theme_ron.as_bytes() {
+            anyhow::bail!("Regenerated theme differs from {}", path.display());
         }
     }
-
-    // Derived from background
-    for direction in [-1.0f32, 1.0f32] {
-        let mut candidate = background_lab;
-        let mut steps_taken = 0usize;
-        loop {
-            if steps_taken >= 6 {
-                break;
-            }
-            steps_taken += 1;
-            candidate[0] = (candidate[0] + direction * 6.0).clamp(0.0, 100.0);
-            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
-            let contrast_text = color::calculate_contrast_ratio(candidate, text_lab);
-            if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
-                let delta = candidate[0] - background_lab[0];
-                add_candidate(
-                    candidate,
-                    format!("background_adjusted:{:+.1}", delta),
-                    2,
-                    0.65,
-                );
-                break;
-            }
-        }
-    }
-
-    // Text fallback
-    add_candidate(text_lab, "text".to_string(), 3, 0.6);
-
-    // Black/White fallback
-    for (rgb, name) in [
-        ([0, 0, 0], "synthetic:black"),
-        ([255, 255, 255], "synthetic:white"),
-    ] {
-        let lab = color::rgb8_to_lab(rgb);
-        add_candidate(lab, name.to_string(), 4, 0.55);
-    }
-
-    if candidates.is_empty() {
-        let assignment = role_assignment_from_lab(
-            ColorRole::Frame,
-            accent_lab,
-            accent_assignment.source_cluster_index,
-            0.6,
-            Some("fallback:accent"),
-            Some(background_lab),
-            Some(text_lab),
+    let replayed = &generated.output.provenance.theme_sha256;
+    if *replayed != provenance.theme_sha256 {
+        anyhow::bail!(
+            "Regenerated theme sha256 {} does not match recorded {}",
+            replayed,
+            provenance.theme_sha256
         );
-        let debug = RoleDebugEntry {
-            hex: assignment.hex.clone(),
-            origin: "fallback:accent".to_string(),
-            contrast_against_active: None,
-            contrast_against_background: Some(color::calculate_contrast_ratio(
-                accent_lab,
-                background_lab,
-            )),
-            contrast_against_text: Some(color::calculate_contrast_ratio(accent_lab, text_lab)),
-        };
-        return (assignment, debug);
-    }
-
-    candidates.sort_by(|a, b| {
-        a.preference
-            .cmp(&b.preference)
-            .then_with(|| cmp_f32_desc(a.contrast_background, b.contrast_background))
-            .then_with(|| {
-                a.delta_from_accent
-                    .partial_cmp(&b.delta_from_accent)
-                    .unwrap_or(Ordering::Equal)
-            })
-    });
-
-    let winner = &candidates[0];
-    let assignment = role_assignment_from_lab(
-        ColorRole::Frame,
-        winner.lab,
-        None,
-        winner.confidence,
-        Some(winner.origin.as_str()),
-        Some(background_lab),
-        Some(text_lab),
-    );
-    let debug = RoleDebugEntry {
-        hex: assignment.hex.clone(),
-        origin: winner.origin.clone(),
-        contrast_against_active: None,
-        contrast_against_background: Some(winner.contrast_background),
-        contrast_against_text: Some(winner.contrast_text),
-    };
-
-    (assignment, debug)
-}
-
-fn select_header_accent(
-    accent_assignment: &RoleAssignment,
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-) -> RoleAssignment {
-    // Try raw accent first; require ≥4.5:1 vs background to ensure header readability
-    let mut best_lab = accent_assignment.lab;
-    let mut best_origin = accent_assignment
-        .origin
-        .as_deref()
-        .unwrap_or("accent")
-        .to_string();
-    let mut best_conf = accent_assignment.confidence.min(0.8);
-
-    let mut contrast_bg = color::calculate_contrast_ratio(best_lab, bg_lab);
-    if contrast_bg < 4.5 {
-        // Adjust only lightness to reach the floor
-        for direction in [-1.0f32, 1.0f32] {
-            let mut candidate = best_lab;
-            for _ in 0..10 {
-                candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
-                let c_bg = color::calculate_contrast_ratio(candidate, bg_lab);
-                let c_txt = color::calculate_contrast_ratio(candidate, text_lab);
-                if c_bg >= 4.5 {
-                    best_lab = candidate;
-                    best_origin = format!("header_accent_adjusted:{:+.1}", candidate[0] - accent_assignment.lab[0]);
-                    best_conf = best_conf.min(0.75);
-                    contrast_bg = c_bg;
-                    break;
-                }
-                // keep minor guard that we don't crash readability versus text if it overlays
-                if c_txt < 2.0 {
-                    break;
-                }
-            }
-            if contrast_bg >= 4.5 {
-                break;
-            }
-        }
-    }
-
-    // Fallbacks if accent cannot be adjusted sufficiently
-    if contrast_bg < 4.5 {
-        let white = color::rgb8_to_lab([255, 255, 255]);
-        let black = color::rgb8_to_lab([0, 0, 0]);
-        let c_white = color::calculate_contrast_ratio(white, bg_lab);
-        let c_black = color::calculate_contrast_ratio(black, bg_lab);
-        if c_white >= 4.5 && c_white >= c_black {
-            best_lab = white;
-            best_origin = "header_accent:white".to_string();
-            best_conf = 0.6;
-        } else if c_black >= 4.5 {
-            best_lab = black;
-            best_origin = "header_accent:black".to_string();
-            best_conf = 0.6;
-        } else {
-            // last resort: use text color
-            best_lab = text_lab;
-            best_origin = "header_accent:text".to_string();
-            best_conf = 0.6;
-        }
     }
 
-    role_assignment_from_lab(
-        ColorRole::HeaderAccent,
-        best_lab,
-        None,
-        best_conf,
-        Some(&best_origin),
-        Some(bg_lab),
-        Some(text_lab),
-    )
-}
-
-fn build_pair_metrics(
-    accent_lab: [f32; 3],
-    active_lab: [f32; 3],
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-) -> PairwiseMetrics {
-    let accent_rgb = color::lab_to_rgb8(accent_lab);
-    let active_rgb = color::lab_to_rgb8(active_lab);
-    let bg_rgb = color::lab_to_rgb8(bg_lab);
-    let text_rgb = color::lab_to_rgb8(text_lab);
-
-    let accent_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(accent_rgb));
-    let active_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(active_rgb));
-    let bg_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(bg_rgb));
-    let text_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(text_rgb));
-
-    PairwiseMetrics {
-        accent_bg: color::calculate_contrast_ratio(accent_lab, bg_lab),
-        accent_text: color::calculate_contrast_ratio(accent_lab, text_lab),
-        accent_active: color::calculate_contrast_ratio(accent_lab, active_lab),
-        active_bg: color::calculate_contrast_ratio(active_lab, bg_lab),
-        active_text: color::calculate_contrast_ratio(active_lab, text_lab),
-        delta_e: color::delta_e_cie76(accent_lab, active_lab),
-        accent_l: accent_lab[0],
-        active_l: active_lab[0],
-        accent_hue: accent_oklch[2],
-        accent_chroma: accent_oklch[1],
-        active_hue: active_oklch[2],
-        active_chroma: active_oklch[1],
-        background_hue: bg_oklch[2],
-        background_chroma: bg_oklch[1],
-        text_hue: text_oklch[2],
-        text_chroma: text_oklch[1],
-    }
+    eprintln!("Replay verified: theme sha256 {replayed}");
+    Ok(())
 }
 
-fn passes_pairwise_guardrails(metrics: &PairwiseMetrics, guard: PairwiseGuardrails) -> bool {
-    if metrics.accent_bg < guard.min_accent_vs_bg {
-        return false;
-    }
-    if metrics.accent_text < guard.min_accent_vs_text {
-        return false;
-    }
-    if metrics.active_bg < guard.min_active_vs_bg {
-        return false;
-    }
-    if metrics.active_text < guard.min_active_vs_text {
-        return false;
-    }
-    if metrics.accent_active < guard.min_peer_contrast {
-        return false;
-    }
-    if metrics.delta_e < guard.min_peer_delta_e {
-        return false;
-    }
-    if metrics.brightness_separation() < guard.min_brightness_separation {
-        return false;
+/// Render the theme recorded in `analysis_path` as a PNG mockup beside its cover.
+fn preview(analysis_path: &Path, png_path: &Path) -> Result<()> {
+    let provenance = load_provenance(analysis_path)?;
+    let generated = generate(&provenance, false)?;
+    let output = &generated.output;
+    if !provenance.theme_sha256.is_empty()
+        && output.provenance.theme_sha256 != provenance.theme_sha256
+    {
+        eprintln!(
+            "warning: regenerated theme differs from the recorded run; previewing the regenerated one"
+        );
     }
-    true
-}
 
-fn compare_pairwise_results(lhs: &PairwiseResult, rhs: &PairwiseResult) -> Ordering {
-    cmp_f32_desc(lhs.metrics.min_contrast(), rhs.metrics.min_contrast())
-        .then_with(|| {
-            cmp_f32_desc(
-                lhs.metrics.brightness_separation(),
-                rhs.metrics.brightness_separation(),
-            )
-        })
-        .then_with(|| lhs.provenance_score.cmp(&rhs.provenance_score))
-        .then_with(|| cmp_f32_desc(lhs.metrics.avg_contrast(), rhs.metrics.avg_contrast()))
-        .then_with(|| {
-            let lhs_score = lhs.accent.base_score + lhs.active.base_score;
-            let rhs_score = rhs.accent.base_score + rhs.active.base_score;
-            cmp_f32_desc(lhs_score, rhs_score)
-        })
-}
+    let cover_path = &provenance.config.sampling.path;
+    let cover = image::open(cover_path)
+        .with_context(|| format!("Failed to open cover {}", cover_path.display()))?;
+    let image = preview_image(
+        &cover,
+        &output.clusters,
+        &output.role_assignments,
+        output.scrollbar_enabled,
+    );
 
-fn make_debug_entry(result: &PairwiseResult) -> PairwiseDebugEntry {
-    let accent_rgb = color::lab_to_rgb8(result.accent.lab);
-    let active_rgb = color::lab_to_rgb8(result.active.lab);
-    PairwiseDebugEntry {
-        accent_hex: color::rgb_to_hex(accent_rgb),
-        accent_origin: result.accent.origin_label.clone(),
-        active_hex: color::rgb_to_hex(active_rgb),
-        active_origin: result.active.origin_label.clone(),
-        accent_bg: result.metrics.accent_bg,
-        accent_text: result.metrics.accent_text,
-        accent_active: result.metrics.accent_active,
-        active_bg: result.metrics.active_bg,
-        active_text: result.metrics.active_text,
-        delta_e: result.metrics.delta_e,
-        min_contrast: result.metrics.min_contrast(),
-        brightness_separation: result.metrics.brightness_separation(),
-        provenance_score: result.provenance_score,
-        accent_hue: result.metrics.accent_hue,
-        accent_chroma: result.metrics.accent_chroma,
-        active_hue: result.metrics.active_hue,
-        active_chroma: result.metrics.active_chroma,
-        background_hue: result.metrics.background_hue,
-        background_chroma: result.metrics.background_chroma,
-        text_hue: result.metrics.text_hue,
-        text_chroma: result.metrics.text_chroma,
+    if let Some(parent) = png_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
     }
+    image
+        .save_with_format(png_path, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write preview to {}", png_path.display()))?;
+    eprintln!("Preview written to: {}", png_path.display());
+    Ok(())
 }
 
-fn solve_with_guardrails(
-    accent_candidates: &[RoleColorCandidate],
-    active_candidates: &[RoleColorCandidate],
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    guardrails: PairwiseGuardrails,
-    debug_enabled: bool,
-) -> (Option<PairwiseResult>, usize, Vec<PairwiseResult>) {
-    let mut best: Option<PairwiseResult> = None;
-    let mut evaluated = 0usize;
-    let mut passing: Vec<PairwiseResult> = Vec::new();
-
-    for accent in accent_candidates {
-        for active in active_candidates {
-            evaluated += 1;
-            let metrics = build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
-            if !passes_pairwise_guardrails(&metrics, guardrails) {
-                continue;
-            }
-            if !hue_separation_ok(
-                metrics.active_hue,
-                metrics.active_chroma,
-                metrics.background_hue,
-                metrics.background_chroma,
-                MIN_HUE_DELTA_ACTIVE_BG,
-            ) {
-                continue;
-            }
-            if !hue_separation_ok(
-                metrics.active_hue,
-                metrics.active_chroma,
-                metrics.text_hue,
-                metrics.text_chroma,
-                MIN_HUE_DELTA_ACTIVE_TEXT,
-            ) {
-                continue;
-            }
-            if !hue_separation_ok(
-                metrics.accent_hue,
-                metrics.accent_chroma,
-                metrics.active_hue,
-                metrics.active_chroma,
-                MIN_HUE_DELTA_ACCENT_ACTIVE,
-            ) {
-                continue;
-            }
-            if !hue_separation_ok(
-                metrics.accent_hue,
-                metrics.accent_chroma,
-                metrics.background_hue,
-                metrics.background_chroma,
-                MIN_HUE_DELTA_ACCENT_BG,
-            ) {
-                continue;
-            }
-
-            let candidate = PairwiseResult {
-                accent: accent.clone(),
-                active: active.clone(),
-                metrics,
-                provenance_score: accent.provenance_rank + active.provenance_rank,
-            };
-
-            match &mut best {
-                Some(current_best) => {
-                    if compare_pairwise_results(&candidate, current_best) == Ordering::Less {
-                        *current_best = candidate.clone();
-                    }
-                }
-                None => best = Some(candidate.clone()),
-            }
-
-            if debug_enabled {
-                passing.push(candidate);
-            }
-        }
-    }
-
-    if debug_enabled {
-        passing.sort_by(|a, b| compare_pairwise_results(a, b));
-        passing.truncate(8);
-    } else {
-        passing.clear();
-    }
-
-    (best, evaluated, passing)
+fn write_report(report_path: &Path, output: &ThemeGenOutput) -> Result<()> {
+    let cover_path = &output.provenance.config.sampling.path;
+    let cover = image::open(cover_path)
+        .with_context(|| format!("Failed to open cover {}", cover_path.display()))?;
+    let debug = output
+        .debug
+        .as_ref()
+        .context("Report requires solver diagnostics")?;
+    let html = html_report(&ReportInput {
+        source: &cover_path.display().to_string(),
+        cover: &cover,
+        clusters: &output.clusters,
+        roles: &output.role_assignments,
+        debug,
+    })?;
+
+    if let Some(parent) = report_path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    std::fs::write(report_path, html)
+        .with_context(|| format!("Failed to write report to {}", report_path.display()))?;
+    eprintln!("Report written to: {}", report_path.display());
+    Ok(())
 }
 
-fn select_accent_and_active(
-    clusters: &[ColorCluster],
-    used_indices: &mut Vec<usize>,
-    bg_lab: [f32; 3],
-    text_lab: [f32; 3],
-    debug_enabled: bool,
-) -> (RoleAssignment, RoleAssignment, Option<PairwiseDebug>) {
-    let mut accent_candidates = build_accent_candidates(clusters, bg_lab, text_lab, used_indices);
-    if accent_candidates.is_empty() {
-        accent_candidates.push(RoleColorCandidate {
-            lab: [bg_lab[0].clamp(0.0, 100.0), 0.0, 0.0],
-            source_cluster_index: None,
-            origin_label: "synthetic:bg-neutral".to_string(),
-            provenance_rank: 2,
-            base_score: 0.3,
-        });
-    }
-
-    let mut active_candidates = build_active_candidates(clusters, bg_lab, text_lab, used_indices);
-    if active_candidates.is_empty() {
-        active_candidates.push(RoleColorCandidate {
-            lab: [text_lab[0].clamp(0.0, 100.0), 0.0, 0.0],
-            source_cluster_index: None,
-            origin_label: "synthetic:text-neutral".to_string(),
-            provenance_rank: 2,
-            base_score: 0.3,
-        });
-    }
+/// Print the contrast table for `theme_path` and fail if any pair misses `level`.
+fn audit(theme_path: &Path, level: AuditLevel, apca: bool) -> Result<()> {
+    let ron = std::fs::read_to_string(theme_path)
+        .with_context(|| format!("Failed to read {}", theme_path.display()))?;
+    let entries = audit_theme(&ron)
+        .with_context(|| format!("Failed to parse theme {}", theme_path.display()))?;
 
-    let strict_guardrails = PairwiseGuardrails {
-        min_accent_vs_bg: ACCENT_BG_MIN,
-        min_accent_vs_text: ACCENT_TEXT_MIN,
-        min_active_vs_bg: ACTIVE_BG_MIN,
-        min_active_vs_text: ACTIVE_TEXT_MIN,
-        min_peer_contrast: PEER_CONTRAST_MIN,
-        min_peer_delta_e: PEER_DELTA_E_MIN,
-        min_brightness_separation: BRIGHTNESS_SEPARATION_MIN,
+    let color_output = std::io::stdout().is_terminal();
+    let mark = |ok: bool| match (ok, color_output) {
+        (true, _) => "pass".to_string(),
+        (false, true) => "\x1b[1;31mFAIL\x1b[0m".to_string(),
+        (false, false) => "FAIL".to_string(),
     };
-
-    let mut total_pairs_evaluated = 0usize;
-
-    let (strict_result, strict_evaluated, strict_debug_pairs) = solve_with_guardrails(
-        &accent_candidates,
-        &active_candidates,
-        bg_lab,
-        text_lab,
-        strict_guardrails,
-        debug_enabled,
+    let width = entries
+        .iter()
+        .map(|entry| entry.style.len())
+        .max()
+        .unwrap_or(0)
+        .max("STYLE".len());
+
+    print!(
+        "{:<width$}  {:<7}  {:<8}  {:>6}  {:<4}  {:<4}",
+        "STYLE", "FG", "BG", "RATIO", "AA", "AAA"
     );
+    if apca {
+        print!("  {:>6}  {:<4}", "LC", "APCA");
+    }
+    println!();
 
-    total_pairs_evaluated += strict_evaluated;
-
-    let (result, pass_mode, debug_pairs) = if let Some(res) = strict_result {
-        (res, "strict".to_string(), strict_debug_pairs)
-    } else {
-        let relaxed_guardrails = PairwiseGuardrails {
-            min_accent_vs_bg: ACCENT_BG_MIN,
-            min_accent_vs_text: ACCENT_TEXT_MIN,
-            min_active_vs_bg: ACTIVE_BG_RELAXED_MIN,
-            min_active_vs_text: ACTIVE_TEXT_MIN,
-            min_peer_contrast: RELAXED_PEER_CONTRAST_MIN,
-            min_peer_delta_e: RELAXED_PEER_DELTA_E_MIN,
-            min_brightness_separation: BRIGHTNESS_SEPARATION_MIN * 0.7,
+    let mut failures = 0usize;
+    for entry in &entries {
+        let aaa = match entry.kind {
+            PairKind::Text => mark(entry.passes(AuditLevel::Aaa)),
+            PairKind::NonText => format!("{:<4}", "-"),
         };
-
-        let (relaxed_result, relaxed_evaluated, relaxed_debug_pairs) = solve_with_guardrails(
-            &accent_candidates,
-            &active_candidates,
-            bg_lab,
-            text_lab,
-            relaxed_guardrails,
-            debug_enabled,
+        // Inherited backgrounds are marked with '*'
+        let bg = format!(
+            "{}{}",
+            color::rgb_to_hex(entry.bg),
+            if entry.bg_inherited { "*" } else { "" }
         );
-
-        total_pairs_evaluated += relaxed_evaluated;
-
-        if let Some(res) = relaxed_result {
-            (
-                res,
-                "relaxed".to_string(),
-                if debug_enabled {
-                    relaxed_debug_pairs
-                } else {
-                    Vec::new()
-                },
-            )
-        } else {
-            // Final fallback: choose best overall by min contrast even if peer guard not met
-            let mut fallback_best: Option<PairwiseResult> = None;
-            let mut fallback_list: Vec<PairwiseResult> = Vec::new();
-            let mut fallback_evaluated = 0usize;
-            for accent in &accent_candidates {
-                for active in &active_candidates {
-                    fallback_evaluated += 1;
-                    let metrics = build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
-                    if !hue_separation_ok(
-                        metrics.active_hue,
-                        metrics.active_chroma,
-                        metrics.background_hue,
-                        metrics.background_chroma,
-                        MIN_HUE_DELTA_ACTIVE_BG,
-                    ) {
-                        continue;
-                    }
-                    if !hue_separation_ok(
-                        metrics.active_hue,
-                        metrics.active_chroma,
-                        metrics.text_hue,
-                        metrics.text_chroma,
-                        MIN_HUE_DELTA_ACTIVE_TEXT,
-                    ) {
-                        continue;
-                    }
-                    if !hue_separation_ok(
-                        metrics.accent_hue,
-                        metrics.accent_chroma,
-                        metrics.active_hue,
-                        metrics.active_chroma,
-                        MIN_HUE_DELTA_ACCENT_ACTIVE,
-                    ) {
-                        continue;
-                    }
-                    if !hue_separation_ok(
-                        metrics.accent_hue,
-                        metrics.accent_chroma,
-                        metrics.background_hue,
-                        metrics.background_chroma,
-                        MIN_HUE_DELTA_ACCENT_BG,
-                    ) {
-                        continue;
-                    }
-                    let candidate = PairwiseResult {
-                        accent: accent.clone(),
-                        active: active.clone(),
-                        metrics,
-                        provenance_score: accent.provenance_rank + active.provenance_rank,
-                    };
-                    match &mut fallback_best {
-                        Some(current) => {
-                            if compare_pairwise_results(&candidate, current) == Ordering::Less {
-                                *current = candidate.clone();
-                            }
-                        }
-                        None => fallback_best = Some(candidate.clone()),
-                    }
-                    if debug_enabled {
-                        fallback_list.push(candidate);
-                    }
-                }
-            }
-
-            if fallback_best.is_none() {
-                for accent in &accent_candidates {
-                    for active in &active_candidates {
-                        let metrics =
-                            build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
-                        let candidate = PairwiseResult {
-                            accent: accent.clone(),
-                            active: active.clone(),
-                            metrics,
-                            provenance_score: accent.provenance_rank + active.provenance_rank,
-                        };
-                        match &mut fallback_best {
-                            Some(current) => {
-                                if compare_pairwise_results(&candidate, current) == Ordering::Less {
-                                    *current = candidate.clone();
-                                }
-                            }
-                            None => fallback_best = Some(candidate.clone()),
-                        }
-                        if debug_enabled && fallback_list.len() < 8 {
-                            fallback_list.push(candidate);
-                        }
-                    }
-                }
-            }
-
-            if debug_enabled {
-                fallback_list.sort_by(|a, b| compare_pairwise_results(a, b));
-                fallback_list.truncate(8);
-            }
-
-            total_pairs_evaluated += fallback_evaluated;
-
-            (
-                fallback_best.expect("fallback should produce a candidate"),
-                "fallback".to_string(),
-                fallback_list,
-            )
-        }
-    };
-
-    // Build assignments
-    let accent_assignment = role_assignment_from_lab(
-        ColorRole::Accent,
-        result.accent.lab,
-        result.accent.source_cluster_index,
-        result.accent.confidence(),
-        Some(&result.accent.origin_label),
-        Some(bg_lab),
-        Some(text_lab),
-    );
-
-    if let Some(idx) = result.accent.source_cluster_index {
-        if !used_indices.contains(&idx) {
-            used_indices.push(idx);
-        }
-    }
-
-    let mut active_assignment = role_assignment_from_lab(
-        ColorRole::ActiveItem,
-        result.active.lab,
-        result.active.source_cluster_index,
-        result.active.confidence(),
-        Some(&result.active.origin_label),
-        Some(bg_lab),
-        Some(text_lab),
-    );
-
-    if let Some(idx) = result.active.source_cluster_index {
-        if !used_indices.contains(&idx) {
-            used_indices.push(idx);
+        print!(
+            "{:<width$}  {:<7}  {:<8}  {:>6.2}  {}  {}",
+            entry.style,
+            color::rgb_to_hex(entry.fg),
+            bg,
+            entry.contrast,
+            mark(entry.passes(AuditLevel::Aa)),
+            aaa
+        );
+        if apca {
+            print!("  {:>6.1}  {}", entry.apca_lc, mark(entry.passes_apca()));
         }
-    }
+        println!();
 
-    let active_contrast_ok =
-        color::calculate_contrast_ratio(active_assignment.lab, bg_lab) >= ACTIVE_BG_MIN;
-    if !active_contrast_ok {
-        if let Some((derived_lab, origin)) =
-            derive_active_from_background(bg_lab, accent_assignment.lab)
-        {
-            active_assignment = role_assignment_from_lab(
-                ColorRole::ActiveItem,
-                derived_lab,
-                None,
-                active_assignment.confidence.min(0.6),
-                Some(&origin),
-                Some(bg_lab),
-                Some(text_lab),
-            );
+        if !entry.passes(level) || (apca && !entry.passes_apca()) {
+            failures += 1;
         }
     }
+    println!("* background inherited from the pane; borders, separators and bars use the 3:1 non-text minimum");
 
-    // Hue separation: if active is too close in hue to background or text, derive a hue-rotated candidate
-    {
-        let act_rgb = color::lab_to_rgb8(active_assignment.lab);
-        let act_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(act_rgb));
-        let bg_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(bg_lab)));
-        let txt_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(text_lab)));
-        let accent_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(
-            accent_assignment.lab,
-        )));
-        let ok_bg = hue_separation_ok(
-            act_oklch[2],
-            act_oklch[1],
-            bg_oklch[2],
-            bg_oklch[1],
-            MIN_HUE_DELTA_ACTIVE_BG,
-        );
-        let ok_txt = hue_separation_ok(
-            act_oklch[2],
-            act_oklch[1],
-            txt_oklch[2],
-            txt_oklch[1],
-            MIN_HUE_DELTA_ACTIVE_TEXT,
+    if failures > 0 {
+        anyhow::bail!(
+            "{} of {} pairs fail {}{}",
+            failures,
+            entries.len(),
+            match level {
+                AuditLevel::Aa => "WCAG AA",
+                AuditLevel::Aaa => "WCAG AAA",
+            },
+            if apca { " or APCA" } else { "" }
         );
-        if !(ok_bg && ok_txt) {
-            if let Some((lab, origin)) = derive_active_with_hue(
-                bg_lab,
-                text_lab,
-                accent_assignment.lab,
-                bg_oklch,
-                txt_oklch,
-                accent_oklch,
-            ) {
-                let metrics = build_pair_metrics(accent_assignment.lab, lab, bg_lab, text_lab);
-                let hue_ok = hue_separation_ok(
-                    metrics.active_hue,
-                    metrics.active_chroma,
-                    metrics.background_hue,
-                    metrics.background_chroma,
-                    MIN_HUE_DELTA_ACTIVE_BG,
-                ) && hue_separation_ok(
-                    metrics.active_hue,
-                    metrics.active_chroma,
-                    metrics.text_hue,
-                    metrics.text_chroma,
-                    MIN_HUE_DELTA_ACTIVE_TEXT,
-                ) && hue_separation_ok(
-                    metrics.accent_hue,
-                    metrics.accent_chroma,
-                    metrics.active_hue,
-                    metrics.active_chroma,
-                    MIN_HUE_DELTA_ACCENT_ACTIVE,
-                ) && hue_separation_ok(
-                    metrics.accent_hue,
-                    metrics.accent_chroma,
-                    metrics.background_hue,
-                    metrics.background_chroma,
-                    MIN_HUE_DELTA_ACCENT_BG,
-                );
-                if hue_ok && passes_pairwise_guardrails(&metrics, strict_guardrails) {
-                    active_assignment = role_assignment_from_lab(
-                        ColorRole::ActiveItem,
-                        lab,
-                        None,
-                        active_assignment.confidence.min(0.6),
-                        Some(&format!("{}+hue", origin)),
-                        Some(bg_lab),
-                        Some(text_lab),
-                    );
-                } else if let Some((derived_lab, origin_bg)) =
-                    derive_active_from_background(bg_lab, accent_assignment.lab)
-                {
-                    let origin = format!("fallback_bg:{}", origin_bg);
-                    active_assignment = role_assignment_from_lab(
-                        ColorRole::ActiveItem,
-                        derived_lab,
-                        None,
-                        active_assignment.confidence.min(0.6),
-                        Some(&origin),
-                        Some(bg_lab),
-                        Some(text_lab),
-                    );
-                }
-            }
-        }
     }
-
-    let debug = if debug_enabled {
-        let winning_entry = make_debug_entry(&result);
-        let top_entries: Vec<PairwiseDebugEntry> = debug_pairs
-            .iter()
-            .map(|res| make_debug_entry(res))
-            .collect();
-        Some(PairwiseDebug {
-            evaluated_pairs: total_pairs_evaluated,
-            pass_mode,
-            winning_pair: winning_entry,
-            top_pairs: top_entries,
-            accent_candidates: candidate_list_for_debug(&accent_candidates),
-            active_candidates: candidate_list_for_debug(&active_candidates),
-        })
-    } else {
-        None
-    };
-
-    (accent_assignment, active_assignment, debug)
-}
-
-fn role_assignment_from_lab(
-    role: ColorRole,
-    lab: [f32; 3],
-    source_cluster_index: Option<usize>,
-    confidence: f32,
-    origin: Option<&str>,
-    bg_lab: Option<[f32; 3]>,
-    text_lab: Option<[f32; 3]>,
-) -> RoleAssignment {
-    let rgb = color::lab_to_rgb8(lab);
-    let hsv = color::rgb8_to_hsv(rgb);
-    let canonical_lab = color::rgb8_to_lab(rgb);
-    let hex = color::rgb_to_hex(rgb);
-
-    let contrast_against_background =
-        bg_lab.map(|bg| color::calculate_contrast_ratio(canonical_lab, bg));
-    let contrast_against_text =
-        text_lab.map(|text| color::calculate_contrast_ratio(canonical_lab, text));
-
-    RoleAssignment {
-        role,
-        rgb: RgbValue {
-            r: rgb[0],
-            g: rgb[1],
-            b: rgb[2],
-        },
-        hsv,
-        lab: canonical_lab,
-        hex,
-        source_cluster_index,
-        confidence,
-        contrast_against_background,
-        contrast_against_text,
-        origin: origin.map(|s| s.to_string()),
-    }
-}
-
-fn clone_for_role(role: ColorRole, base: &RoleAssignment, confidence: f32) -> RoleAssignment {
-    RoleAssignment {
-        role,
-        rgb: base.rgb,
-        hsv: base.hsv,
-        lab: base.lab,
-        hex: base.hex.clone(),
-        source_cluster_index: base.source_cluster_index,
-        confidence,
-        contrast_against_background: base.contrast_against_background,
-        contrast_against_text: base.contrast_against_text,
-        origin: base.origin.clone(),
-    }
-}
-
-/// Generate synthetic light text color as fallback
-fn generate_light_text() -> ([u8; 3], [f32; 3], [f32; 3]) {
-    let rgb = [220, 220, 220];
-    let hsv = color::rgb8_to_hsv(rgb);
-    let lab = color::rgb8_to_lab(rgb);
-    (rgb, hsv, lab)
-}
-
-/// Generate synthetic dark text color as fallback
-fn generate_dark_text() -> ([u8; 3], [f32; 3], [f32; 3]) {
-    let rgb = [30, 30, 30];
-    let hsv = color::rgb8_to_hsv(rgb);
-    let lab = color::rgb8_to_lab(rgb);
-    (rgb, hsv, lab)
-}
-
-/// Generate RON theme file content from role assignments
-fn generate_theme_ron(assignments: &[RoleAssignment], scrollbar_enabled: bool) -> String {
-    // Find role assignments
-    let bg = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::Background)
-        .unwrap();
-    let text = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::Text)
-        .unwrap();
-    let accent = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::Accent)
-        .unwrap();
-    let active = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::ActiveItem)
-        .unwrap();
-    let inactive = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::InactiveItem)
-        .unwrap();
-    let highlight_text_role = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::HighlightText)
-        .unwrap();
-    let frame = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::Frame)
-        .unwrap();
-    let header_accent = assignments
-        .iter()
-        .find(|a| a.role == ColorRole::HeaderAccent)
-        .unwrap();
-
-    let scrollbar_block = if scrollbar_enabled {
-        format!(
-            "    scrollbar: (\n        symbols: [\"│\", \"█\", \"▲\", \"▼\"],\n        track_style: (fg: \"{}\", bg: \"{}\"),\n        ends_style: (fg: \"{}\", bg: \"{}\"),\n        thumb_style: (fg: \"{}\", bg: \"{}\"),\n    ),\n",
-            frame.hex, bg.hex, frame.hex, bg.hex, frame.hex, bg.hex
-        )
-    } else {
-        "    scrollbar: None,\n".to_string()
-    };
-
-    format!(
-        r##"#![enable(implicit_some)]
-#![enable(unwrap_newtypes)]
-#![enable(unwrap_variant_newtypes)]
-(
-    default_album_art_path: None,
-    show_song_table_header: true,
-    draw_borders: true,
-    format_tag_separator: " | ",
-    browser_column_widths: [20, 38, 42],
-    background_color: "{bg}",
-    text_color: "{text}",
-    header_background_color: "{bg}",
-    modal_background_color: "{bg}",
-    modal_backdrop: false,
-    preview_label_style: (fg: "{header_accent}", bg: "{bg}"),
-    preview_metadata_group_style: (fg: "{header_accent}", bg: "{bg}", modifiers: "Bold"),
-    tab_bar: (
-        enabled: true,
-        active_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
-        inactive_style: (fg: "{inactive}", bg: "{bg}"),
-    ),
-    highlighted_item_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
-    current_item_style: (fg: "{highlight_text}", bg: "{active}", modifiers: "Bold"),
-    borders_style: (fg: "{frame}"),
-    highlight_border_style: (fg: "{frame}"),
-    symbols: (
-        song: "",
-        dir: "",
-        playlist: "P",
-        marker: "M",
-        ellipsis: "...",
-        song_style: None,
-        dir_style: None,
-        playlist_style: None,
-    ),
-    level_styles: (
-        info: (fg: "{accent}", bg: "{bg}"),
-        warn: (fg: "#f0c674", bg: "{bg}"),
-        error: (fg: "#cc6666", bg: "{bg}"),
-        debug: (fg: "#b5bd68", bg: "{bg}"),
-        trace: (fg: "#b294bb", bg: "{bg}"),
-    ),
-    progress_bar: (
-        symbols: ["[", "=", ">", " ", "]"],
-        track_style: (fg: "{frame}", bg: "{bg}"),
-        elapsed_style: (fg: "{active}", bg: "{bg}"),
-        thumb_style: (fg: "{frame}", bg: "{bg}"),
-    ),
-{scrollbar}
-    song_table_format: [
-        (
-            prop: (kind: Property(Artist),
-                default: (kind: Text("Unknown"))
-            ),
-            width: "20%",
-        ),
-        (
-            prop: (kind: Property(Title),
-                default: (kind: Text("Unknown"))
-            ),
-            width: "35%",
-        ),
-        (
-            // Keep Album column styling neutral so row-level styles (e.g., playing-not-selected
-            // foreground) can apply uniformly across all columns. Content inherits the row/table
-            // style without forcing its own fg/bg here.
-            prop: (kind: Property(Album),
-                default: (kind: Text("Unknown Album"))
-            ),
-            width: "30%",
-        ),
-        (
-            prop: (kind: Property(Duration),
-                default: (kind: Text("-"))
-            ),
-            width: "15%",
-            alignment: Right,
-        ),
-    ],
-    components: {{}},
-    layout: Split(
-        direction: Vertical,
-        panes: [
-            (
-                pane: Pane(Header),
-                size: "2",
-            ),
-            (
-                pane: Pane(Tabs),
-                size: "3",
-            ),
-            (
-                pane: Pane(TabContent),
-                size: "100%",
-            ),
-            (
-                pane: Pane(ProgressBar),
-                size: "1",
-            ),
-        ],
-    ),
-    header: (
-        rows: [
-            (
-                left: [
-                    (kind: Text("["), style: (fg: "{header_accent}", modifiers: "Bold")),
-                    (kind: Property(Status(StateV2(playing_label: "Playing", paused_label: "Paused", stopped_label: "Stopped"))), style: (fg: "{header_accent}", modifiers: "Bold")),
-                    (kind: Text("]"), style: (fg: "{header_accent}", modifiers: "Bold"))
-                ],
-                center: [
-                    (kind: Property(Song(Title)), style: (modifiers: "Bold"),
-                        default: (kind: Text("No Song"), style: (modifiers: "Bold"))
-                    )
-                ],
-                right: [
-                    (kind: Property(Widget(ScanStatus)), style: (fg: "{text}")),
-                    (kind: Property(Widget(Volume)), style: (fg: "{text}"))
-                ]
-            ),
-            (
-                left: [
-                    (kind: Property(Status(Elapsed))),
-                    (kind: Text(" / ")),
-                    (kind: Property(Status(Duration))),
-                    (kind: Text(" (")),
-                    (kind: Property(Status(Bitrate))),
-                    (kind: Text(" kbps)"))
-                ],
-                center: [
-                    (kind: Property(Song(Artist)), style: (fg: "{header_accent}", modifiers: "Bold"),
-                        default: (kind: Text("Unknown"), style: (fg: "{header_accent}", modifiers: "Bold"))
-                    ),
-                    (kind: Text(" - ")), 
-                    (kind: Property(Song(Album)),
-                        default: (kind: Text("Unknown Album"))
-                    )
-                ],
-                right: [
-                    (
-                        kind: Property(Widget(States(
-                            active_style: (fg: "{text}", modifiers: "Bold"),
-                            separator_style: (fg: "{frame}")))
-                        ),
-                        style: (fg: "{frame}")
-                    ),
-                ]
-            ),
-        ],
-    ),
-    browser_song_format: [
-        (
-            kind: Group([
-                (kind: Property(Track)),
-                (kind: Text(" ")), 
-            ])
-        ),
-        (
-            kind: Group([
-                (kind: Property(Artist)),
-                (kind: Text(" - ")), 
-                (kind: Property(Title)),
-            ]),
-            default: (kind: Property(Filename))
-        ),
-    ],
-    lyrics: (
-        timestamp: false
-    )
-)
-"##,
-        bg = bg.hex,
-        text = text.hex,
-        accent = accent.hex,
-        active = active.hex,
-        inactive = inactive.hex,
-        highlight_text = highlight_text_role.hex,
-        frame = frame.hex,
-        header_accent = header_accent.hex,
-        scrollbar = scrollbar_block,
-    )
-}
-
-/// Map color clusters to UI element roles
-fn map_colors_to_roles(
-    clusters: &[ColorCluster],
-    debug_enabled: bool,
-) -> (
-    Vec<RoleAssignment>,
-    Option<PairwiseDebug>,
-    Option<RolesDebug>,
-) {
-    let mut assignments = Vec::new();
-    let mut used_indices = Vec::new();
-
-    // 1. Background (most dominant, reasonable properties)
-    let (bg_idx, bg_conf) = select_background(clusters);
-    let bg_cluster = &clusters[bg_idx];
-    let bg_assignment = role_assignment_from_lab(
-        ColorRole::Background,
-        bg_cluster.lab,
-        Some(bg_idx),
-        bg_conf,
-        Some("cluster"),
-        None,
-        None,
-    );
-    let bg_lab = bg_assignment.lab;
-    assignments.push(bg_assignment);
-    used_indices.push(bg_idx);
-
-    // 2. Text color with fallback to light/dark synthetic values if needed
-    let (text_idx, mut text_conf) = select_text_color(clusters, bg_lab);
-    let text_cluster = &clusters[text_idx];
-    let mut text_lab = text_cluster.lab;
-    let mut text_source = Some(text_idx);
-    let mut text_origin = "cluster";
-    if color::calculate_contrast_ratio(bg_lab, text_lab) < 4.5 {
-        text_conf = 0.45;
-        text_source = None;
-        text_origin = "synthetic";
-        let (_, _, lab) = if bg_lab[0] < 50.0 {
-            generate_light_text()
-        } else {
-            generate_dark_text()
-        };
-        text_lab = lab;
-    } else {
-        used_indices.push(text_idx);
-    }
-
-    let text_assignment = role_assignment_from_lab(
-        ColorRole::Text,
-        text_lab,
-        text_source,
-        text_conf,
-        Some(text_origin),
-        Some(bg_lab),
-        None,
-    );
-    let text_lab = text_assignment.lab;
-    assignments.push(text_assignment);
-
-    // 3. Solve accent + active pair together
-    let (accent_assignment, active_assignment, pairwise_debug) =
-        select_accent_and_active(clusters, &mut used_indices, bg_lab, text_lab, debug_enabled);
-    assignments.push(accent_assignment.clone());
-
-    // 4. Border color (distinct from background)
-    let (border_idx, border_conf) = select_border_color(clusters, bg_lab, &used_indices);
-    let border_cluster = &clusters[border_idx];
-    used_indices.push(border_idx);
-    let border_assignment = role_assignment_from_lab(
-        ColorRole::Border,
-        border_cluster.lab,
-        Some(border_idx),
-        border_conf,
-        Some("cluster"),
-        None,
-        None,
-    );
-    assignments.push(border_assignment.clone());
-
-    assignments.push(active_assignment.clone());
-
-    // 5. Highlight text color tuned for active background
-    let (highlight_assignment, highlight_debug) = select_highlight_text(
-        assignments
-            .iter()
-            .find(|a| a.role == ColorRole::Text)
-            .unwrap(),
-        &accent_assignment,
-        &active_assignment,
-        bg_lab,
-    );
-    assignments.push(highlight_assignment.clone());
-
-    // 6. Frame color for borders/separators
-    let (frame_assignment, frame_debug) = select_frame_color(&accent_assignment, bg_lab, text_lab);
-    assignments.push(frame_assignment.clone());
-
-    // 6b. Header accent derived from accent with ≥4.5:1 vs background
-    let header_accent = select_header_accent(&accent_assignment, bg_lab, text_lab);
-    assignments.push(header_accent);
-
-    // 6. Inactive/muted - reuse border color
-    assignments.push(clone_for_role(
-        ColorRole::InactiveItem,
-        &border_assignment,
-        0.7,
-    ));
-
-    // 7. Progress bar - reuse accent color
-    assignments.push(clone_for_role(
-        ColorRole::ProgressBar,
-        &accent_assignment,
-        accent_assignment.confidence,
-    ));
-
-    // 8. Scrollbar - reuse active color
-    assignments.push(clone_for_role(
-        ColorRole::Scrollbar,
-        &active_assignment,
-        active_assignment.confidence,
-    ));
-
-    let playing_debug = {
-        let contrast_active =
-            color::calculate_contrast_ratio(highlight_assignment.lab, active_assignment.lab);
-        let contrast_bg = color::calculate_contrast_ratio(highlight_assignment.lab, bg_lab);
-        let contrast_text =
-            color::calculate_contrast_ratio(highlight_assignment.lab, text_lab);
-        let fg_origin = highlight_assignment.origin.as_deref().unwrap_or("highlight");
-        let bg_origin = active_assignment.origin.as_deref().unwrap_or("active");
-        RoleDebugEntry {
-            hex: highlight_assignment.hex.clone(),
-            origin: format!("fg:{} | bg:{}", fg_origin, bg_origin),
-            contrast_against_active: Some(contrast_active),
-            contrast_against_background: Some(contrast_bg),
-            contrast_against_text: Some(contrast_text),
-        }
-    };
-
-    let roles_debug = if debug_enabled {
-        Some(RolesDebug {
-            highlight_text: highlight_debug,
-            frame: frame_debug,
-            playing_row: playing_debug,
-        })
-    } else {
-        None
-    };
-
-    (assignments, pairwise_debug, roles_debug)
+    println!("All {} pairs pass", entries.len());
+    Ok(())
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    let start = Instant::now();
+
+    match &args.command {
+        Some(Command::Replay {
+            output,
+            theme,
+            theme_output,
+            force,
+        }) => return replay(output, theme.as_deref(), theme_output.as_deref(), *force),
+        Some(Command::Audit { theme, level, apca }) => return audit(theme, *level, *apca),
+        Some(Command::Preview { analysis, output }) => return preview(analysis, output),
+        None => {}
+    }
 
     let debug_enabled = if args.debug {
         true
@@ -2266,164 +713,63 @@ fn main() -> Result<()> {
     };
 
     // Validate image path exists
-    if !args.image.exists() {
-        anyhow::bail!("Image file not found: {}", args.image.display());
+    let image = args.image.as_deref().context("--image is required")?;
+    if !image.exists() {
+        anyhow::bail!("Image file not found: {}", image.display());
+    }
+
+    let provenance = Provenance::from_args(&args, image)?;
+    // The report needs the solver diagnostics even when the JSON should not carry them
+    let Generated {
+        mut output,
+        theme_ron,
+    } = generate(&provenance, debug_enabled || args.report.is_some())?;
+    for conflict in &output.pin_conflicts {
+        let under = conflict
+            .cvd
+            .map(|kind| format!(" under {kind} simulation"))
+            .unwrap_or_default();
+        eprintln!(
+            "warning: {} is {:.2}{} with pinned {}, below the {:.1} guardrail",
+            conflict.guardrail,
+            conflict.value,
+            under,
+            conflict.pinned.join(" and "),
+            conflict.min
+        );
     }
-
-    // Prepare sampling parameters
-    let sample_params = SampleParams {
-        path: args.image.clone(),
-        stride: 4,
-        min_lum: 0,
-        max_samples: 300_000,
-        max_dimension: Some(3200),
-        seed: 1,
-    };
-
-    // Sample pixels from image
-    let sample_result =
-        prepare_samples(&sample_params).context("Failed to load and sample image")?;
-
-    if sample_result.samples.is_empty() {
-        anyhow::bail!("No pixels sampled from image");
+    for conflict in &output.cvd_conflicts {
+        eprintln!(
+            "warning: {} is ΔE {:.2} under {} simulation, below the {:.1} guardrail; no candidate met it",
+            conflict.guardrail,
+            conflict.value,
+            conflict.cvd.unwrap_or("cvd"),
+            conflict.min
+        );
     }
-
-    // Convert samples to chosen color space
-    let space_upper = args.space.to_ascii_uppercase();
-    let dataset: Vec<[f32; 3]> = match space_upper.as_str() {
-        "CIELAB" | "LAB" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| color::rgb8_to_lab(rgb))
-            .collect(),
-        "RGB" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| [rgb[0] as f32, rgb[1] as f32, rgb[2] as f32])
-            .collect(),
-        "HSL" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| color::rgb8_to_hsl(rgb))
-            .collect(),
-        "HSV" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| color::rgb8_to_hsv(rgb))
-            .collect(),
-        "YUV" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| color::rgb8_to_yuv(rgb))
-            .collect(),
-        "CIELUV" | "LUV" => sample_result
-            .samples
-            .iter()
-            .map(|&rgb| color::rgb8_to_luv(rgb))
-            .collect(),
-        _ => anyhow::bail!("Unsupported color space: {}", args.space),
-    };
-
-    // Run K-means clustering
-    let k = args.k.min(dataset.len().max(1));
-    let kmeans_config = KMeansConfig {
-        k,
-        max_iters: 40,
-        tol: 1e-3,
-        seed: 1,
-        warm_start: None,
-        mini_batch: None,
-    };
-
-    let kmeans_result = run_kmeans(&dataset, &kmeans_config);
-
-    // Convert centroids to all color spaces
-    let mut clusters: Vec<ColorCluster> = Vec::with_capacity(kmeans_result.centroids.len());
-    for (centroid, &count) in kmeans_result
-        .centroids
-        .iter()
-        .zip(kmeans_result.counts.iter())
-    {
-        if count == 0 {
-            continue;
+    if let Some(report_path) = &args.report {
+        write_report(report_path, &output)?;
+        if !debug_enabled {
+            output.debug = None;
         }
-
-        // Convert centroid from clustering space to RGB
-        let rgb_u8 = match space_upper.as_str() {
-            "RGB" => [
-                centroid[0].round().clamp(0.0, 255.0) as u8,
-                centroid[1].round().clamp(0.0, 255.0) as u8,
-                centroid[2].round().clamp(0.0, 255.0) as u8,
-            ],
-            "HSL" => color::hsl_to_rgb8(*centroid),
-            "HSV" => color::hsv_to_rgb8(*centroid),
-            "YUV" => color::yuv_to_rgb8(*centroid),
-            "CIELAB" | "LAB" => color::lab_to_rgb8(*centroid),
-            "CIELUV" | "LUV" => color::luv_to_rgb8(*centroid),
-            _ => unreachable!(),
-        };
-
-        let hsv = color::rgb8_to_hsv(rgb_u8);
-        let lab = color::rgb8_to_lab(rgb_u8);
-
-        clusters.push(ColorCluster {
-            rgb: RgbValue {
-                r: rgb_u8[0],
-                g: rgb_u8[1],
-                b: rgb_u8[2],
-            },
-            hsv,
-            lab,
-            count,
-            share: (count as f64) / (sample_result.sampled_pixels as f64),
-        });
     }
 
-    // Sort clusters by count (descending) for consistency
-    clusters.sort_by(|a, b| b.count.cmp(&a.count));
-
-    // Map colors to theme element roles
-    let (role_assignments, pairwise_debug, roles_debug) =
-        map_colors_to_roles(&clusters, debug_enabled);
-    let scrollbar_enabled = !args.disable_scrollbar;
+    if args.preview_terminal {
+        eprint!(
+            "{}",
+            terminal_preview(
+                &output.clusters,
+                &output.role_assignments,
+                output.scrollbar_enabled
+            )
+        );
+    }
 
-    // Generate theme file if requested (before moving role_assignments)
+    // Generate theme file if requested
     if let Some(theme_path) = &args.theme_output {
-        let theme_ron = generate_theme_ron(&role_assignments, scrollbar_enabled);
-
-        // Ensure parent directory exists
-        if let Some(parent) = theme_path.parent() {
-            std::fs::create_dir_all(parent)
-                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
-        }
-
-        std::fs::write(&theme_path, theme_ron)
-            .with_context(|| format!("Failed to write theme to {}", theme_path.display()))?;
-
-        eprintln!("Theme written to: {}", theme_path.display());
+        write_theme(theme_path, &theme_ron)?;
     }
 
-    let duration_ms = start.elapsed().as_secs_f64() * 1000.0;
-
-    let output = ThemeGenOutput {
-        version: APP_VERSION.to_string(),
-        clusters,
-        role_assignments,
-        total_samples: sample_result.sampled_pixels,
-        iterations: kmeans_result.iterations,
-        duration_ms,
-        color_space: args.space.clone(),
-        scrollbar_enabled,
-        debug: if debug_enabled {
-            Some(DebugOutput {
-                pairwise: pairwise_debug,
-                roles: roles_debug,
-            })
-        } else {
-            None
-        },
-    };
-
     // Serialize to JSON
     let json_output =
         serde_json::to_string_pretty(&output).context("Failed to serialize output to JSON")?;
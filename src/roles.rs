@@ -0,0 +1,4471 @@
+//! Role solving: maps clustered colors onto rmpc theme roles (background, text,
+//! accent, active item, ...) under WCAG contrast guardrails.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::{self, CvdKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ColorRole {
+    Background,
+    Text,
+    Accent,
+    Border,
+    ActiveItem,
+    HighlightText,
+    Frame,
+    InactiveItem,
+    ProgressBar,
+    Scrollbar,
+    HeaderAccent,
+    Info,
+    Warn,
+    Error,
+    Debug,
+    Trace,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleAssignment {
+    pub role: ColorRole,
+    pub rgb: RgbValue,
+    pub hsv: [f32; 3],
+    pub lab: [f32; 3],
+    pub hex: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_cluster_index: Option<usize>,
+    pub confidence: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast_against_background: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub contrast_against_text: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+}
+
+const ACCENT_BG_MIN: f32 = 4.5;
+const ACCENT_TEXT_MIN: f32 = 4.5;
+const ACTIVE_BG_MIN: f32 = 3.5;
+const ACTIVE_BG_RELAXED_MIN: f32 = 3.5;
+const ACTIVE_TEXT_MIN: f32 = 4.5;
+const PEER_CONTRAST_MIN: f32 = 4.5;
+const PEER_DELTA_E_MIN: f32 = 25.0;
+const BRIGHTNESS_SEPARATION_MIN: f32 = 25.0;
+const RELAXED_PEER_CONTRAST_MIN: f32 = 3.5;
+const RELAXED_PEER_DELTA_E_MIN: f32 = 20.0;
+const HIGHLIGHT_TEXT_MIN_CONTRAST: f32 = 4.5;
+const HIGHLIGHT_TEXT_BG_MIN: f32 = 1.5;
+const FRAME_BG_MIN: f32 = 3.0;
+const FRAME_TEXT_MIN: f32 = 2.0;
+const MIN_CHROMA_THRESHOLD: f32 = 0.05;
+const MIN_HUE_DELTA_ACTIVE_BG: f32 = 30.0;
+const MIN_HUE_DELTA_ACTIVE_TEXT: f32 = 30.0;
+const MIN_HUE_DELTA_ACCENT_ACTIVE: f32 = 25.0;
+const MIN_HUE_DELTA_ACCENT_BG: f32 = 20.0;
+/// Minimum simulated ΔE between accent, active, background and highlight text
+/// when a CVD guardrail is requested.
+const CVD_DELTA_E_MIN: f32 = 20.0;
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub struct RgbValue {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ColorCluster {
+    pub rgb: RgbValue,
+    pub hsv: [f32; 3],
+    pub lab: [f32; 3],
+    pub count: usize,
+    pub share: f64,
+}
+
+impl ColorCluster {
+    /// A cluster of `rgb` covering `share` of the image, with its HSV and Lab
+    /// coordinates derived from it and no sample count.
+    pub fn from_rgb(rgb: [u8; 3], share: f64) -> Self {
+        Self {
+            rgb: RgbValue {
+                r: rgb[0],
+                g: rgb[1],
+                b: rgb[2],
+            },
+            hsv: color::rgb8_to_hsv(rgb),
+            lab: color::rgb8_to_lab(rgb),
+            count: 0,
+            share,
+        }
+    }
+}
+
+/// Dark blue background, cream text and two accents, for the tests that need
+/// a rendered theme rather than a particular solver outcome.
+#[cfg(test)]
+pub(crate) fn sample_palette() -> Vec<ColorCluster> {
+    vec![
+        ColorCluster::from_rgb([24, 26, 38], 0.55),
+        ColorCluster::from_rgb([226, 214, 190], 0.2),
+        ColorCluster::from_rgb([214, 92, 64], 0.15),
+        ColorCluster::from_rgb([72, 160, 196], 0.1),
+    ]
+}
+
+/// Snapshot of the compiled-in base contrast thresholds, so replay can check
+/// that the running build uses the same values. Preset overrides of these floors
+/// are recorded and replayed through the preset in `ThemeConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GuardrailParams {
+    pub accent_bg_min: f32,
+    pub accent_text_min: f32,
+    pub active_bg_min: f32,
+    pub active_bg_relaxed_min: f32,
+    pub active_text_min: f32,
+    pub peer_contrast_min: f32,
+    pub peer_delta_e_min: f32,
+    pub brightness_separation_min: f32,
+    pub relaxed_peer_contrast_min: f32,
+    pub relaxed_peer_delta_e_min: f32,
+    pub highlight_text_min_contrast: f32,
+    pub highlight_text_bg_min: f32,
+    pub frame_bg_min: f32,
+    pub frame_text_min: f32,
+    pub min_chroma_threshold: f32,
+    pub min_hue_delta_active_bg: f32,
+    pub min_hue_delta_active_text: f32,
+    pub min_hue_delta_accent_active: f32,
+    pub min_hue_delta_accent_bg: f32,
+    /// Absent from outputs written before the CVD guardrail existed.
+    #[serde(default = "default_cvd_delta_e_min")]
+    pub cvd_delta_e_min: f32,
+}
+
+fn default_cvd_delta_e_min() -> f32 {
+    CVD_DELTA_E_MIN
+}
+
+impl GuardrailParams {
+    pub fn current() -> Self {
+        Self {
+            accent_bg_min: ACCENT_BG_MIN,
+            accent_text_min: ACCENT_TEXT_MIN,
+            active_bg_min: ACTIVE_BG_MIN,
+            active_bg_relaxed_min: ACTIVE_BG_RELAXED_MIN,
+            active_text_min: ACTIVE_TEXT_MIN,
+            peer_contrast_min: PEER_CONTRAST_MIN,
+            peer_delta_e_min: PEER_DELTA_E_MIN,
+            brightness_separation_min: BRIGHTNESS_SEPARATION_MIN,
+            relaxed_peer_contrast_min: RELAXED_PEER_CONTRAST_MIN,
+            relaxed_peer_delta_e_min: RELAXED_PEER_DELTA_E_MIN,
+            highlight_text_min_contrast: HIGHLIGHT_TEXT_MIN_CONTRAST,
+            highlight_text_bg_min: HIGHLIGHT_TEXT_BG_MIN,
+            frame_bg_min: FRAME_BG_MIN,
+            frame_text_min: FRAME_TEXT_MIN,
+            min_chroma_threshold: MIN_CHROMA_THRESHOLD,
+            min_hue_delta_active_bg: MIN_HUE_DELTA_ACTIVE_BG,
+            min_hue_delta_active_text: MIN_HUE_DELTA_ACTIVE_TEXT,
+            min_hue_delta_accent_active: MIN_HUE_DELTA_ACCENT_ACTIVE,
+            min_hue_delta_accent_bg: MIN_HUE_DELTA_ACCENT_BG,
+            cvd_delta_e_min: CVD_DELTA_E_MIN,
+        }
+    }
+}
+
+/// How the core roles (background, text, accent, active item, border) are chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoleSolver {
+    /// Fix background, then text, then the accent/active pair, then border,
+    /// each one best-first given the roles already picked.
+    #[default]
+    Greedy,
+    /// Beam search over complete assignments scored by a weighted objective;
+    /// contrast floors are hard constraints.
+    Global,
+}
+
+impl std::str::FromStr for RoleSolver {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "greedy" => Ok(RoleSolver::Greedy),
+            "global" => Ok(RoleSolver::Global),
+            other => Err(format!(
+                "unknown role solver '{other}' (expected greedy or global)"
+            )),
+        }
+    }
+}
+
+/// Color-vision deficiencies the accent/active/background/highlight
+/// separation must survive when `RoleOptions::cvd` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CvdMode {
+    Protan,
+    Deutan,
+    Tritan,
+    All,
+}
+
+impl CvdMode {
+    pub fn kinds(self) -> &'static [CvdKind] {
+        match self {
+            CvdMode::Protan => &[CvdKind::Protanopia],
+            CvdMode::Deutan => &[CvdKind::Deuteranopia],
+            CvdMode::Tritan => &[CvdKind::Tritanopia],
+            CvdMode::All => &CvdKind::ALL,
+        }
+    }
+}
+
+impl std::str::FromStr for CvdMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "protan" | "protanopia" => Ok(CvdMode::Protan),
+            "deutan" | "deuteranopia" => Ok(CvdMode::Deutan),
+            "tritan" | "tritanopia" => Ok(CvdMode::Tritan),
+            "all" => Ok(CvdMode::All),
+            other => Err(format!(
+                "unknown color vision deficiency '{other}' (expected protan, deutan, tritan or all)"
+            )),
+        }
+    }
+}
+
+/// Colors fixed by the user instead of solved. Pinned roles are inputs to
+/// [`map_colors_to_roles`]; every other role is solved around them with the
+/// usual guardrails. `None` (written `auto`) leaves a role to the solver.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct RolePins {
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub background: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub text: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub accent: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub active: Option<[u8; 3]>,
+    /// Inactive tabs; also stands in for the border the solver would pick.
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub inactive: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub highlight_text: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub frame: Option<[u8; 3]>,
+    #[serde(with = "pin_color", skip_serializing_if = "Option::is_none")]
+    pub header_accent: Option<[u8; 3]>,
+}
+
+impl RolePins {
+    /// Role names accepted by [`RolePins::set`].
+    pub const NAMES: [&'static str; 8] = [
+        "background",
+        "text",
+        "accent",
+        "active",
+        "inactive",
+        "highlight_text",
+        "frame",
+        "header_accent",
+    ];
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Pin the role called `name` to `color`, or hand it back to the solver
+    /// when `color` is `None`.
+    pub fn set(&mut self, name: &str, color: Option<[u8; 3]>) -> Result<(), String> {
+        let slot = match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "background" | "bg" => &mut self.background,
+            "text" => &mut self.text,
+            "accent" => &mut self.accent,
+            "active" | "active_item" => &mut self.active,
+            "inactive" | "inactive_item" => &mut self.inactive,
+            "highlight_text" => &mut self.highlight_text,
+            "frame" => &mut self.frame,
+            "header_accent" => &mut self.header_accent,
+            "progress_bar" | "scrollbar" => {
+                return Err(format!(
+                    "{name} reuses the accent (progress_bar) or active (scrollbar) color; pin that instead"
+                ))
+            }
+            _ => {
+                return Err(format!(
+                    "unknown role '{name}' (expected one of {})",
+                    Self::NAMES.join(", ")
+                ))
+            }
+        };
+        *slot = color;
+        Ok(())
+    }
+
+    /// Pinned colors drawn on the background next to the border; border
+    /// candidates keep clear of them.
+    fn border_neighbors(&self) -> Vec<[f32; 3]> {
+        [self.frame, self.header_accent]
+            .into_iter()
+            .flatten()
+            .map(color::rgb8_to_lab)
+            .collect()
+    }
+
+    /// The pin name of `role` when it is pinned.
+    fn pinned_name(&self, role: ColorRole) -> Option<&'static str> {
+        let (name, pin) = match role {
+            ColorRole::Background => ("background", self.background),
+            ColorRole::Text => ("text", self.text),
+            ColorRole::Accent => ("accent", self.accent),
+            ColorRole::ActiveItem => ("active", self.active),
+            ColorRole::InactiveItem | ColorRole::Border => ("inactive", self.inactive),
+            ColorRole::HighlightText => ("highlight_text", self.highlight_text),
+            ColorRole::Frame => ("frame", self.frame),
+            ColorRole::HeaderAccent => ("header_accent", self.header_accent),
+            _ => return None,
+        };
+        pin.map(|_| name)
+    }
+}
+
+/// Pins serialize as `#rrggbb`; `auto` reads back as unpinned.
+mod pin_color {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::color;
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<[u8; 3]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(rgb) => serializer.serialize_str(&color::rgb_to_hex(*rgb)),
+            None => serializer.serialize_str("auto"),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<[u8; 3]>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        if value.trim().eq_ignore_ascii_case("auto") {
+            return Ok(None);
+        }
+        color::hex_to_rgb(&value).map(Some).ok_or_else(|| {
+            serde::de::Error::custom(format!(
+                "invalid pin color '{value}' (expected #rrggbb or auto)"
+            ))
+        })
+    }
+}
+
+/// A named reshaping of the role pipeline, selected with `--preset`. The
+/// palette adjustments apply to every color the non-background roles are drawn
+/// from; the background keeps its cluster color. Fields left unset keep the
+/// default behaviour, so config files can define presets of their own.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct Preset {
+    /// Weight of OKLCH chroma when ranking accent and active candidates.
+    pub chroma_weight: f32,
+    /// Cap on OKLCH chroma.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_chroma: Option<f32>,
+    /// Floor on OKLCH lightness.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_lightness: Option<f32>,
+    /// Clamp hues to within this many degrees of the background hue. Also drops
+    /// the hue separation checks between accent, active, text and background,
+    /// which a single hue family cannot meet. Status colors keep their hues.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hue_spread: Option<f32>,
+    /// Raised accent/background contrast floor; also applies to the header
+    /// accent and status colors. Values below the built-in floor are ignored.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accent_bg_min: Option<f32>,
+    /// Raised active/text contrast floor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active_text_min: Option<f32>,
+}
+
+impl Preset {
+    /// Names accepted by [`Preset::builtin`].
+    pub const BUILTIN_NAMES: [&'static str; 5] =
+        ["vivid", "muted", "pastel", "monochrome", "high-contrast"];
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        let preset = match name {
+            // Prefer the most saturated clusters for accent and active
+            "vivid" => Self {
+                chroma_weight: 12.0,
+                ..Self::default()
+            },
+            "muted" => Self {
+                max_chroma: Some(0.07),
+                ..Self::default()
+            },
+            "pastel" => Self {
+                max_chroma: Some(0.1),
+                min_lightness: Some(0.8),
+                ..Self::default()
+            },
+            "monochrome" => Self {
+                hue_spread: Some(10.0),
+                ..Self::default()
+            },
+            // WCAG AAA for accent text and the playing row
+            "high-contrast" => Self {
+                accent_bg_min: Some(7.0),
+                active_text_min: Some(7.0),
+                ..Self::default()
+            },
+            _ => return None,
+        };
+        Some(preset)
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn reshapes_palette(&self) -> bool {
+        self.max_chroma.is_some() || self.min_lightness.is_some() || self.hue_spread.is_some()
+    }
+
+    fn accent_floor(&self) -> f32 {
+        self.accent_bg_min
+            .map_or(ACCENT_BG_MIN, |min| min.max(ACCENT_BG_MIN))
+    }
+
+    fn active_text_floor(&self) -> f32 {
+        self.active_text_min
+            .map_or(ACTIVE_TEXT_MIN, |min| min.max(ACTIVE_TEXT_MIN))
+    }
+
+    /// `clusters` with their colors reshaped in OKLCH, leaving the background
+    /// cluster `bg_idx` untouched. Cluster indices are preserved.
+    fn style_clusters<'a>(
+        &self,
+        clusters: &'a [ColorCluster],
+        bg_lab: [f32; 3],
+        bg_idx: Option<usize>,
+    ) -> Cow<'a, [ColorCluster]> {
+        if !self.reshapes_palette() {
+            return Cow::Borrowed(clusters);
+        }
+        let oklch_of =
+            |lab: [f32; 3]| color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(lab)));
+        // A neutral background has no hue family; borrow the dominant chromatic one
+        let family_hue = std::iter::once(bg_lab)
+            .chain(clusters.iter().map(|cluster| cluster.lab))
+            .map(oklch_of)
+            .find(|lch| lch[1] >= MIN_CHROMA_THRESHOLD)
+            .map(|lch| lch[2]);
+
+        let styled = clusters
+            .iter()
+            .enumerate()
+            .map(|(idx, cluster)| {
+                if Some(idx) == bg_idx {
+                    return cluster.clone();
+                }
+                let [mut l, mut c, mut h] = oklch_of(cluster.lab);
+                if let Some(min) = self.min_lightness {
+                    l = l.max(min);
+                }
+                if let Some(max) = self.max_chroma {
+                    c = c.min(max);
+                }
+                if let (Some(spread), Some(family)) = (self.hue_spread, family_hue) {
+                    h = family + signed_hue_delta(family, h).clamp(-spread, spread);
+                }
+                let rgb = color::lab_to_rgb8(oklch_to_lab_mapped(l, c, h));
+                ColorCluster {
+                    count: cluster.count,
+                    ..ColorCluster::from_rgb(rgb, cluster.share)
+                }
+            })
+            .collect();
+        Cow::Owned(styled)
+    }
+}
+
+fn oklch_chroma(cluster: &ColorCluster) -> f32 {
+    color::oklab_to_oklch(color::rgb8_to_oklab([
+        cluster.rgb.r,
+        cluster.rgb.g,
+        cluster.rgb.b,
+    ]))[1]
+}
+
+/// Settings that shape role selection.
+#[derive(Debug, Clone, Default)]
+pub struct RoleOptions {
+    pub solver: RoleSolver,
+    /// Also require role separation under simulated color vision deficiency.
+    pub cvd: Option<CvdMode>,
+    pub pins: RolePins,
+    pub preset: Preset,
+}
+
+/// A guardrail that a pinned color, or a `--cvd` fallback, leaves unmet in the
+/// solved theme.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PinConflict {
+    /// The two roles compared, foreground first, e.g. `accent/bg`.
+    pub guardrail: &'static str,
+    /// Which of the two roles are pinned; empty for `--cvd` fallbacks.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pinned: Vec<&'static str>,
+    /// Contrast ratio, or ΔE for `accent/active` and simulated deficiencies.
+    pub value: f32,
+    pub min: f32,
+    /// The simulated color vision deficiency, for `--cvd` checks.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cvd: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugOutput {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pairwise: Option<PairwiseDebug>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roles: Option<RolesDebug>,
+    /// Evenly thinned pixel samples for plotting; too bulky for the JSON output.
+    #[serde(skip)]
+    pub samples: Vec<[u8; 3]>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolesDebug {
+    highlight_text: RoleDebugEntry,
+    frame: RoleDebugEntry,
+    playing_row: RoleDebugEntry,
+    /// Simulated ΔE for the `--cvd` deficiencies, or all three when unset.
+    cvd: Vec<CvdDebugEntry>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CvdDebugEntry {
+    kind: &'static str,
+    accent_active: f32,
+    accent_background: f32,
+    active_background: f32,
+    highlight_active: f32,
+    passes: bool,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RoleDebugEntry {
+    hex: String,
+    origin: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contrast_against_active: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contrast_against_background: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    contrast_against_text: Option<f32>,
+}
+
+/// Good background: low-mid saturation, reasonable lightness
+fn background_suitable(cluster: &ColorCluster) -> bool {
+    let s = cluster.hsv[1];
+    let l = cluster.lab[0];
+    s < 0.4 && l > 15.0 && l < 85.0
+}
+
+fn cvd_delta_e(a: [f32; 3], b: [f32; 3], kind: CvdKind) -> f32 {
+    color::delta_e_cie76(
+        color::simulate_cvd_lab(a, kind),
+        color::simulate_cvd_lab(b, kind),
+    )
+}
+
+/// Whether every pair stays at least `CVD_DELTA_E_MIN` apart under each
+/// simulated deficiency. Always true without a CVD guardrail.
+fn cvd_separation_ok(pairs: &[([f32; 3], [f32; 3])], cvd: Option<CvdMode>) -> bool {
+    let Some(mode) = cvd else {
+        return true;
+    };
+    mode.kinds().iter().all(|&kind| {
+        pairs
+            .iter()
+            .all(|&(a, b)| cvd_delta_e(a, b, kind) >= CVD_DELTA_E_MIN)
+    })
+}
+
+fn pair_cvd_ok(accent: [f32; 3], active: [f32; 3], bg: [f32; 3], cvd: Option<CvdMode>) -> bool {
+    cvd_separation_ok(&[(accent, active), (accent, bg), (active, bg)], cvd)
+}
+
+/// Select background color: prefer most dominant with reasonable saturation/lightness
+fn select_background(clusters: &[ColorCluster]) -> (usize, f32) {
+    // Prefer dominant colors with moderate properties
+    if let Some(idx) = clusters.iter().position(background_suitable) {
+        return (idx, 0.9);
+    }
+
+    // Fallback: most dominant color regardless of properties
+    (0, 0.5)
+}
+
+/// Select text color: highest contrast against background
+fn select_text_color(clusters: &[ColorCluster], bg_lab: [f32; 3]) -> (usize, f32) {
+    let mut best_idx = 0;
+    let mut best_contrast = 0.0;
+
+    for (idx, cluster) in clusters.iter().enumerate() {
+        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
+        if contrast > best_contrast {
+            best_contrast = contrast;
+            best_idx = idx;
+        }
+    }
+
+    // Check if we meet WCAG AA standard (4.5:1)
+    let confidence = if best_contrast >= 4.5 { 0.9 } else { 0.6 };
+    (best_idx, confidence)
+}
+
+#[derive(Clone, Debug)]
+struct Candidate {
+    index: usize,
+    score: f32,
+}
+
+fn sort_candidates_desc(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(Ordering::Equal));
+}
+
+#[derive(Clone, Debug)]
+struct RoleColorCandidate {
+    lab: [f32; 3],
+    source_cluster_index: Option<usize>,
+    origin_label: String,
+    /// 0 raw cluster, 2 lightness-adjusted cluster, 3 harmony synthesis,
+    /// 4 synthetic midline or gray. Doubled so harmony ranks in between.
+    provenance_rank: u8,
+    base_score: f32,
+}
+
+impl RoleColorCandidate {
+    fn confidence(&self) -> f32 {
+        match self.provenance_rank {
+            0 if self.base_score > 2.0 => 0.9,
+            0 => 0.75,
+            2 => 0.7,
+            3 => 0.55,
+            _ => 0.45,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PairwiseMetrics {
+    accent_bg: f32,
+    accent_text: f32,
+    accent_active: f32,
+    active_bg: f32,
+    active_text: f32,
+    delta_e: f32,
+    accent_l: f32,
+    active_l: f32,
+    accent_hue: f32,
+    accent_chroma: f32,
+    active_hue: f32,
+    active_chroma: f32,
+    background_hue: f32,
+    background_chroma: f32,
+    text_hue: f32,
+    text_chroma: f32,
+}
+
+impl PairwiseMetrics {
+    fn min_contrast(&self) -> f32 {
+        self.accent_bg
+            .min(self.accent_text)
+            .min(self.accent_active)
+            .min(self.active_bg)
+            .min(self.active_text)
+    }
+
+    fn brightness_separation(&self) -> f32 {
+        (self.accent_l - self.active_l).abs()
+    }
+
+    fn avg_contrast(&self) -> f32 {
+        (self.accent_bg + self.accent_text + self.accent_active + self.active_bg + self.active_text)
+            / 5.0
+    }
+}
+
+fn hue_separation_ok(h1: f32, c1: f32, h2: f32, c2: f32, min_delta: f32) -> bool {
+    if c1 < MIN_CHROMA_THRESHOLD || c2 < MIN_CHROMA_THRESHOLD {
+        return true;
+    }
+    color::delta_hue_degrees(h1, h2) >= min_delta
+}
+
+fn oklch_to_lab(l: f32, c: f32, h_deg: f32) -> [f32; 3] {
+    let oklch = [l, c, h_deg];
+    let oklab = color::oklch_to_oklab(oklch);
+    let rgb = color::oklab_to_rgb8(oklab);
+    color::rgb8_to_lab(rgb)
+}
+
+fn derive_active_with_hue(
+    background_lab: [f32; 3],
+    text_lab: [f32; 3],
+    _accent_lab: [f32; 3],
+    bg_oklch: [f32; 3],
+    text_oklch: [f32; 3],
+    accent_oklch: [f32; 3],
+) -> Option<([f32; 3], String)> {
+    // Choose a target hue: prefer accent hue if distinct from background; otherwise flip background hue
+    let use_accent = hue_separation_ok(
+        accent_oklch[2],
+        accent_oklch[1],
+        bg_oklch[2],
+        bg_oklch[1],
+        MIN_HUE_DELTA_ACTIVE_BG,
+    );
+    let target_hue = if use_accent {
+        accent_oklch[2]
+    } else {
+        (bg_oklch[2] + 180.0) % 360.0
+    };
+    let base_chroma = bg_oklch[1].max(accent_oklch[1]).clamp(0.06, 0.18);
+
+    // Try darkening first (usually safer for highlight backgrounds), then lightening
+    let directions = [-1.0f32, 1.0f32];
+    for step in 1..=12 {
+        let delta = step as f32 * 3.0;
+        for &dir in &directions {
+            let l = (bg_oklch[0] + dir * delta).clamp(0.0, 1.0);
+            let lab = oklch_to_lab(l, base_chroma, target_hue);
+            let c_bg = color::calculate_contrast_ratio(lab, background_lab);
+            if c_bg < ACTIVE_BG_MIN {
+                continue;
+            }
+            let c_text = color::calculate_contrast_ratio(lab, text_lab);
+            if c_text < ACTIVE_TEXT_MIN {
+                continue;
+            }
+            // Enforce hue separation against background and text if both sides have chroma
+            if !hue_separation_ok(
+                target_hue,
+                base_chroma,
+                bg_oklch[2],
+                bg_oklch[1],
+                MIN_HUE_DELTA_ACTIVE_BG,
+            ) {
+                continue;
+            }
+            if !hue_separation_ok(
+                target_hue,
+                base_chroma,
+                text_oklch[2],
+                text_oklch[1],
+                MIN_HUE_DELTA_ACTIVE_TEXT,
+            ) {
+                continue;
+            }
+            let origin = if use_accent {
+                format!(
+                    "accent_hue:{:.1} L{:+.1}",
+                    target_hue,
+                    l * 100.0 - background_lab[0]
+                )
+            } else {
+                format!(
+                    "bg_hue_rotated:{:.1} L{:+.1}",
+                    target_hue,
+                    l * 100.0 - background_lab[0]
+                )
+            };
+            return Some((lab, origin));
+        }
+    }
+    None
+}
+
+#[derive(Clone, Debug)]
+struct PairwiseResult {
+    accent: RoleColorCandidate,
+    active: RoleColorCandidate,
+    metrics: PairwiseMetrics,
+    provenance_score: u8,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PairwiseDebugEntry {
+    pub accent_hex: String,
+    pub accent_origin: String,
+    pub active_hex: String,
+    pub active_origin: String,
+    pub accent_bg: f32,
+    pub accent_text: f32,
+    pub accent_active: f32,
+    pub active_bg: f32,
+    pub active_text: f32,
+    pub delta_e: f32,
+    pub min_contrast: f32,
+    pub brightness_separation: f32,
+    pub provenance_score: u8,
+    pub accent_hue: f32,
+    pub accent_chroma: f32,
+    pub active_hue: f32,
+    pub active_chroma: f32,
+    pub background_hue: f32,
+    pub background_chroma: f32,
+    pub text_hue: f32,
+    pub text_chroma: f32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PairwiseDebug {
+    pub evaluated_pairs: usize,
+    pub pass_mode: String,
+    /// Stages the solver tries, in order; `pass_mode` names the one it used.
+    pub stages: Vec<SolverStage>,
+    pub winning_pair: PairwiseDebugEntry,
+    pub top_pairs: Vec<PairwiseDebugEntry>,
+    pub accent_candidates: Vec<PairCandidateDebug>,
+    pub active_candidates: Vec<PairCandidateDebug>,
+}
+
+/// A relaxation stage of the accent/active solver, described with the floors
+/// it applied under the run's preset and `--cvd` setting.
+#[derive(Debug, Serialize, Clone)]
+pub struct SolverStage {
+    pub name: &'static str,
+    pub description: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PairCandidateDebug {
+    pub hex: String,
+    pub origin: String,
+    pub provenance_rank: u8,
+    pub base_score: f32,
+    pub contrast_background: f32,
+    pub contrast_text: f32,
+}
+
+/// One guardrail measured on a debug entry, with the floors the strict and
+/// relaxed passes compare it against.
+#[derive(Debug, Clone, Copy)]
+pub struct GuardrailCheck {
+    pub name: &'static str,
+    pub value: f32,
+    pub strict_min: f32,
+    pub relaxed_min: f32,
+    /// Hue checks do not apply when either color is near-neutral.
+    pub waived: bool,
+}
+
+impl GuardrailCheck {
+    fn floor(name: &'static str, value: f32, strict_min: f32, relaxed_min: f32) -> Self {
+        Self {
+            name,
+            value,
+            strict_min,
+            relaxed_min,
+            waived: false,
+        }
+    }
+
+    fn hue(name: &'static str, hues: [(f32, f32); 2], min_delta: f32) -> Self {
+        let [(h1, c1), (h2, c2)] = hues;
+        Self {
+            name,
+            value: color::delta_hue_degrees(h1, h2),
+            strict_min: min_delta,
+            relaxed_min: min_delta,
+            waived: c1 < MIN_CHROMA_THRESHOLD || c2 < MIN_CHROMA_THRESHOLD,
+        }
+    }
+
+    pub fn passes_strict(&self) -> bool {
+        self.waived || self.value >= self.strict_min
+    }
+
+    pub fn passes_relaxed(&self) -> bool {
+        self.waived || self.value >= self.relaxed_min
+    }
+}
+
+impl PairwiseDebugEntry {
+    /// The pairwise guardrails of the strict and relaxed passes, in the order
+    /// the solver applies them.
+    pub fn guardrail_checks(&self) -> Vec<GuardrailCheck> {
+        let (strict, relaxed) = (STRICT_GUARDRAILS, RELAXED_GUARDRAILS);
+        let accent = (self.accent_hue, self.accent_chroma);
+        let active = (self.active_hue, self.active_chroma);
+        let background = (self.background_hue, self.background_chroma);
+        let text = (self.text_hue, self.text_chroma);
+        vec![
+            GuardrailCheck::floor(
+                "accent/bg",
+                self.accent_bg,
+                strict.min_accent_vs_bg,
+                relaxed.min_accent_vs_bg,
+            ),
+            GuardrailCheck::floor(
+                "accent/text",
+                self.accent_text,
+                strict.min_accent_vs_text,
+                relaxed.min_accent_vs_text,
+            ),
+            GuardrailCheck::floor(
+                "active/bg",
+                self.active_bg,
+                strict.min_active_vs_bg,
+                relaxed.min_active_vs_bg,
+            ),
+            GuardrailCheck::floor(
+                "active/text",
+                self.active_text,
+                strict.min_active_vs_text,
+                relaxed.min_active_vs_text,
+            ),
+            GuardrailCheck::floor(
+                "accent/active",
+                self.accent_active,
+                strict.min_peer_contrast,
+                relaxed.min_peer_contrast,
+            ),
+            GuardrailCheck::floor(
+                "ΔE",
+                self.delta_e,
+                strict.min_peer_delta_e,
+                relaxed.min_peer_delta_e,
+            ),
+            GuardrailCheck::floor(
+                "ΔL",
+                self.brightness_separation,
+                strict.min_brightness_separation,
+                relaxed.min_brightness_separation,
+            ),
+            GuardrailCheck::hue(
+                "hue active/bg",
+                [active, background],
+                MIN_HUE_DELTA_ACTIVE_BG,
+            ),
+            GuardrailCheck::hue("hue active/text", [active, text], MIN_HUE_DELTA_ACTIVE_TEXT),
+            GuardrailCheck::hue(
+                "hue accent/active",
+                [accent, active],
+                MIN_HUE_DELTA_ACCENT_ACTIVE,
+            ),
+            GuardrailCheck::hue(
+                "hue accent/bg",
+                [accent, background],
+                MIN_HUE_DELTA_ACCENT_BG,
+            ),
+        ]
+    }
+}
+
+impl PairCandidateDebug {
+    /// Contrast floors this candidate must clear to serve as the accent.
+    pub fn accent_checks(&self) -> [GuardrailCheck; 2] {
+        [
+            GuardrailCheck::floor(
+                "vs bg",
+                self.contrast_background,
+                STRICT_GUARDRAILS.min_accent_vs_bg,
+                RELAXED_GUARDRAILS.min_accent_vs_bg,
+            ),
+            GuardrailCheck::floor(
+                "vs text",
+                self.contrast_text,
+                STRICT_GUARDRAILS.min_accent_vs_text,
+                RELAXED_GUARDRAILS.min_accent_vs_text,
+            ),
+        ]
+    }
+
+    /// Contrast floors this candidate must clear to serve as the active item.
+    pub fn active_checks(&self) -> [GuardrailCheck; 2] {
+        [
+            GuardrailCheck::floor(
+                "vs bg",
+                self.contrast_background,
+                STRICT_GUARDRAILS.min_active_vs_bg,
+                RELAXED_GUARDRAILS.min_active_vs_bg,
+            ),
+            GuardrailCheck::floor(
+                "vs text",
+                self.contrast_text,
+                STRICT_GUARDRAILS.min_active_vs_text,
+                RELAXED_GUARDRAILS.min_active_vs_text,
+            ),
+        ]
+    }
+}
+
+/// Rank accent color candidates: high saturation with good contrast
+fn rank_accent_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &[usize],
+    chroma_weight: f32,
+) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+
+    for (idx, cluster) in clusters.iter().enumerate() {
+        if used_indices.contains(&idx) {
+            continue;
+        }
+
+        let s = cluster.hsv[1];
+        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
+        let score = s * 2.0 + (contrast / 21.0) * 3.0 + chroma_weight * oklch_chroma(cluster);
+
+        if contrast > 1.5 {
+            candidates.push(Candidate { index: idx, score });
+        }
+    }
+
+    sort_candidates_desc(&mut candidates);
+    candidates
+}
+
+/// Select border color: mid-saturation, distinct from background
+fn select_border_color(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &[usize],
+    neighbors: &[[f32; 3]],
+) -> (usize, f32) {
+    let (best_idx, best_score) =
+        best_border(clusters, bg_lab, used_indices, neighbors).unwrap_or((0, 0.0));
+    let confidence = if best_score > 0.5 { 0.8 } else { 0.5 };
+    (best_idx, confidence)
+}
+
+/// Best unused border cluster and its score, or `None` when no cluster is
+/// perceptually distinct from the background and the pinned `neighbors`.
+fn best_border(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &[usize],
+    neighbors: &[[f32; 3]],
+) -> Option<(usize, f32)> {
+    let mut best: Option<(usize, f32)> = None;
+
+    for (idx, cluster) in clusters.iter().enumerate() {
+        if used_indices.contains(&idx)
+            || neighbors
+                .iter()
+                .any(|&lab| color::delta_e_cie76(lab, cluster.lab) <= 20.0)
+        {
+            continue;
+        }
+
+        let s = cluster.hsv[1];
+        let delta_e = color::delta_e_cie76(bg_lab, cluster.lab);
+
+        // Prefer mid-saturation with good perceptual distance
+        let score = if (0.2..=0.6).contains(&s) {
+            delta_e / 100.0 + s
+        } else {
+            delta_e / 100.0
+        };
+
+        if delta_e > 20.0 && best.is_none_or(|(_, top)| score > top) {
+            best = Some((idx, score));
+        }
+    }
+
+    best
+}
+
+/// Rank active item color candidates: bright and saturated
+fn rank_active_item_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &[usize],
+    chroma_weight: f32,
+) -> Vec<Candidate> {
+    let mut candidates = Vec::new();
+
+    for (idx, cluster) in clusters.iter().enumerate() {
+        if used_indices.contains(&idx) {
+            continue;
+        }
+
+        let s = cluster.hsv[1];
+        let v = cluster.hsv[2];
+        let contrast = color::calculate_contrast_ratio(bg_lab, cluster.lab);
+        let score = v + s + (contrast / 21.0) + chroma_weight * oklch_chroma(cluster);
+
+        if v > 0.4 {
+            candidates.push(Candidate { index: idx, score });
+        }
+    }
+
+    sort_candidates_desc(&mut candidates);
+    candidates
+}
+
+#[derive(Clone, Copy)]
+struct GuardrailConfig {
+    min_contrast_bg: f32,
+    min_contrast_text: f32,
+    min_contrast_peer: Option<f32>,
+    min_delta_e_peer: Option<f32>,
+    adjust_step: f32,
+    max_adjust_steps: usize,
+}
+
+#[derive(Clone, Copy)]
+struct PairwiseGuardrails {
+    min_accent_vs_bg: f32,
+    min_accent_vs_text: f32,
+    min_active_vs_bg: f32,
+    min_active_vs_text: f32,
+    min_peer_contrast: f32,
+    min_peer_delta_e: f32,
+    min_brightness_separation: f32,
+    /// Require the accent/active/background/text hue separation minimums.
+    hue_separation: bool,
+}
+
+impl PairwiseGuardrails {
+    /// These guardrails with the floors and hue checks `preset` changes.
+    fn for_preset(self, preset: &Preset) -> Self {
+        Self {
+            min_accent_vs_bg: self.min_accent_vs_bg.max(preset.accent_floor()),
+            min_active_vs_text: self.min_active_vs_text.max(preset.active_text_floor()),
+            hue_separation: self.hue_separation && preset.hue_spread.is_none(),
+            ..self
+        }
+    }
+
+    /// The floors as text, e.g. `accent/bg 4.5:1, …, accent/active ΔE 25, hues apart`.
+    fn describe(&self, cvd: Option<CvdMode>) -> String {
+        let mut text = format!(
+            "accent/bg {:.1}:1, accent/text {:.1}:1, active/bg {:.1}:1, active/text {:.1}:1",
+            self.min_accent_vs_bg,
+            self.min_accent_vs_text,
+            self.min_active_vs_bg,
+            self.min_active_vs_text
+        );
+        if self.min_peer_contrast > 1.0 {
+            text.push_str(&format!(", accent/active {:.1}:1", self.min_peer_contrast));
+        }
+        text.push_str(&format!(", accent/active ΔE {:.0}", self.min_peer_delta_e));
+        if self.min_brightness_separation > 0.0 {
+            text.push_str(&format!(", ΔL {:.1}", self.min_brightness_separation));
+        }
+        if self.hue_separation {
+            text.push_str(", hues apart");
+        }
+        text + &cvd_clause(cvd)
+    }
+}
+
+/// The `--cvd` separation every stage also requires, or nothing when unset.
+fn cvd_clause(cvd: Option<CvdMode>) -> String {
+    cvd.map(|mode| {
+        let kinds: Vec<&str> = mode.kinds().iter().map(|kind| kind.name()).collect();
+        format!(
+            ", ΔE {CVD_DELTA_E_MIN:.0} under {} simulation",
+            kinds.join("/")
+        )
+    })
+    .unwrap_or_default()
+}
+
+fn greedy_stages(
+    strict: PairwiseGuardrails,
+    relaxed: PairwiseGuardrails,
+    cvd: Option<CvdMode>,
+) -> Vec<SolverStage> {
+    let kept_apart = match (relaxed.hue_separation, cvd.is_some()) {
+        (true, true) => "keep their hues apart and clear the --cvd separation",
+        (true, false) => "keep their hues apart",
+        (false, true) => "clear the --cvd separation",
+        (false, false) => "",
+    };
+    let fallback = if kept_apart.is_empty() {
+        "best minimum contrast over every pair, ignoring contrast floors".to_string()
+    } else {
+        format!(
+            "best minimum contrast ignoring contrast floors, among pairs that {kept_apart} \
+             if any do, otherwise over every pair"
+        )
+    };
+    vec![
+        SolverStage {
+            name: "strict",
+            description: strict.describe(cvd),
+        },
+        SolverStage {
+            name: "relaxed",
+            description: relaxed.describe(cvd),
+        },
+        SolverStage {
+            name: "fallback",
+            description: fallback,
+        },
+    ]
+}
+
+fn global_stages(
+    strict: PairwiseGuardrails,
+    global: PairwiseGuardrails,
+    cvd: Option<CvdMode>,
+) -> Vec<SolverStage> {
+    vec![
+        SolverStage {
+            name: "global:strict",
+            description: format!(
+                "the best complete assignment also clears the strict floors: {}",
+                // The global search scores hue separation instead of checking it
+                PairwiseGuardrails {
+                    hue_separation: false,
+                    ..strict
+                }
+                .describe(cvd)
+            ),
+        },
+        SolverStage {
+            name: "global",
+            description: format!(
+                "the best complete assignment clears the hard floors only: {}",
+                global.describe(cvd)
+            ),
+        },
+    ]
+}
+
+const STRICT_GUARDRAILS: PairwiseGuardrails = PairwiseGuardrails {
+    min_accent_vs_bg: ACCENT_BG_MIN,
+    min_accent_vs_text: ACCENT_TEXT_MIN,
+    min_active_vs_bg: ACTIVE_BG_MIN,
+    min_active_vs_text: ACTIVE_TEXT_MIN,
+    min_peer_contrast: PEER_CONTRAST_MIN,
+    min_peer_delta_e: PEER_DELTA_E_MIN,
+    min_brightness_separation: BRIGHTNESS_SEPARATION_MIN,
+    hue_separation: true,
+};
+
+const RELAXED_GUARDRAILS: PairwiseGuardrails = PairwiseGuardrails {
+    min_accent_vs_bg: ACCENT_BG_MIN,
+    min_accent_vs_text: ACCENT_TEXT_MIN,
+    min_active_vs_bg: ACTIVE_BG_RELAXED_MIN,
+    min_active_vs_text: ACTIVE_TEXT_MIN,
+    min_peer_contrast: RELAXED_PEER_CONTRAST_MIN,
+    min_peer_delta_e: RELAXED_PEER_DELTA_E_MIN,
+    min_brightness_separation: BRIGHTNESS_SEPARATION_MIN * 0.7,
+    hue_separation: true,
+};
+
+fn contrast_metrics(lab: [f32; 3], bg_lab: [f32; 3], text_lab: [f32; 3]) -> (f32, f32) {
+    let contrast_bg = color::calculate_contrast_ratio(lab, bg_lab);
+    let contrast_text = color::calculate_contrast_ratio(lab, text_lab);
+    (contrast_bg, contrast_text)
+}
+
+fn meets_guardrails(
+    contrast_bg: f32,
+    contrast_text: f32,
+    config: &GuardrailConfig,
+    peer_contrast: Option<f32>,
+    peer_delta_e: Option<f32>,
+) -> bool {
+    if contrast_bg < config.min_contrast_bg || contrast_text < config.min_contrast_text {
+        return false;
+    }
+
+    if let Some(min_peer_contrast) = config.min_contrast_peer {
+        if peer_contrast.is_none_or(|actual| actual < min_peer_contrast) {
+            return false;
+        }
+    }
+
+    if let Some(min_peer_delta_e) = config.min_delta_e_peer {
+        if peer_delta_e.is_none_or(|actual| actual < min_peer_delta_e) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn lab_close(a: [f32; 3], b: [f32; 3], tol: f32) -> bool {
+    color::delta_e_cie76(a, b) < tol
+}
+
+fn collect_adjusted_variants(
+    base_lab: [f32; 3],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    config: &GuardrailConfig,
+    peer_lab: Option<[f32; 3]>,
+) -> Vec<([f32; 3], f32)> {
+    let mut variants = Vec::new();
+
+    for direction in [-1.0f32, 1.0f32] {
+        let mut candidate = base_lab;
+        for _ in 0..config.max_adjust_steps {
+            candidate[0] = (candidate[0] + direction * config.adjust_step).clamp(0.0, 100.0);
+            let (contrast_bg, contrast_text) = contrast_metrics(candidate, bg_lab, text_lab);
+            let peer_metrics = peer_lab.map(|peer| {
+                (
+                    color::calculate_contrast_ratio(candidate, peer),
+                    color::delta_e_cie76(candidate, peer),
+                )
+            });
+            let (peer_contrast, peer_delta_e) = peer_metrics.unwrap_or((f32::NAN, f32::NAN));
+            if meets_guardrails(
+                contrast_bg,
+                contrast_text,
+                config,
+                peer_lab.map(|_| peer_contrast),
+                peer_lab.map(|_| peer_delta_e),
+            ) {
+                if !variants
+                    .iter()
+                    .any(|(existing, _)| lab_close(*existing, candidate, 0.5))
+                {
+                    variants.push((candidate, candidate[0] - base_lab[0]));
+                }
+                break;
+            }
+        }
+    }
+
+    variants
+}
+
+fn push_candidate_if_unique(
+    candidates: &mut Vec<RoleColorCandidate>,
+    candidate: RoleColorCandidate,
+) {
+    if !candidates
+        .iter()
+        .any(|existing| lab_close(existing.lab, candidate.lab, 0.5))
+    {
+        candidates.push(candidate);
+    }
+}
+
+fn cmp_f32_desc(a: f32, b: f32) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        (false, false) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+    }
+}
+
+fn synthesize_color_between(
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    config: &GuardrailConfig,
+    peer_lab: Option<[f32; 3]>,
+) -> Option<[f32; 3]> {
+    let mut t = -0.3f32;
+    while t <= 1.3 {
+        let candidate = [
+            bg_lab[0] + (text_lab[0] - bg_lab[0]) * t,
+            bg_lab[1] + (text_lab[1] - bg_lab[1]) * t,
+            bg_lab[2] + (text_lab[2] - bg_lab[2]) * t,
+        ];
+        let (contrast_bg, contrast_text) = contrast_metrics(candidate, bg_lab, text_lab);
+        let peer_metrics = peer_lab.map(|peer| {
+            (
+                color::calculate_contrast_ratio(candidate, peer),
+                color::delta_e_cie76(candidate, peer),
+            )
+        });
+        let (peer_contrast, peer_delta_e) = peer_metrics.unwrap_or((f32::NAN, f32::NAN));
+        if meets_guardrails(
+            contrast_bg,
+            contrast_text,
+            config,
+            peer_lab.map(|_| peer_contrast),
+            peer_lab.map(|_| peer_delta_e),
+        ) {
+            return Some(candidate);
+        }
+        t += 0.05;
+    }
+    None
+}
+
+/// Hue offsets (degrees) of the harmony schemes tried when no cluster passes.
+const HARMONY_OFFSETS: [(&str, f32); 7] = [
+    ("complementary", 180.0),
+    ("split-complementary", 150.0),
+    ("split-complementary", 210.0),
+    ("triadic", 120.0),
+    ("triadic", 240.0),
+    ("analogous", 30.0),
+    ("analogous", -30.0),
+];
+const HARMONY_CHROMA_MIN: f32 = 0.08;
+const HARMONY_CHROMA_MAX: f32 = 0.16;
+const HARMONY_LIGHTNESS_STEP: f32 = 0.01;
+
+/// Colors in classic harmonies with the background hue, or the dominant
+/// chromatic cluster's when the background is neutral. Each is walked along
+/// OKLCH lightness away from the background, gamut-mapped by chroma reduction,
+/// until it clears `min_contrast_bg`; the text floor is left to the pair
+/// solver, since mid-tone text leaves no band clearing both. Hue, chroma and
+/// lightness stay within the preset's limits. Empty when the palette has no
+/// hue at all.
+fn harmony_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    min_contrast_bg: f32,
+    preset: &Preset,
+) -> Vec<([f32; 3], String)> {
+    let oklch_of =
+        |lab: [f32; 3]| color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(lab)));
+    let Some([_, base_chroma, base_hue]) = std::iter::once(bg_lab)
+        .chain(clusters.iter().map(|cluster| cluster.lab))
+        .map(oklch_of)
+        .find(|lch| lch[1] >= MIN_CHROMA_THRESHOLD)
+    else {
+        return Vec::new();
+    };
+    let mut chroma = base_chroma.clamp(HARMONY_CHROMA_MIN, HARMONY_CHROMA_MAX);
+    if let Some(max) = preset.max_chroma {
+        chroma = chroma.min(max);
+    }
+    let min_l = preset.min_lightness.unwrap_or(0.0);
+    let bg_l = oklch_of(bg_lab)[0];
+    let directions = if bg_lab[0] < 50.0 {
+        [1.0f32, -1.0]
+    } else {
+        [-1.0, 1.0]
+    };
+
+    let mut results = Vec::new();
+    for (scheme, offset) in HARMONY_OFFSETS {
+        let mut hue = base_hue + offset;
+        if let Some(spread) = preset.hue_spread {
+            hue = base_hue + signed_hue_delta(base_hue, hue).clamp(-spread, spread);
+        }
+        let hue = hue.rem_euclid(360.0);
+        let found = directions.iter().find_map(|&direction| {
+            let mut l = bg_l;
+            loop {
+                l += direction * HARMONY_LIGHTNESS_STEP;
+                if !(0.0..=1.0).contains(&l) {
+                    return None;
+                }
+                if l < min_l {
+                    continue;
+                }
+                let lab = oklch_to_lab_mapped(l, chroma, hue);
+                if color::calculate_contrast_ratio(lab, bg_lab) >= min_contrast_bg {
+                    return Some(lab);
+                }
+            }
+        });
+        if let Some(lab) = found {
+            results.push((lab, format!("harmony:{scheme}:{hue:.0}")));
+        }
+    }
+    results
+}
+
+fn build_accent_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    used_indices: &[usize],
+    preset: &Preset,
+) -> Vec<RoleColorCandidate> {
+    let guard = GuardrailConfig {
+        min_contrast_bg: preset.accent_floor(),
+        min_contrast_text: ACCENT_TEXT_MIN,
+        min_contrast_peer: None,
+        min_delta_e_peer: None,
+        adjust_step: 4.0,
+        max_adjust_steps: 12,
+    };
+
+    let ranked = rank_accent_candidates(clusters, bg_lab, used_indices, preset.chroma_weight);
+    let mut results: Vec<RoleColorCandidate> = Vec::new();
+
+    for candidate in ranked.iter().take(12) {
+        let cluster = &clusters[candidate.index];
+        let (contrast_bg, contrast_text) = contrast_metrics(cluster.lab, bg_lab, text_lab);
+        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: cluster.lab,
+                    source_cluster_index: Some(candidate.index),
+                    origin_label: format!("cluster:{}", candidate.index),
+                    provenance_rank: 0,
+                    base_score: candidate.score,
+                },
+            );
+        }
+
+        for (adjusted, delta_l) in
+            collect_adjusted_variants(cluster.lab, bg_lab, text_lab, &guard, None)
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: adjusted,
+                    source_cluster_index: Some(candidate.index),
+                    origin_label: format!("adjusted:{}:{:+.1}", candidate.index, delta_l),
+                    provenance_rank: 2,
+                    base_score: candidate.score * 0.85,
+                },
+            );
+        }
+    }
+
+    if results.is_empty() {
+        for (lab, origin_label) in
+            harmony_candidates(clusters, bg_lab, guard.min_contrast_bg, preset)
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab,
+                    source_cluster_index: None,
+                    origin_label,
+                    provenance_rank: 3,
+                    base_score: 0.55,
+                },
+            );
+        }
+    }
+
+    if let Some(lab) = synthesize_color_between(bg_lab, text_lab, &guard, None) {
+        push_candidate_if_unique(
+            &mut results,
+            RoleColorCandidate {
+                lab,
+                source_cluster_index: None,
+                origin_label: "synthetic:midline".to_string(),
+                provenance_rank: 4,
+                base_score: 0.5,
+            },
+        );
+    }
+
+    for l in [25.0f32, 75.0f32] {
+        let candidate_lab = [l, 0.0, 0.0];
+        let (contrast_bg, contrast_text) = contrast_metrics(candidate_lab, bg_lab, text_lab);
+        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: candidate_lab,
+                    source_cluster_index: None,
+                    origin_label: format!("synthetic:gray-{:.0}", l),
+                    provenance_rank: 4,
+                    base_score: 0.35,
+                },
+            );
+        }
+    }
+
+    results
+}
+
+fn build_active_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    used_indices: &[usize],
+    preset: &Preset,
+) -> Vec<RoleColorCandidate> {
+    let guard = GuardrailConfig {
+        min_contrast_bg: ACTIVE_BG_MIN,
+        min_contrast_text: preset.active_text_floor(),
+        min_contrast_peer: None,
+        min_delta_e_peer: None,
+        adjust_step: 4.0,
+        max_adjust_steps: 12,
+    };
+
+    let ranked = rank_active_item_candidates(clusters, bg_lab, used_indices, preset.chroma_weight);
+    let mut results: Vec<RoleColorCandidate> = Vec::new();
+
+    for candidate in ranked.iter().take(12) {
+        let cluster = &clusters[candidate.index];
+        let (contrast_bg, contrast_text) = contrast_metrics(cluster.lab, bg_lab, text_lab);
+        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: cluster.lab,
+                    source_cluster_index: Some(candidate.index),
+                    origin_label: format!("cluster:{}", candidate.index),
+                    provenance_rank: 0,
+                    base_score: candidate.score,
+                },
+            );
+        }
+
+        for (adjusted, delta_l) in
+            collect_adjusted_variants(cluster.lab, bg_lab, text_lab, &guard, None)
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: adjusted,
+                    source_cluster_index: Some(candidate.index),
+                    origin_label: format!("adjusted:{}:{:+.1}", candidate.index, delta_l),
+                    provenance_rank: 2,
+                    base_score: candidate.score * 0.8,
+                },
+            );
+        }
+    }
+
+    if results.is_empty() {
+        for (lab, origin_label) in
+            harmony_candidates(clusters, bg_lab, guard.min_contrast_bg, preset)
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab,
+                    source_cluster_index: None,
+                    origin_label,
+                    provenance_rank: 3,
+                    base_score: 0.45,
+                },
+            );
+        }
+    }
+
+    if let Some(lab) = synthesize_color_between(bg_lab, text_lab, &guard, None) {
+        push_candidate_if_unique(
+            &mut results,
+            RoleColorCandidate {
+                lab,
+                source_cluster_index: None,
+                origin_label: "synthetic:midline".to_string(),
+                provenance_rank: 4,
+                base_score: 0.4,
+            },
+        );
+    }
+
+    for l in [30.0f32, 50.0f32, 70.0f32] {
+        let candidate_lab = [l, 0.0, 0.0];
+        let (contrast_bg, contrast_text) = contrast_metrics(candidate_lab, bg_lab, text_lab);
+        if meets_guardrails(contrast_bg, contrast_text, &guard, None, None) {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: candidate_lab,
+                    source_cluster_index: None,
+                    origin_label: format!("synthetic:gray-{:.0}", l),
+                    provenance_rank: 4,
+                    base_score: 0.35,
+                },
+            );
+        }
+    }
+
+    results
+}
+
+fn candidate_list_for_debug(
+    candidates: &[RoleColorCandidate],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+) -> Vec<PairCandidateDebug> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            let rgb = color::lab_to_rgb8(candidate.lab);
+            let (contrast_background, contrast_text) =
+                contrast_metrics(candidate.lab, bg_lab, text_lab);
+            PairCandidateDebug {
+                hex: color::rgb_to_hex(rgb),
+                origin: candidate.origin_label.clone(),
+                provenance_rank: candidate.provenance_rank,
+                base_score: candidate.base_score,
+                contrast_background,
+                contrast_text,
+            }
+        })
+        .collect()
+}
+
+struct HighlightCandidate {
+    lab: [f32; 3],
+    origin: String,
+    preference: u8,
+    confidence: f32,
+    contrast_active: f32,
+    contrast_background: f32,
+    delta_from_text: f32,
+}
+
+fn select_highlight_text(
+    text_assignment: &RoleAssignment,
+    accent_assignment: &RoleAssignment,
+    active_assignment: &RoleAssignment,
+    background_lab: [f32; 3],
+    cvd: Option<CvdMode>,
+) -> (RoleAssignment, RoleDebugEntry) {
+    let text_lab = text_assignment.lab;
+    let accent_lab = accent_assignment.lab;
+    let active_lab = active_assignment.lab;
+
+    let mut candidates: Vec<HighlightCandidate> = Vec::new();
+
+    let mut add_candidate = |lab: [f32; 3], origin: String, preference: u8, confidence: f32| {
+        let contrast_active = color::calculate_contrast_ratio(lab, active_lab);
+        let contrast_background = color::calculate_contrast_ratio(lab, background_lab);
+        if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
+            && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
+            && cvd_separation_ok(&[(lab, active_lab)], cvd)
+        {
+            let delta_from_text = color::delta_e_cie76(lab, text_lab);
+            candidates.push(HighlightCandidate {
+                lab,
+                origin,
+                preference,
+                confidence,
+                contrast_active,
+                contrast_background,
+                delta_from_text,
+            });
+        }
+    };
+
+    // Original text color
+    add_candidate(
+        text_lab,
+        "text".to_string(),
+        0,
+        text_assignment.confidence.max(0.85),
+    );
+
+    // Adjusted text (lighter/darker)
+    for direction in [-1.0f32, 1.0f32] {
+        let mut candidate = text_lab;
+        let mut steps_taken = 0usize;
+        loop {
+            if steps_taken >= 8 {
+                break;
+            }
+            steps_taken += 1;
+            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
+            let contrast_active = color::calculate_contrast_ratio(candidate, active_lab);
+            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
+            if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
+                && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
+            {
+                let delta = candidate[0] - text_lab[0];
+                add_candidate(candidate, format!("text_adjusted:{:+.1}", delta), 1, 0.75);
+                break;
+            }
+        }
+    }
+
+    // Accent as fallback
+    add_candidate(accent_lab, "accent".to_string(), 2, 0.65);
+
+    // Adjusted accent
+    for direction in [-1.0f32, 1.0f32] {
+        let mut candidate = accent_lab;
+        let mut steps_taken = 0usize;
+        loop {
+            if steps_taken >= 6 {
+                break;
+            }
+            steps_taken += 1;
+            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
+            let contrast_active = color::calculate_contrast_ratio(candidate, active_lab);
+            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
+            if contrast_active >= HIGHLIGHT_TEXT_MIN_CONTRAST
+                && contrast_background >= HIGHLIGHT_TEXT_BG_MIN
+            {
+                let delta = candidate[0] - accent_lab[0];
+                add_candidate(candidate, format!("accent_adjusted:{:+.1}", delta), 3, 0.65);
+                break;
+            }
+        }
+    }
+
+    // Black / White fallback
+    for (rgb, name) in [
+        ([0, 0, 0], "synthetic:black"),
+        ([255, 255, 255], "synthetic:white"),
+    ] {
+        let lab = color::rgb8_to_lab(rgb);
+        add_candidate(lab, name.to_string(), 4, 0.6);
+    }
+
+    if candidates.is_empty() {
+        let assignment = role_assignment_from_lab(
+            ColorRole::HighlightText,
+            text_lab,
+            None,
+            0.5,
+            Some("fallback:text"),
+            Some(active_lab),
+            None,
+        );
+        let debug = RoleDebugEntry {
+            hex: assignment.hex.clone(),
+            origin: "fallback:text".to_string(),
+            contrast_against_active: Some(color::calculate_contrast_ratio(text_lab, active_lab)),
+            contrast_against_background: Some(color::calculate_contrast_ratio(
+                text_lab,
+                background_lab,
+            )),
+            contrast_against_text: None,
+        };
+        return (assignment, debug);
+    }
+
+    candidates.sort_by(|a, b| {
+        a.preference
+            .cmp(&b.preference)
+            .then_with(|| cmp_f32_desc(a.contrast_active, b.contrast_active))
+            .then_with(|| {
+                a.delta_from_text
+                    .partial_cmp(&b.delta_from_text)
+                    .unwrap_or(Ordering::Equal)
+            })
+    });
+
+    let winner = &candidates[0];
+    let assignment = role_assignment_from_lab(
+        ColorRole::HighlightText,
+        winner.lab,
+        None,
+        winner.confidence,
+        Some(winner.origin.as_str()),
+        Some(active_lab),
+        None,
+    );
+    let debug = RoleDebugEntry {
+        hex: assignment.hex.clone(),
+        origin: winner.origin.clone(),
+        contrast_against_active: Some(winner.contrast_active),
+        contrast_against_background: Some(winner.contrast_background),
+        contrast_against_text: Some(color::calculate_contrast_ratio(winner.lab, text_lab)),
+    };
+
+    (assignment, debug)
+}
+
+struct FrameCandidate {
+    lab: [f32; 3],
+    origin: String,
+    preference: u8,
+    confidence: f32,
+    contrast_background: f32,
+    contrast_text: f32,
+    delta_from_accent: f32,
+}
+
+fn derive_active_from_background(
+    background_lab: [f32; 3],
+    accent_lab: [f32; 3],
+) -> Option<([f32; 3], String)> {
+    let directions = [-1.0f32, 1.0f32];
+    for step in 1..=12 {
+        let delta = step as f32 * 3.0;
+        for &direction in &directions {
+            let mut candidate = background_lab;
+            candidate[0] = (candidate[0] + direction * delta).clamp(0.0, 100.0);
+            let contrast_bg = color::calculate_contrast_ratio(candidate, background_lab);
+            let contrast_accent = color::calculate_contrast_ratio(candidate, accent_lab);
+            // Also ensure body text will be readable on the active background later
+            // We can't compute c_text here (we don't have text_lab), so we enforce it in derive_active_with_hue
+            if contrast_bg >= ACTIVE_BG_MIN && contrast_accent >= ACCENT_BG_MIN {
+                let origin = if direction < 0.0 {
+                    format!("background_adjusted:-{:.1}", delta)
+                } else {
+                    format!("background_adjusted:+{:.1}", delta)
+                };
+                return Some((candidate, origin));
+            }
+        }
+    }
+    None
+}
+
+/// Frame colors derived from the accent, the background, text and black/white
+/// that clear `FRAME_BG_MIN` and `FRAME_TEXT_MIN`, unsorted.
+fn frame_candidates(
+    accent_lab: [f32; 3],
+    accent_confidence: f32,
+    background_lab: [f32; 3],
+    text_lab: [f32; 3],
+) -> Vec<FrameCandidate> {
+    let mut candidates: Vec<FrameCandidate> = Vec::new();
+
+    let mut add_candidate = |lab: [f32; 3], origin: String, preference: u8, confidence: f32| {
+        let contrast_background = color::calculate_contrast_ratio(lab, background_lab);
+        let contrast_text = color::calculate_contrast_ratio(lab, text_lab);
+        if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
+            let delta_from_accent = color::delta_e_cie76(lab, accent_lab);
+            candidates.push(FrameCandidate {
+                lab,
+                origin,
+                preference,
+                confidence,
+                contrast_background,
+                contrast_text,
+                delta_from_accent,
+            });
+        }
+    };
+
+    add_candidate(
+        accent_lab,
+        "accent".to_string(),
+        0,
+        accent_confidence.max(0.85),
+    );
+
+    for direction in [-1.0f32, 1.0f32] {
+        let mut candidate = accent_lab;
+        let mut steps_taken = 0usize;
+        loop {
+            if steps_taken >= 6 {
+                break;
+            }
+            steps_taken += 1;
+            candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
+            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
+            let contrast_text = color::calculate_contrast_ratio(candidate, text_lab);
+            if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
+                let delta = candidate[0] - accent_lab[0];
+                add_candidate(candidate, format!("accent_adjusted:{:+.1}", delta), 1, 0.8);
+                break;
+            }
+        }
+    }
+
+    // Derived from background
+    for direction in [-1.0f32, 1.0f32] {
+        let mut candidate = background_lab;
+        let mut steps_taken = 0usize;
+        loop {
+            if steps_taken >= 6 {
+                break;
+            }
+            steps_taken += 1;
+            candidate[0] = (candidate[0] + direction * 6.0).clamp(0.0, 100.0);
+            let contrast_background = color::calculate_contrast_ratio(candidate, background_lab);
+            let contrast_text = color::calculate_contrast_ratio(candidate, text_lab);
+            if contrast_background >= FRAME_BG_MIN && contrast_text >= FRAME_TEXT_MIN {
+                let delta = candidate[0] - background_lab[0];
+                add_candidate(
+                    candidate,
+                    format!("background_adjusted:{:+.1}", delta),
+                    2,
+                    0.65,
+                );
+                break;
+            }
+        }
+    }
+
+    // Text fallback
+    add_candidate(text_lab, "text".to_string(), 3, 0.6);
+
+    // Black/White fallback
+    for (rgb, name) in [
+        ([0, 0, 0], "synthetic:black"),
+        ([255, 255, 255], "synthetic:white"),
+    ] {
+        let lab = color::rgb8_to_lab(rgb);
+        add_candidate(lab, name.to_string(), 4, 0.55);
+    }
+
+    candidates
+}
+
+fn select_frame_color(
+    accent_assignment: &RoleAssignment,
+    background_lab: [f32; 3],
+    text_lab: [f32; 3],
+) -> (RoleAssignment, RoleDebugEntry) {
+    let accent_lab = accent_assignment.lab;
+    let mut candidates = frame_candidates(
+        accent_lab,
+        accent_assignment.confidence,
+        background_lab,
+        text_lab,
+    );
+
+    if candidates.is_empty() {
+        let assignment = role_assignment_from_lab(
+            ColorRole::Frame,
+            accent_lab,
+            accent_assignment.source_cluster_index,
+            0.6,
+            Some("fallback:accent"),
+            Some(background_lab),
+            Some(text_lab),
+        );
+        let debug = RoleDebugEntry {
+            hex: assignment.hex.clone(),
+            origin: "fallback:accent".to_string(),
+            contrast_against_active: None,
+            contrast_against_background: Some(color::calculate_contrast_ratio(
+                accent_lab,
+                background_lab,
+            )),
+            contrast_against_text: Some(color::calculate_contrast_ratio(accent_lab, text_lab)),
+        };
+        return (assignment, debug);
+    }
+
+    candidates.sort_by(|a, b| {
+        a.preference
+            .cmp(&b.preference)
+            .then_with(|| cmp_f32_desc(a.contrast_background, b.contrast_background))
+            .then_with(|| {
+                a.delta_from_accent
+                    .partial_cmp(&b.delta_from_accent)
+                    .unwrap_or(Ordering::Equal)
+            })
+    });
+
+    let winner = &candidates[0];
+    let assignment = role_assignment_from_lab(
+        ColorRole::Frame,
+        winner.lab,
+        None,
+        winner.confidence,
+        Some(winner.origin.as_str()),
+        Some(background_lab),
+        Some(text_lab),
+    );
+    let debug = RoleDebugEntry {
+        hex: assignment.hex.clone(),
+        origin: winner.origin.clone(),
+        contrast_against_active: None,
+        contrast_against_background: Some(winner.contrast_background),
+        contrast_against_text: Some(winner.contrast_text),
+    };
+
+    (assignment, debug)
+}
+
+fn select_header_accent(
+    accent_assignment: &RoleAssignment,
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    min_contrast: f32,
+) -> RoleAssignment {
+    // Try raw accent first; require ≥4.5:1 (or the preset floor) vs background to ensure header readability
+    let mut best_lab = accent_assignment.lab;
+    let mut best_origin = accent_assignment
+        .origin
+        .as_deref()
+        .unwrap_or("accent")
+        .to_string();
+    let mut best_conf = accent_assignment.confidence.min(0.8);
+
+    let mut contrast_bg = color::calculate_contrast_ratio(best_lab, bg_lab);
+    if contrast_bg < min_contrast {
+        // Adjust only lightness to reach the floor
+        for direction in [-1.0f32, 1.0f32] {
+            let mut candidate = best_lab;
+            for _ in 0..10 {
+                candidate[0] = (candidate[0] + direction * 4.0).clamp(0.0, 100.0);
+                let c_bg = color::calculate_contrast_ratio(candidate, bg_lab);
+                let c_txt = color::calculate_contrast_ratio(candidate, text_lab);
+                if c_bg >= min_contrast {
+                    best_lab = candidate;
+                    best_origin = format!("header_accent_adjusted:{:+.1}", candidate[0] - accent_assignment.lab[0]);
+                    best_conf = best_conf.min(0.75);
+                    contrast_bg = c_bg;
+                    break;
+                }
+                // keep minor guard that we don't crash readability versus text if it overlays
+                if c_txt < 2.0 {
+                    break;
+                }
+            }
+            if contrast_bg >= min_contrast {
+                break;
+            }
+        }
+    }
+
+    // Fallbacks if accent cannot be adjusted sufficiently
+    if contrast_bg < min_contrast {
+        let white = color::rgb8_to_lab([255, 255, 255]);
+        let black = color::rgb8_to_lab([0, 0, 0]);
+        let c_white = color::calculate_contrast_ratio(white, bg_lab);
+        let c_black = color::calculate_contrast_ratio(black, bg_lab);
+        if c_white >= min_contrast && c_white >= c_black {
+            best_lab = white;
+            best_origin = "header_accent:white".to_string();
+            best_conf = 0.6;
+        } else if c_black >= min_contrast {
+            best_lab = black;
+            best_origin = "header_accent:black".to_string();
+            best_conf = 0.6;
+        } else {
+            // last resort: use text color
+            best_lab = text_lab;
+            best_origin = "header_accent:text".to_string();
+            best_conf = 0.6;
+        }
+    }
+
+    role_assignment_from_lab(
+        ColorRole::HeaderAccent,
+        best_lab,
+        None,
+        best_conf,
+        Some(&best_origin),
+        Some(bg_lab),
+        Some(text_lab),
+    )
+}
+
+/// OKLCH anchor hues (degrees) for the log-level status roles: amber, red, green
+/// and violet. Info follows the accent hue instead, or `STATUS_INFO_HUE` (blue)
+/// when the accent is neutral.
+const STATUS_HUES: [(ColorRole, &str, f32); 4] = [
+    (ColorRole::Warn, "warn", 75.0),
+    (ColorRole::Error, "error", 25.0),
+    (ColorRole::Debug, "debug", 140.0),
+    (ColorRole::Trace, "trace", 305.0),
+];
+/// Palette hues within this many degrees of an anchor pull the status hue toward
+/// them, by at most `STATUS_MAX_HUE_SHIFT` so each family stays recognizable.
+const STATUS_HUE_WINDOW: f32 = 45.0;
+const STATUS_INFO_HUE: f32 = 250.0;
+const STATUS_MAX_HUE_SHIFT: f32 = 15.0;
+const STATUS_MIN_CLUSTER_CHROMA: f32 = 0.04;
+const STATUS_CHROMA_MIN: f32 = 0.08;
+const STATUS_CHROMA_MAX: f32 = 0.18;
+const STATUS_LIGHTNESS_STEP: f32 = 0.02;
+const STATUS_BISECT_STEPS: usize = 12;
+
+/// Signed shortest rotation from hue `from` to hue `to`, in (-180, 180].
+fn signed_hue_delta(from: f32, to: f32) -> f32 {
+    let delta = (to - from).rem_euclid(360.0);
+    if delta > 180.0 {
+        delta - 360.0
+    } else {
+        delta
+    }
+}
+
+/// Lab of an OKLCH color, gamut-mapped by chroma reduction so the hue survives.
+fn oklch_to_lab_mapped(l: f32, c: f32, h_deg: f32) -> [f32; 3] {
+    let linear = color::oklab_to_linear(color::oklch_to_oklab([l, c, h_deg]));
+    color::rgb8_to_lab(color::linear_to_srgb8_mapped(linear))
+}
+
+/// Walk OKLCH lightness away from the background, starting at the background's own
+/// lightness, until the color clears `min_contrast`. The first passing step keeps
+/// the color as close to the page tone as readability allows. When only the last
+/// stretch before white or black passes, bisect into it so the result keeps a
+/// tint of the hue instead of jumping straight to white or black.
+fn solve_status_lightness(
+    hue: f32,
+    chroma: f32,
+    bg_lab: [f32; 3],
+    min_contrast: f32,
+) -> Option<[f32; 3]> {
+    let bg_l = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(bg_lab)))[0];
+    let directions = if bg_lab[0] < 50.0 {
+        [1.0f32, -1.0]
+    } else {
+        [-1.0, 1.0]
+    };
+    let passes = |l: f32| {
+        let lab = oklch_to_lab_mapped(l, chroma, hue);
+        (color::calculate_contrast_ratio(lab, bg_lab) >= min_contrast).then_some(lab)
+    };
+    for direction in directions {
+        let mut l = bg_l;
+        loop {
+            let next = l + direction * STATUS_LIGHTNESS_STEP;
+            if !(0.0..=1.0).contains(&next) {
+                break;
+            }
+            l = next;
+            if let Some(lab) = passes(l) {
+                return Some(lab);
+            }
+        }
+        let extreme = direction.max(0.0);
+        let Some(mut best) = passes(extreme) else {
+            continue;
+        };
+        let (mut failing, mut passing) = (l, extreme);
+        for _ in 0..STATUS_BISECT_STEPS {
+            let mid = (failing + passing) / 2.0;
+            match passes(mid) {
+                Some(lab) => {
+                    best = lab;
+                    passing = mid;
+                }
+                None => failing = mid,
+            }
+        }
+        return Some(best);
+    }
+    None
+}
+
+/// Solve the log-level roles (info, warn, error, debug, trace). Each keeps a fixed
+/// hue family, tinted toward nearby palette hues and the palette's chroma in
+/// OKLCH, and is guaranteed `min_contrast` (at least `ACCENT_BG_MIN`) against
+/// the background.
+fn select_status_colors(
+    clusters: &[ColorCluster],
+    accent_assignment: &RoleAssignment,
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    min_contrast: f32,
+) -> Vec<RoleAssignment> {
+    let palette: Vec<([f32; 3], f64)> = clusters
+        .iter()
+        .map(|cluster| {
+            let rgb = [cluster.rgb.r, cluster.rgb.g, cluster.rgb.b];
+            let mut lch = color::oklab_to_oklch(color::rgb8_to_oklab(rgb));
+            lch[2] = lch[2].rem_euclid(360.0);
+            (lch, cluster.share)
+        })
+        .filter(|(lch, _)| lch[1] >= STATUS_MIN_CLUSTER_CHROMA)
+        .collect();
+    let total_share: f64 = palette.iter().map(|(_, share)| share).sum();
+    let palette_chroma = if total_share > 0.0 {
+        (palette
+            .iter()
+            .map(|(lch, share)| lch[1] as f64 * share)
+            .sum::<f64>()
+            / total_share) as f32
+    } else {
+        STATUS_CHROMA_MIN
+    }
+    .clamp(STATUS_CHROMA_MIN, STATUS_CHROMA_MAX);
+
+    let solve = |role: ColorRole, name: &str, hue: f32, chroma: f32, origin: String| {
+        let (lab, origin, confidence) =
+            match solve_status_lightness(hue, chroma, bg_lab, min_contrast) {
+                Some(lab) => (lab, origin, 0.7),
+                None => {
+                    // Mid-tone backgrounds can defeat every lightness; fall back to
+                    // whichever of white/black reads better
+                    let white = color::rgb8_to_lab([255, 255, 255]);
+                    let black = color::rgb8_to_lab([0, 0, 0]);
+                    let lab = if color::calculate_contrast_ratio(white, bg_lab)
+                        >= color::calculate_contrast_ratio(black, bg_lab)
+                    {
+                        white
+                    } else {
+                        black
+                    };
+                    (lab, format!("status:{name}:fallback"), 0.4)
+                }
+            };
+        role_assignment_from_lab(
+            role,
+            lab,
+            None,
+            confidence,
+            Some(&origin),
+            Some(bg_lab),
+            Some(text_lab),
+        )
+    };
+
+    let mut assignments = Vec::with_capacity(STATUS_HUES.len() + 1);
+    if color::calculate_contrast_ratio(accent_assignment.lab, bg_lab) >= min_contrast {
+        assignments.push(clone_for_role(
+            ColorRole::Info,
+            accent_assignment,
+            accent_assignment.confidence,
+        ));
+    } else {
+        let accent_rgb = color::lab_to_rgb8(accent_assignment.lab);
+        let [_, chroma, hue] = color::oklab_to_oklch(color::rgb8_to_oklab(accent_rgb));
+        let (hue, chroma, origin) = if chroma >= STATUS_MIN_CLUSTER_CHROMA {
+            (hue.rem_euclid(360.0), chroma, "status:info:accent")
+        } else {
+            (STATUS_INFO_HUE, palette_chroma, "status:info:anchor")
+        };
+        assignments.push(solve(
+            ColorRole::Info,
+            "info",
+            hue,
+            chroma.clamp(STATUS_CHROMA_MIN, STATUS_CHROMA_MAX),
+            origin.to_string(),
+        ));
+    }
+
+    for (role, name, anchor) in STATUS_HUES {
+        // Strongest palette color inside the family window sets the tint
+        let tint = palette
+            .iter()
+            .map(|(lch, share)| (signed_hue_delta(anchor, lch[2]), *share))
+            .filter(|(delta, _)| delta.abs() <= STATUS_HUE_WINDOW)
+            .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(Ordering::Equal));
+        let (hue, origin) = match tint {
+            Some((delta, _)) => (
+                (anchor + delta.clamp(-STATUS_MAX_HUE_SHIFT, STATUS_MAX_HUE_SHIFT))
+                    .rem_euclid(360.0),
+                format!("status:{name}:palette"),
+            ),
+            None => (anchor, format!("status:{name}:anchor")),
+        };
+        assignments.push(solve(role, name, hue, palette_chroma, origin));
+    }
+
+    assignments
+}
+
+fn build_pair_metrics(
+    accent_lab: [f32; 3],
+    active_lab: [f32; 3],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+) -> PairwiseMetrics {
+    let accent_rgb = color::lab_to_rgb8(accent_lab);
+    let active_rgb = color::lab_to_rgb8(active_lab);
+    let bg_rgb = color::lab_to_rgb8(bg_lab);
+    let text_rgb = color::lab_to_rgb8(text_lab);
+
+    let accent_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(accent_rgb));
+    let active_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(active_rgb));
+    let bg_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(bg_rgb));
+    let text_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(text_rgb));
+
+    PairwiseMetrics {
+        accent_bg: color::calculate_contrast_ratio(accent_lab, bg_lab),
+        accent_text: color::calculate_contrast_ratio(accent_lab, text_lab),
+        accent_active: color::calculate_contrast_ratio(accent_lab, active_lab),
+        active_bg: color::calculate_contrast_ratio(active_lab, bg_lab),
+        active_text: color::calculate_contrast_ratio(active_lab, text_lab),
+        delta_e: color::delta_e_cie76(accent_lab, active_lab),
+        accent_l: accent_lab[0],
+        active_l: active_lab[0],
+        accent_hue: accent_oklch[2],
+        accent_chroma: accent_oklch[1],
+        active_hue: active_oklch[2],
+        active_chroma: active_oklch[1],
+        background_hue: bg_oklch[2],
+        background_chroma: bg_oklch[1],
+        text_hue: text_oklch[2],
+        text_chroma: text_oklch[1],
+    }
+}
+
+fn passes_pairwise_guardrails(metrics: &PairwiseMetrics, guard: PairwiseGuardrails) -> bool {
+    if metrics.accent_bg < guard.min_accent_vs_bg {
+        return false;
+    }
+    if metrics.accent_text < guard.min_accent_vs_text {
+        return false;
+    }
+    if metrics.active_bg < guard.min_active_vs_bg {
+        return false;
+    }
+    if metrics.active_text < guard.min_active_vs_text {
+        return false;
+    }
+    if metrics.accent_active < guard.min_peer_contrast {
+        return false;
+    }
+    if metrics.delta_e < guard.min_peer_delta_e {
+        return false;
+    }
+    if metrics.brightness_separation() < guard.min_brightness_separation {
+        return false;
+    }
+    true
+}
+
+fn compare_pairwise_results(lhs: &PairwiseResult, rhs: &PairwiseResult) -> Ordering {
+    cmp_f32_desc(lhs.metrics.min_contrast(), rhs.metrics.min_contrast())
+        .then_with(|| {
+            cmp_f32_desc(
+                lhs.metrics.brightness_separation(),
+                rhs.metrics.brightness_separation(),
+            )
+        })
+        .then_with(|| lhs.provenance_score.cmp(&rhs.provenance_score))
+        .then_with(|| cmp_f32_desc(lhs.metrics.avg_contrast(), rhs.metrics.avg_contrast()))
+        .then_with(|| {
+            let lhs_score = lhs.accent.base_score + lhs.active.base_score;
+            let rhs_score = rhs.accent.base_score + rhs.active.base_score;
+            cmp_f32_desc(lhs_score, rhs_score)
+        })
+}
+
+fn make_debug_entry(result: &PairwiseResult) -> PairwiseDebugEntry {
+    let accent_rgb = color::lab_to_rgb8(result.accent.lab);
+    let active_rgb = color::lab_to_rgb8(result.active.lab);
+    PairwiseDebugEntry {
+        accent_hex: color::rgb_to_hex(accent_rgb),
+        accent_origin: result.accent.origin_label.clone(),
+        active_hex: color::rgb_to_hex(active_rgb),
+        active_origin: result.active.origin_label.clone(),
+        accent_bg: result.metrics.accent_bg,
+        accent_text: result.metrics.accent_text,
+        accent_active: result.metrics.accent_active,
+        active_bg: result.metrics.active_bg,
+        active_text: result.metrics.active_text,
+        delta_e: result.metrics.delta_e,
+        min_contrast: result.metrics.min_contrast(),
+        brightness_separation: result.metrics.brightness_separation(),
+        provenance_score: result.provenance_score,
+        accent_hue: result.metrics.accent_hue,
+        accent_chroma: result.metrics.accent_chroma,
+        active_hue: result.metrics.active_hue,
+        active_chroma: result.metrics.active_chroma,
+        background_hue: result.metrics.background_hue,
+        background_chroma: result.metrics.background_chroma,
+        text_hue: result.metrics.text_hue,
+        text_chroma: result.metrics.text_chroma,
+    }
+}
+
+fn solve_with_guardrails(
+    accent_candidates: &[RoleColorCandidate],
+    active_candidates: &[RoleColorCandidate],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    guardrails: PairwiseGuardrails,
+    cvd: Option<CvdMode>,
+    debug_enabled: bool,
+) -> (Option<PairwiseResult>, usize, Vec<PairwiseResult>) {
+    let mut best: Option<PairwiseResult> = None;
+    let mut evaluated = 0usize;
+    let mut passing: Vec<PairwiseResult> = Vec::new();
+
+    for accent in accent_candidates {
+        for active in active_candidates {
+            evaluated += 1;
+            let metrics = build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
+            if !passes_pairwise_guardrails(&metrics, guardrails) {
+                continue;
+            }
+            if !pair_cvd_ok(accent.lab, active.lab, bg_lab, cvd) {
+                continue;
+            }
+            if guardrails.hue_separation && hue_separation_count(&metrics) < 4 {
+                continue;
+            }
+
+            let candidate = PairwiseResult {
+                accent: accent.clone(),
+                active: active.clone(),
+                metrics,
+                provenance_score: accent.provenance_rank + active.provenance_rank,
+            };
+
+            match &mut best {
+                Some(current_best) => {
+                    if compare_pairwise_results(&candidate, current_best) == Ordering::Less {
+                        *current_best = candidate.clone();
+                    }
+                }
+                None => best = Some(candidate.clone()),
+            }
+
+            if debug_enabled {
+                passing.push(candidate);
+            }
+        }
+    }
+
+    if debug_enabled {
+        passing.sort_by(compare_pairwise_results);
+        passing.truncate(8);
+    } else {
+        passing.clear();
+    }
+
+    (best, evaluated, passing)
+}
+
+fn pinned_candidate(rgb: [u8; 3]) -> RoleColorCandidate {
+    RoleColorCandidate {
+        lab: color::rgb8_to_lab(rgb),
+        source_cluster_index: None,
+        origin_label: "pinned".to_string(),
+        provenance_rank: 0,
+        base_score: 0.0,
+    }
+}
+
+fn pinned_assignment(
+    role: ColorRole,
+    rgb: [u8; 3],
+    bg_lab: Option<[f32; 3]>,
+    text_lab: Option<[f32; 3]>,
+) -> RoleAssignment {
+    role_assignment_from_lab(
+        role,
+        color::rgb8_to_lab(rgb),
+        None,
+        1.0,
+        Some("pinned"),
+        bg_lab,
+        text_lab,
+    )
+}
+
+fn select_accent_and_active(
+    clusters: &[ColorCluster],
+    used_indices: &mut Vec<usize>,
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    options: &RoleOptions,
+    debug_enabled: bool,
+) -> (RoleAssignment, RoleAssignment, Option<PairwiseDebug>) {
+    let (cvd, preset) = (options.cvd, &options.preset);
+    let pinned_active = options.pins.active;
+    let (strict, relaxed) = (
+        STRICT_GUARDRAILS.for_preset(preset),
+        RELAXED_GUARDRAILS.for_preset(preset),
+    );
+    let hue_separation = relaxed.hue_separation;
+    let mut accent_candidates = match options.pins.accent {
+        Some(rgb) => vec![pinned_candidate(rgb)],
+        None => build_accent_candidates(clusters, bg_lab, text_lab, used_indices, preset),
+    };
+    if accent_candidates.is_empty() {
+        accent_candidates.push(RoleColorCandidate {
+            lab: [bg_lab[0].clamp(0.0, 100.0), 0.0, 0.0],
+            source_cluster_index: None,
+            origin_label: "synthetic:bg-neutral".to_string(),
+            provenance_rank: 4,
+            base_score: 0.3,
+        });
+    }
+
+    let mut active_candidates = match pinned_active {
+        Some(rgb) => vec![pinned_candidate(rgb)],
+        None => {
+            let mut candidates =
+                build_active_candidates(clusters, bg_lab, text_lab, used_indices, preset);
+            // Pinned highlight text or frame keep the actives they still work with
+            let pins = &options.pins;
+            if (pins.highlight_text.is_some() || pins.frame.is_some())
+                && candidates
+                    .iter()
+                    .any(|c| active_fits_pins(c.lab, bg_lab, text_lab, options))
+            {
+                candidates.retain(|c| active_fits_pins(c.lab, bg_lab, text_lab, options));
+            }
+            candidates
+        }
+    };
+    if active_candidates.is_empty() {
+        active_candidates.push(RoleColorCandidate {
+            lab: [text_lab[0].clamp(0.0, 100.0), 0.0, 0.0],
+            source_cluster_index: None,
+            origin_label: "synthetic:text-neutral".to_string(),
+            provenance_rank: 4,
+            base_score: 0.3,
+        });
+    }
+
+    let mut total_pairs_evaluated = 0usize;
+
+    let (strict_result, strict_evaluated, strict_debug_pairs) = solve_with_guardrails(
+        &accent_candidates,
+        &active_candidates,
+        bg_lab,
+        text_lab,
+        strict,
+        cvd,
+        debug_enabled,
+    );
+
+    total_pairs_evaluated += strict_evaluated;
+
+    let (result, pass_mode, debug_pairs) = if let Some(res) = strict_result {
+        (res, "strict".to_string(), strict_debug_pairs)
+    } else {
+        let (relaxed_result, relaxed_evaluated, relaxed_debug_pairs) = solve_with_guardrails(
+            &accent_candidates,
+            &active_candidates,
+            bg_lab,
+            text_lab,
+            relaxed,
+            cvd,
+            debug_enabled,
+        );
+
+        total_pairs_evaluated += relaxed_evaluated;
+
+        if let Some(res) = relaxed_result {
+            (
+                res,
+                "relaxed".to_string(),
+                if debug_enabled {
+                    relaxed_debug_pairs
+                } else {
+                    Vec::new()
+                },
+            )
+        } else {
+            // Final fallback: choose best overall by min contrast even if peer guard not met
+            let mut fallback_best: Option<PairwiseResult> = None;
+            let mut fallback_list: Vec<PairwiseResult> = Vec::new();
+            let mut fallback_evaluated = 0usize;
+            for accent in &accent_candidates {
+                for active in &active_candidates {
+                    fallback_evaluated += 1;
+                    if !pair_cvd_ok(accent.lab, active.lab, bg_lab, cvd) {
+                        continue;
+                    }
+                    let metrics = build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
+                    if hue_separation && hue_separation_count(&metrics) < 4 {
+                        continue;
+                    }
+                    let candidate = PairwiseResult {
+                        accent: accent.clone(),
+                        active: active.clone(),
+                        metrics,
+                        provenance_score: accent.provenance_rank + active.provenance_rank,
+                    };
+                    match &mut fallback_best {
+                        Some(current) => {
+                            if compare_pairwise_results(&candidate, current) == Ordering::Less {
+                                *current = candidate.clone();
+                            }
+                        }
+                        None => fallback_best = Some(candidate.clone()),
+                    }
+                    if debug_enabled {
+                        fallback_list.push(candidate);
+                    }
+                }
+            }
+
+            if fallback_best.is_none() {
+                for accent in &accent_candidates {
+                    for active in &active_candidates {
+                        let metrics =
+                            build_pair_metrics(accent.lab, active.lab, bg_lab, text_lab);
+                        let candidate = PairwiseResult {
+                            accent: accent.clone(),
+                            active: active.clone(),
+                            metrics,
+                            provenance_score: accent.provenance_rank + active.provenance_rank,
+                        };
+                        match &mut fallback_best {
+                            Some(current) => {
+                                if compare_pairwise_results(&candidate, current) == Ordering::Less {
+                                    *current = candidate.clone();
+                                }
+                            }
+                            None => fallback_best = Some(candidate.clone()),
+                        }
+                        if debug_enabled && fallback_list.len() < 8 {
+                            fallback_list.push(candidate);
+                        }
+                    }
+                }
+            }
+
+            if debug_enabled {
+                fallback_list.sort_by(compare_pairwise_results);
+                fallback_list.truncate(8);
+            }
+
+            total_pairs_evaluated += fallback_evaluated;
+
+            (
+                fallback_best.expect("fallback should produce a candidate"),
+                "fallback".to_string(),
+                fallback_list,
+            )
+        }
+    };
+
+    // Build assignments
+    let accent_assignment = role_assignment_from_lab(
+        ColorRole::Accent,
+        result.accent.lab,
+        result.accent.source_cluster_index,
+        result.accent.confidence(),
+        Some(&result.accent.origin_label),
+        Some(bg_lab),
+        Some(text_lab),
+    );
+
+    if let Some(idx) = result.accent.source_cluster_index {
+        if !used_indices.contains(&idx) {
+            used_indices.push(idx);
+        }
+    }
+
+    let mut active_assignment = role_assignment_from_lab(
+        ColorRole::ActiveItem,
+        result.active.lab,
+        result.active.source_cluster_index,
+        result.active.confidence(),
+        Some(&result.active.origin_label),
+        Some(bg_lab),
+        Some(text_lab),
+    );
+
+    if let Some(idx) = result.active.source_cluster_index {
+        if !used_indices.contains(&idx) {
+            used_indices.push(idx);
+        }
+    }
+
+    // A pinned active color is kept as given
+    let active_contrast_ok = pinned_active.is_some()
+        || color::calculate_contrast_ratio(active_assignment.lab, bg_lab) >= ACTIVE_BG_MIN;
+    if !active_contrast_ok {
+        if let Some((derived_lab, origin)) =
+            derive_active_from_background(bg_lab, accent_assignment.lab)
+        {
+            active_assignment = role_assignment_from_lab(
+                ColorRole::ActiveItem,
+                derived_lab,
+                None,
+                active_assignment.confidence.min(0.6),
+                Some(&origin),
+                Some(bg_lab),
+                Some(text_lab),
+            );
+        }
+    }
+
+    // Hue separation: if active is too close in hue to background or text, derive a hue-rotated candidate
+    if pinned_active.is_none() && hue_separation {
+        let act_rgb = color::lab_to_rgb8(active_assignment.lab);
+        let act_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(act_rgb));
+        let bg_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(bg_lab)));
+        let txt_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(text_lab)));
+        let accent_oklch = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(
+            accent_assignment.lab,
+        )));
+        let ok_bg = hue_separation_ok(
+            act_oklch[2],
+            act_oklch[1],
+            bg_oklch[2],
+            bg_oklch[1],
+            MIN_HUE_DELTA_ACTIVE_BG,
+        );
+        let ok_txt = hue_separation_ok(
+            act_oklch[2],
+            act_oklch[1],
+            txt_oklch[2],
+            txt_oklch[1],
+            MIN_HUE_DELTA_ACTIVE_TEXT,
+        );
+        if !(ok_bg && ok_txt) {
+            if let Some((lab, origin)) = derive_active_with_hue(
+                bg_lab,
+                text_lab,
+                accent_assignment.lab,
+                bg_oklch,
+                txt_oklch,
+                accent_oklch,
+            ) {
+                let metrics = build_pair_metrics(accent_assignment.lab, lab, bg_lab, text_lab);
+                let hue_ok = hue_separation_ok(
+                    metrics.active_hue,
+                    metrics.active_chroma,
+                    metrics.background_hue,
+                    metrics.background_chroma,
+                    MIN_HUE_DELTA_ACTIVE_BG,
+                ) && hue_separation_ok(
+                    metrics.active_hue,
+                    metrics.active_chroma,
+                    metrics.text_hue,
+                    metrics.text_chroma,
+                    MIN_HUE_DELTA_ACTIVE_TEXT,
+                ) && hue_separation_ok(
+                    metrics.accent_hue,
+                    metrics.accent_chroma,
+                    metrics.active_hue,
+                    metrics.active_chroma,
+                    MIN_HUE_DELTA_ACCENT_ACTIVE,
+                ) && hue_separation_ok(
+                    metrics.accent_hue,
+                    metrics.accent_chroma,
+                    metrics.background_hue,
+                    metrics.background_chroma,
+                    MIN_HUE_DELTA_ACCENT_BG,
+                );
+                if hue_ok
+                    && passes_pairwise_guardrails(&metrics, strict)
+                    && pair_cvd_ok(accent_assignment.lab, lab, bg_lab, cvd)
+                {
+                    active_assignment = role_assignment_from_lab(
+                        ColorRole::ActiveItem,
+                        lab,
+                        None,
+                        active_assignment.confidence.min(0.6),
+                        Some(&format!("{}+hue", origin)),
+                        Some(bg_lab),
+                        Some(text_lab),
+                    );
+                } else if let Some((derived_lab, origin_bg)) =
+                    derive_active_from_background(bg_lab, accent_assignment.lab)
+                {
+                    let origin = format!("fallback_bg:{}", origin_bg);
+                    active_assignment = role_assignment_from_lab(
+                        ColorRole::ActiveItem,
+                        derived_lab,
+                        None,
+                        active_assignment.confidence.min(0.6),
+                        Some(&origin),
+                        Some(bg_lab),
+                        Some(text_lab),
+                    );
+                }
+            }
+        }
+    }
+
+    let debug = if debug_enabled {
+        let winning_entry = make_debug_entry(&result);
+        let top_entries: Vec<PairwiseDebugEntry> =
+            debug_pairs.iter().map(make_debug_entry).collect();
+        Some(PairwiseDebug {
+            evaluated_pairs: total_pairs_evaluated,
+            pass_mode,
+            stages: greedy_stages(strict, relaxed, cvd),
+            winning_pair: winning_entry,
+            top_pairs: top_entries,
+            accent_candidates: candidate_list_for_debug(&accent_candidates, bg_lab, text_lab),
+            active_candidates: candidate_list_for_debug(&active_candidates, bg_lab, text_lab),
+        })
+    } else {
+        None
+    };
+
+    (accent_assignment, active_assignment, debug)
+}
+
+fn role_assignment_from_lab(
+    role: ColorRole,
+    lab: [f32; 3],
+    source_cluster_index: Option<usize>,
+    confidence: f32,
+    origin: Option<&str>,
+    bg_lab: Option<[f32; 3]>,
+    text_lab: Option<[f32; 3]>,
+) -> RoleAssignment {
+    let rgb = color::lab_to_rgb8(lab);
+    let hsv = color::rgb8_to_hsv(rgb);
+    let canonical_lab = color::rgb8_to_lab(rgb);
+    let hex = color::rgb_to_hex(rgb);
+
+    let contrast_against_background =
+        bg_lab.map(|bg| color::calculate_contrast_ratio(canonical_lab, bg));
+    let contrast_against_text =
+        text_lab.map(|text| color::calculate_contrast_ratio(canonical_lab, text));
+
+    RoleAssignment {
+        role,
+        rgb: RgbValue {
+            r: rgb[0],
+            g: rgb[1],
+            b: rgb[2],
+        },
+        hsv,
+        lab: canonical_lab,
+        hex,
+        source_cluster_index,
+        confidence,
+        contrast_against_background,
+        contrast_against_text,
+        origin: origin.map(|s| s.to_string()),
+    }
+}
+
+fn clone_for_role(role: ColorRole, base: &RoleAssignment, confidence: f32) -> RoleAssignment {
+    RoleAssignment {
+        role,
+        rgb: base.rgb,
+        hsv: base.hsv,
+        lab: base.lab,
+        hex: base.hex.clone(),
+        source_cluster_index: base.source_cluster_index,
+        confidence,
+        contrast_against_background: base.contrast_against_background,
+        contrast_against_text: base.contrast_against_text,
+        origin: base.origin.clone(),
+    }
+}
+
+/// Generate synthetic light text color as fallback
+fn generate_light_text() -> ([u8; 3], [f32; 3], [f32; 3]) {
+    let rgb = [220, 220, 220];
+    let hsv = color::rgb8_to_hsv(rgb);
+    let lab = color::rgb8_to_lab(rgb);
+    (rgb, hsv, lab)
+}
+
+/// Generate synthetic dark text color as fallback
+fn generate_dark_text() -> ([u8; 3], [f32; 3], [f32; 3]) {
+    let rgb = [30, 30, 30];
+    let hsv = color::rgb8_to_hsv(rgb);
+    let lab = color::rgb8_to_lab(rgb);
+    (rgb, hsv, lab)
+}
+
+/// Roles fixed by the solver; everything else is derived from them.
+struct CoreRoles {
+    background: RoleAssignment,
+    text: RoleAssignment,
+    accent: RoleAssignment,
+    active: RoleAssignment,
+    border: RoleAssignment,
+    pairwise_debug: Option<PairwiseDebug>,
+}
+
+fn select_border_assignment(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &[usize],
+    pins: &RolePins,
+) -> RoleAssignment {
+    let (border_idx, border_conf) =
+        select_border_color(clusters, bg_lab, used_indices, &pins.border_neighbors());
+    role_assignment_from_lab(
+        ColorRole::Border,
+        clusters[border_idx].lab,
+        Some(border_idx),
+        border_conf,
+        Some("cluster"),
+        None,
+        None,
+    )
+}
+
+/// Sequential pipeline: background, text, accent/active pair, border.
+fn solve_core_greedy(
+    clusters: &[ColorCluster],
+    options: &RoleOptions,
+    debug_enabled: bool,
+) -> CoreRoles {
+    let pins = &options.pins;
+    let mut used_indices = Vec::new();
+
+    // 1. Background (most dominant, reasonable properties)
+    let background = match pins.background {
+        Some(rgb) => pinned_assignment(ColorRole::Background, rgb, None, None),
+        None => {
+            let (bg_idx, bg_conf) = select_background(clusters);
+            used_indices.push(bg_idx);
+            role_assignment_from_lab(
+                ColorRole::Background,
+                clusters[bg_idx].lab,
+                Some(bg_idx),
+                bg_conf,
+                Some("cluster"),
+                None,
+                None,
+            )
+        }
+    };
+    let bg_lab = background.lab;
+    // Every later role draws from the preset-shaped palette
+    let clusters =
+        &*options
+            .preset
+            .style_clusters(clusters, bg_lab, background.source_cluster_index);
+
+    // 2. Text color with fallback to light/dark synthetic values if needed
+    let text = match pins.text {
+        Some(rgb) => pinned_assignment(ColorRole::Text, rgb, Some(bg_lab), None),
+        None => select_greedy_text(clusters, bg_lab, &mut used_indices),
+    };
+    let text_lab = text.lab;
+
+    // 3. Solve accent + active pair together
+    let (accent, active, pairwise_debug) = select_accent_and_active(
+        clusters,
+        &mut used_indices,
+        bg_lab,
+        text_lab,
+        options,
+        debug_enabled,
+    );
+
+    // 4. Border color (distinct from background)
+    let border = match pins.inactive {
+        Some(rgb) => pinned_assignment(ColorRole::Border, rgb, None, None),
+        None => select_border_assignment(clusters, bg_lab, &used_indices, pins),
+    };
+
+    CoreRoles {
+        background,
+        text,
+        accent,
+        active,
+        border,
+        pairwise_debug,
+    }
+}
+
+fn select_greedy_text(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    used_indices: &mut Vec<usize>,
+) -> RoleAssignment {
+    let (text_idx, mut text_conf) = select_text_color(clusters, bg_lab);
+    let text_cluster = &clusters[text_idx];
+    let mut text_lab = text_cluster.lab;
+    let mut text_source = Some(text_idx);
+    let mut text_origin = "cluster";
+    if color::calculate_contrast_ratio(bg_lab, text_lab) < 4.5 {
+        text_conf = 0.45;
+        text_source = None;
+        text_origin = "synthetic";
+        let (_, _, lab) = if bg_lab[0] < 50.0 {
+            generate_light_text()
+        } else {
+            generate_dark_text()
+        };
+        text_lab = lab;
+    } else {
+        used_indices.push(text_idx);
+    }
+
+    role_assignment_from_lab(
+        ColorRole::Text,
+        text_lab,
+        text_source,
+        text_conf,
+        Some(text_origin),
+        Some(bg_lab),
+        None,
+    )
+}
+
+/// Backgrounds tried by the global solver, in share order.
+const GLOBAL_BACKGROUND_CANDIDATES: usize = 8;
+/// Background/text states kept for the accent/active stage.
+const GLOBAL_BEAM_WIDTH: usize = 8;
+const GLOBAL_TEXT_MIN_CONTRAST: f32 = 4.5;
+/// Objective weights. Share rewards dominant album colors, fidelity penalizes
+/// each provenance step away from a raw cluster (adjusted, synthetic), hue
+/// rewards each satisfied hue-separation check, contrast rewards the log
+/// margin over the background floors, separation rewards accent/active being
+/// distinguishable from text, chroma keeps accent/active colorful and border
+/// rewards a distinct border cluster left over by the assignment.
+const GLOBAL_WEIGHT_SHARE: f32 = 4.0;
+const GLOBAL_WEIGHT_FIDELITY: f32 = 1.0;
+const GLOBAL_WEIGHT_HUE: f32 = 1.0;
+const GLOBAL_WEIGHT_CONTRAST: f32 = 1.0;
+const GLOBAL_WEIGHT_SEPARATION: f32 = 1.0;
+const GLOBAL_WEIGHT_CHROMA: f32 = 4.0;
+const GLOBAL_WEIGHT_BORDER: f32 = 1.0;
+/// Bonus for a pair that also clears the strict guardrails.
+const GLOBAL_STRICT_BONUS: f32 = 1.0;
+/// ΔE at which the separation term saturates.
+const GLOBAL_SEPARATION_DELTA_E: f32 = 50.0;
+
+/// Hard constraints of the global solver: the accent and active floors against
+/// both background and text plus a distinguishable accent/active pair. Peer
+/// contrast, brightness separation and hue separation are scored rather than
+/// required.
+const GLOBAL_GUARDRAILS: PairwiseGuardrails = PairwiseGuardrails {
+    min_accent_vs_bg: ACCENT_BG_MIN,
+    min_accent_vs_text: ACCENT_TEXT_MIN,
+    min_active_vs_bg: ACTIVE_BG_RELAXED_MIN,
+    min_active_vs_text: ACTIVE_TEXT_MIN,
+    min_peer_contrast: 1.0,
+    min_peer_delta_e: RELAXED_PEER_DELTA_E_MIN,
+    min_brightness_separation: 0.0,
+    hue_separation: false,
+};
+
+/// Background/text prefix of a complete assignment.
+struct GlobalState {
+    /// `None` when the background is pinned.
+    bg_idx: Option<usize>,
+    bg_lab: [f32; 3],
+    text_idx: Option<usize>,
+    text_lab: [f32; 3],
+    score: f32,
+}
+
+/// Every unused cluster that clears `min_contrast_bg` and `min_contrast_text`,
+/// plus the nearest lightness-adjusted variants that do.
+fn build_global_candidates(
+    clusters: &[ColorCluster],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    used_indices: &[usize],
+    min_contrast_bg: f32,
+    min_contrast_text: f32,
+) -> Vec<RoleColorCandidate> {
+    // Fine steps: both floors together often leave only a narrow L band
+    let guard = GuardrailConfig {
+        min_contrast_bg,
+        min_contrast_text,
+        min_contrast_peer: None,
+        min_delta_e_peer: None,
+        adjust_step: 1.0,
+        max_adjust_steps: 100,
+    };
+
+    let mut results: Vec<RoleColorCandidate> = Vec::new();
+    for (idx, cluster) in clusters.iter().enumerate() {
+        if used_indices.contains(&idx) {
+            continue;
+        }
+        let base_score = cluster.share as f32;
+        if color::calculate_contrast_ratio(cluster.lab, bg_lab) >= min_contrast_bg
+            && color::calculate_contrast_ratio(cluster.lab, text_lab) >= min_contrast_text
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: cluster.lab,
+                    source_cluster_index: Some(idx),
+                    origin_label: format!("cluster:{}", idx),
+                    provenance_rank: 0,
+                    base_score,
+                },
+            );
+            continue;
+        }
+        for (adjusted, delta_l) in
+            collect_adjusted_variants(cluster.lab, bg_lab, text_lab, &guard, None)
+        {
+            push_candidate_if_unique(
+                &mut results,
+                RoleColorCandidate {
+                    lab: canonical_lab(adjusted),
+                    source_cluster_index: Some(idx),
+                    origin_label: format!("adjusted:{}:{:+.1}", idx, delta_l),
+                    provenance_rank: 2,
+                    base_score,
+                },
+            );
+        }
+    }
+    results
+}
+
+fn hue_separation_count(metrics: &PairwiseMetrics) -> usize {
+    [
+        (
+            metrics.active_hue,
+            metrics.active_chroma,
+            metrics.background_hue,
+            metrics.background_chroma,
+            MIN_HUE_DELTA_ACTIVE_BG,
+        ),
+        (
+            metrics.active_hue,
+            metrics.active_chroma,
+            metrics.text_hue,
+            metrics.text_chroma,
+            MIN_HUE_DELTA_ACTIVE_TEXT,
+        ),
+        (
+            metrics.accent_hue,
+            metrics.accent_chroma,
+            metrics.active_hue,
+            metrics.active_chroma,
+            MIN_HUE_DELTA_ACCENT_ACTIVE,
+        ),
+        (
+            metrics.accent_hue,
+            metrics.accent_chroma,
+            metrics.background_hue,
+            metrics.background_chroma,
+            MIN_HUE_DELTA_ACCENT_BG,
+        ),
+    ]
+    .into_iter()
+    .filter(|&(h1, c1, h2, c2, min_delta)| hue_separation_ok(h1, c1, h2, c2, min_delta))
+    .count()
+}
+
+fn global_pair_score(
+    result: &PairwiseResult,
+    clusters: &[ColorCluster],
+    text_lab: [f32; 3],
+    preset: &Preset,
+) -> f32 {
+    let metrics = &result.metrics;
+    let share = |candidate: &RoleColorCandidate| {
+        candidate
+            .source_cluster_index
+            .map_or(0.0, |idx| clusters[idx].share as f32)
+    };
+    let contrast_margin = 0.5
+        * ((metrics.accent_bg / ACCENT_BG_MIN).ln()
+            + (metrics.active_bg / ACTIVE_BG_RELAXED_MIN).ln());
+    let separation = color::delta_e_cie76(result.accent.lab, text_lab)
+        .min(color::delta_e_cie76(result.active.lab, text_lab))
+        .min(GLOBAL_SEPARATION_DELTA_E)
+        / GLOBAL_SEPARATION_DELTA_E;
+    let strict_bonus = if passes_pairwise_guardrails(metrics, STRICT_GUARDRAILS.for_preset(preset))
+    {
+        GLOBAL_STRICT_BONUS
+    } else {
+        0.0
+    };
+    GLOBAL_WEIGHT_SHARE * 0.5 * (share(&result.accent) + share(&result.active))
+        - GLOBAL_WEIGHT_FIDELITY * 0.5 * result.provenance_score as f32
+        + GLOBAL_WEIGHT_HUE * hue_separation_count(metrics) as f32
+        + GLOBAL_WEIGHT_CONTRAST * contrast_margin
+        + GLOBAL_WEIGHT_SEPARATION * separation
+        + (GLOBAL_WEIGHT_CHROMA + preset.chroma_weight)
+            * (metrics.accent_chroma + metrics.active_chroma)
+        + strict_bonus
+}
+
+/// Whether `select_highlight_text` will find text, black or white that reads on
+/// the active background, or the `pinned` highlight does, so the solver never
+/// strands the highlight.
+fn highlight_feasible(
+    active_lab: [f32; 3],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    cvd: Option<CvdMode>,
+    pinned: Option<[u8; 3]>,
+) -> bool {
+    let candidates = match pinned {
+        Some(rgb) => vec![color::rgb8_to_lab(rgb)],
+        None => vec![
+            text_lab,
+            color::rgb8_to_lab([0, 0, 0]),
+            color::rgb8_to_lab([255, 255, 255]),
+        ],
+    };
+    candidates.into_iter().any(|lab| {
+        color::calculate_contrast_ratio(lab, active_lab) >= HIGHLIGHT_TEXT_MIN_CONTRAST
+            && color::calculate_contrast_ratio(lab, bg_lab) >= HIGHLIGHT_TEXT_BG_MIN
+            && cvd_separation_ok(&[(lab, active_lab)], cvd)
+    })
+}
+
+/// Whether `active_lab` works with the roles derived from it: the highlight
+/// stays feasible and a pinned frame stays distinct on the progress bar.
+fn active_fits_pins(
+    active_lab: [f32; 3],
+    bg_lab: [f32; 3],
+    text_lab: [f32; 3],
+    options: &RoleOptions,
+) -> bool {
+    let pins = &options.pins;
+    highlight_feasible(
+        active_lab,
+        bg_lab,
+        text_lab,
+        options.cvd,
+        pins.highlight_text,
+    ) && pins.frame.is_none_or(|rgb| {
+        color::delta_e_cie76(color::rgb8_to_lab(rgb), active_lab) >= RELAXED_PEER_DELTA_E_MIN
+    })
+}
+
+/// Round-trip through sRGB so constraints are checked on the emitted colors.
+fn canonical_lab(lab: [f32; 3]) -> [f32; 3] {
+    color::rgb8_to_lab(color::lab_to_rgb8(lab))
+}
+
+/// Beam search over complete core assignments. Background and text are scored
+/// first and the best `GLOBAL_BEAM_WIDTH` prefixes are extended with every
+/// accent/active pair that clears `GLOBAL_GUARDRAILS` and leaves the highlight
+/// and frame feasible; the border left over is scored with the pair and the
+/// highest total wins. Returns `None` when no prefix admits such a pair.
+/// Pinned roles narrow their candidate set to the pinned color.
+fn solve_core_global(
+    clusters: &[ColorCluster],
+    options: &RoleOptions,
+    debug_enabled: bool,
+) -> Option<CoreRoles> {
+    let (cvd, pins, preset) = (options.cvd, &options.pins, &options.preset);
+    let guardrails = GLOBAL_GUARDRAILS.for_preset(preset);
+    let border_neighbors = pins.border_neighbors();
+    let synthetic_texts = [generate_light_text().2, generate_dark_text().2];
+    let backgrounds: Vec<(Option<usize>, [f32; 3], f32)> = match pins.background {
+        Some(rgb) => vec![(None, color::rgb8_to_lab(rgb), 0.0)],
+        None => clusters
+            .iter()
+            .enumerate()
+            .take(GLOBAL_BACKGROUND_CANDIDATES)
+            .map(|(bg_idx, bg)| {
+                let mut bg_score = GLOBAL_WEIGHT_SHARE * bg.share as f32;
+                if !background_suitable(bg) {
+                    bg_score -= GLOBAL_WEIGHT_FIDELITY;
+                }
+                (Some(bg_idx), bg.lab, bg_score)
+            })
+            .collect(),
+    };
+
+    // Stage 1: background and text prefixes
+    let mut states: Vec<GlobalState> = Vec::new();
+    for (bg_idx, bg_lab, bg_score) in backgrounds {
+        let texts: Vec<(Option<usize>, [f32; 3])> = match pins.text {
+            Some(rgb) => vec![(None, color::rgb8_to_lab(rgb))],
+            None => preset
+                .style_clusters(clusters, bg_lab, bg_idx)
+                .iter()
+                .enumerate()
+                .filter(|&(idx, _)| Some(idx) != bg_idx)
+                .map(|(idx, cluster)| (Some(idx), cluster.lab))
+                .chain(synthetic_texts.iter().map(|&lab| (None, lab)))
+                .collect(),
+        };
+        for (text_idx, text_lab) in texts {
+            let contrast = color::calculate_contrast_ratio(bg_lab, text_lab);
+            if contrast < GLOBAL_TEXT_MIN_CONTRAST && pins.text.is_none() {
+                continue;
+            }
+            let provenance = if text_idx.is_some() || pins.text.is_some() {
+                0.0
+            } else {
+                2.0
+            };
+            states.push(GlobalState {
+                bg_idx,
+                bg_lab,
+                text_idx,
+                text_lab,
+                score: bg_score - GLOBAL_WEIGHT_FIDELITY * provenance
+                    + GLOBAL_WEIGHT_CONTRAST * 0.5 * (contrast / GLOBAL_TEXT_MIN_CONTRAST).ln(),
+            });
+        }
+    }
+    states.sort_by(|a, b| cmp_f32_desc(a.score, b.score));
+    states.truncate(GLOBAL_BEAM_WIDTH);
+
+    // Stage 2: extend each prefix with its best accent/active pair
+    let mut evaluated = 0usize;
+    let mut finalists: Vec<(f32, PairwiseResult)> = Vec::new();
+    let mut best: Option<(f32, usize, PairwiseResult)> = None;
+    let mut best_candidates = (Vec::new(), Vec::new());
+    for (state_idx, state) in states.iter().enumerate() {
+        let bg_lab = state.bg_lab;
+        let styled = preset.style_clusters(clusters, bg_lab, state.bg_idx);
+        let used: Vec<usize> = state.bg_idx.into_iter().chain(state.text_idx).collect();
+        let accents = match pins.accent {
+            Some(rgb) => vec![pinned_candidate(rgb)],
+            None => build_global_candidates(
+                &styled,
+                bg_lab,
+                state.text_lab,
+                &used,
+                guardrails.min_accent_vs_bg,
+                guardrails.min_accent_vs_text,
+            ),
+        };
+        let actives = match pins.active {
+            Some(rgb) => vec![pinned_candidate(rgb)],
+            None => {
+                let mut actives = build_global_candidates(
+                    &styled,
+                    bg_lab,
+                    state.text_lab,
+                    &used,
+                    guardrails.min_active_vs_bg,
+                    guardrails.min_active_vs_text,
+                );
+                actives
+                    .retain(|active| active_fits_pins(active.lab, bg_lab, state.text_lab, options));
+                actives
+            }
+        };
+
+        let mut state_best: Option<(f32, PairwiseResult)> = None;
+        for accent in &accents {
+            for active in &actives {
+                evaluated += 1;
+                let metrics = build_pair_metrics(accent.lab, active.lab, bg_lab, state.text_lab);
+                if !passes_pairwise_guardrails(&metrics, guardrails)
+                    || !pair_cvd_ok(accent.lab, active.lab, bg_lab, cvd)
+                    || (pins.frame.is_none()
+                        && frame_candidates(
+                            accent.lab,
+                            accent.confidence(),
+                            bg_lab,
+                            state.text_lab,
+                        )
+                        .is_empty())
+                {
+                    continue;
+                }
+                let result = PairwiseResult {
+                    accent: accent.clone(),
+                    active: active.clone(),
+                    metrics,
+                    provenance_score: accent.provenance_rank + active.provenance_rank,
+                };
+                let mut score =
+                    state.score + global_pair_score(&result, clusters, state.text_lab, preset);
+                if pins.inactive.is_none() {
+                    let mut pair_used = used.clone();
+                    pair_used.extend(accent.source_cluster_index);
+                    pair_used.extend(active.source_cluster_index);
+                    score += GLOBAL_WEIGHT_BORDER
+                        * best_border(&styled, bg_lab, &pair_used, &border_neighbors)
+                            .map_or(-1.0, |(_, b)| b);
+                }
+                if state_best.as_ref().is_none_or(|(top, _)| score > *top) {
+                    state_best = Some((score, result));
+                }
+            }
+        }
+
+        let Some((score, result)) = state_best else {
+            continue;
+        };
+        if best.as_ref().is_none_or(|(top, _, _)| score > *top) {
+            best = Some((score, state_idx, result.clone()));
+            if debug_enabled {
+                best_candidates = (accents, actives);
+            }
+        }
+        if debug_enabled {
+            finalists.push((score, result));
+        }
+    }
+
+    let (_, state_idx, result) = best?;
+    let state = &states[state_idx];
+    let background = match state.bg_idx {
+        None => pinned_assignment(
+            ColorRole::Background,
+            color::lab_to_rgb8(state.bg_lab),
+            None,
+            None,
+        ),
+        Some(bg_idx) => {
+            let bg_cluster = &clusters[bg_idx];
+            role_assignment_from_lab(
+                ColorRole::Background,
+                bg_cluster.lab,
+                Some(bg_idx),
+                if background_suitable(bg_cluster) {
+                    0.9
+                } else {
+                    0.5
+                },
+                Some("cluster"),
+                None,
+                None,
+            )
+        }
+    };
+    let bg_lab = background.lab;
+    let text = match pins.text {
+        Some(rgb) => pinned_assignment(ColorRole::Text, rgb, Some(bg_lab), None),
+        None => role_assignment_from_lab(
+            ColorRole::Text,
+            state.text_lab,
+            state.text_idx,
+            if state.text_idx.is_some() { 0.9 } else { 0.45 },
+            Some(if state.text_idx.is_some() {
+                "cluster"
+            } else {
+                "synthetic"
+            }),
+            Some(bg_lab),
+            None,
+        ),
+    };
+    let text_lab = text.lab;
+    let accent = role_assignment_from_lab(
+        ColorRole::Accent,
+        result.accent.lab,
+        result.accent.source_cluster_index,
+        result.accent.confidence(),
+        Some(&result.accent.origin_label),
+        Some(bg_lab),
+        Some(text_lab),
+    );
+    let active = role_assignment_from_lab(
+        ColorRole::ActiveItem,
+        result.active.lab,
+        result.active.source_cluster_index,
+        result.active.confidence(),
+        Some(&result.active.origin_label),
+        Some(bg_lab),
+        Some(text_lab),
+    );
+
+    let used_indices: Vec<usize> = [
+        state.bg_idx,
+        state.text_idx,
+        result.accent.source_cluster_index,
+        result.active.source_cluster_index,
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let border = match pins.inactive {
+        Some(rgb) => pinned_assignment(ColorRole::Border, rgb, None, None),
+        None => select_border_assignment(
+            &preset.style_clusters(clusters, bg_lab, state.bg_idx),
+            bg_lab,
+            &used_indices,
+            pins,
+        ),
+    };
+
+    let pairwise_debug = debug_enabled.then(|| {
+        let strict = STRICT_GUARDRAILS.for_preset(preset);
+        let pass_mode = if passes_pairwise_guardrails(&result.metrics, strict) {
+            "global:strict"
+        } else {
+            "global"
+        };
+        finalists.sort_by(|a, b| cmp_f32_desc(a.0, b.0));
+        PairwiseDebug {
+            evaluated_pairs: evaluated,
+            pass_mode: pass_mode.to_string(),
+            stages: global_stages(strict, guardrails, cvd),
+            winning_pair: make_debug_entry(&result),
+            top_pairs: finalists
+                .iter()
+                .take(8)
+                .map(|(_, pair)| make_debug_entry(pair))
+                .collect(),
+            accent_candidates: candidate_list_for_debug(&best_candidates.0, bg_lab, text_lab),
+            active_candidates: candidate_list_for_debug(&best_candidates.1, bg_lab, text_lab),
+        }
+    });
+
+    Some(CoreRoles {
+        background,
+        text,
+        accent,
+        active,
+        border,
+        pairwise_debug,
+    })
+}
+
+fn cvd_debug_entries(
+    mode: CvdMode,
+    accent: &RoleAssignment,
+    active: &RoleAssignment,
+    highlight: &RoleAssignment,
+    bg_lab: [f32; 3],
+) -> Vec<CvdDebugEntry> {
+    mode.kinds()
+        .iter()
+        .map(|&kind| {
+            let accent_active = cvd_delta_e(accent.lab, active.lab, kind);
+            let accent_background = cvd_delta_e(accent.lab, bg_lab, kind);
+            let active_background = cvd_delta_e(active.lab, bg_lab, kind);
+            let highlight_active = cvd_delta_e(highlight.lab, active.lab, kind);
+            CvdDebugEntry {
+                kind: kind.name(),
+                accent_active,
+                accent_background,
+                active_background,
+                highlight_active,
+                passes: [
+                    accent_active,
+                    accent_background,
+                    active_background,
+                    highlight_active,
+                ]
+                .iter()
+                .all(|&delta_e| delta_e >= CVD_DELTA_E_MIN),
+            }
+        })
+        .collect()
+}
+
+/// Guardrails between role pairs that involve at least one pinned role and
+/// that the solved `assignments` fail, at the floors `options.preset` sets.
+/// Covers the status colors against a pinned background and, with `--cvd`,
+/// the simulated separation checks. Pairs between two solved roles are the
+/// solver's business and are not reported.
+pub fn pin_conflicts(assignments: &[RoleAssignment], options: &RoleOptions) -> Vec<PinConflict> {
+    let pins = &options.pins;
+    if pins.is_empty() {
+        return Vec::new();
+    }
+    let guardrails = GLOBAL_GUARDRAILS.for_preset(&options.preset);
+    let find = |role| assignments.iter().find(|a| a.role == role);
+    let mut checks: Vec<(&'static str, ColorRole, ColorRole, f32, bool)> = vec![
+        (
+            "text/bg",
+            ColorRole::Text,
+            ColorRole::Background,
+            GLOBAL_TEXT_MIN_CONTRAST,
+            false,
+        ),
+        (
+            "accent/bg",
+            ColorRole::Accent,
+            ColorRole::Background,
+            guardrails.min_accent_vs_bg,
+            false,
+        ),
+        (
+            "active/bg",
+            ColorRole::ActiveItem,
+            ColorRole::Background,
+            guardrails.min_active_vs_bg,
+            false,
+        ),
+        (
+            "active/text",
+            ColorRole::ActiveItem,
+            ColorRole::Text,
+            guardrails.min_active_vs_text,
+            false,
+        ),
+        (
+            "accent/active",
+            ColorRole::Accent,
+            ColorRole::ActiveItem,
+            guardrails.min_peer_delta_e,
+            true,
+        ),
+        (
+            "highlight_text/active",
+            ColorRole::HighlightText,
+            ColorRole::ActiveItem,
+            HIGHLIGHT_TEXT_MIN_CONTRAST,
+            false,
+        ),
+        (
+            "frame/bg",
+            ColorRole::Frame,
+            ColorRole::Background,
+            FRAME_BG_MIN,
+            false,
+        ),
+        (
+            "header_accent/bg",
+            ColorRole::HeaderAccent,
+            ColorRole::Background,
+            options.preset.accent_floor(),
+            false,
+        ),
+    ];
+    // Status colors are solved against the background, so only its pin binds them
+    checks.extend(
+        [
+            ("info/bg", ColorRole::Info),
+            ("warn/bg", ColorRole::Warn),
+            ("error/bg", ColorRole::Error),
+            ("debug/bg", ColorRole::Debug),
+            ("trace/bg", ColorRole::Trace),
+        ]
+        .map(|(guardrail, role)| {
+            (
+                guardrail,
+                role,
+                ColorRole::Background,
+                options.preset.accent_floor(),
+                false,
+            )
+        }),
+    );
+
+    let pinned_of = |fg, bg| -> Vec<&'static str> {
+        [fg, bg]
+            .into_iter()
+            .filter_map(|role| pins.pinned_name(role))
+            .collect()
+    };
+    let mut conflicts: Vec<PinConflict> = checks
+        .into_iter()
+        .filter_map(|(guardrail, fg, bg, min, delta_e)| {
+            let pinned = pinned_of(fg, bg);
+            if pinned.is_empty() {
+                return None;
+            }
+            let (fg, bg) = (find(fg)?, find(bg)?);
+            let value = if delta_e {
+                color::delta_e_cie76(fg.lab, bg.lab)
+            } else {
+                color::calculate_contrast_ratio(fg.lab, bg.lab)
+            };
+            (value < min).then_some(PinConflict {
+                guardrail,
+                pinned,
+                value,
+                min,
+                cvd: None,
+            })
+        })
+        .collect();
+
+    conflicts.extend(
+        cvd_violations(assignments, options)
+            .into_iter()
+            .filter(|conflict| !conflict.pinned.is_empty()),
+    );
+    conflicts
+}
+
+/// `--cvd` separation checks between solved roles that the solver could not
+/// meet, e.g. when the final fallback or the highlight text had no candidate
+/// that clears them. Pairs involving a pin are left to [`pin_conflicts`].
+pub fn cvd_conflicts(assignments: &[RoleAssignment], options: &RoleOptions) -> Vec<PinConflict> {
+    cvd_violations(assignments, options)
+        .into_iter()
+        .filter(|conflict| conflict.pinned.is_empty())
+        .collect()
+}
+
+/// Every `--cvd` separation check the solved `assignments` fail.
+fn cvd_violations(assignments: &[RoleAssignment], options: &RoleOptions) -> Vec<PinConflict> {
+    let find = |role| assignments.iter().find(|a| a.role == role);
+    let pairs = [
+        ("accent/active", ColorRole::Accent, ColorRole::ActiveItem),
+        ("accent/bg", ColorRole::Accent, ColorRole::Background),
+        ("active/bg", ColorRole::ActiveItem, ColorRole::Background),
+        (
+            "highlight_text/active",
+            ColorRole::HighlightText,
+            ColorRole::ActiveItem,
+        ),
+    ];
+    let mut violations = Vec::new();
+    for &kind in options.cvd.map_or(&[][..], CvdMode::kinds) {
+        for (guardrail, fg, bg) in pairs {
+            let pinned = [fg, bg]
+                .into_iter()
+                .filter_map(|role| options.pins.pinned_name(role))
+                .collect();
+            let (Some(fg), Some(bg)) = (find(fg), find(bg)) else {
+                continue;
+            };
+            let value = cvd_delta_e(fg.lab, bg.lab, kind);
+            if value < CVD_DELTA_E_MIN {
+                violations.push(PinConflict {
+                    guardrail,
+                    pinned,
+                    value,
+                    min: CVD_DELTA_E_MIN,
+                    cvd: Some(kind.name()),
+                });
+            }
+        }
+    }
+    violations
+}
+
+/// Map color clusters to UI element roles. `clusters` must be sorted by share,
+/// most dominant first.
+pub fn map_colors_to_roles(
+    clusters: &[ColorCluster],
+    options: &RoleOptions,
+    debug_enabled: bool,
+) -> (
+    Vec<RoleAssignment>,
+    Option<PairwiseDebug>,
+    Option<RolesDebug>,
+) {
+    let (cvd, pins) = (options.cvd, &options.pins);
+    let core = match options.solver {
+        RoleSolver::Greedy => solve_core_greedy(clusters, options, debug_enabled),
+        RoleSolver::Global => solve_core_global(clusters, options, debug_enabled)
+            .unwrap_or_else(|| solve_core_greedy(clusters, options, debug_enabled)),
+    };
+    let CoreRoles {
+        background,
+        text: text_assignment,
+        accent: accent_assignment,
+        active: active_assignment,
+        border: border_assignment,
+        pairwise_debug,
+    } = core;
+    let bg_lab = background.lab;
+    let text_lab = text_assignment.lab;
+
+    let mut assignments = vec![
+        background,
+        text_assignment.clone(),
+        accent_assignment.clone(),
+        border_assignment.clone(),
+        active_assignment.clone(),
+    ];
+
+    // 5. Highlight text color tuned for active background
+    let (mut highlight_assignment, highlight_debug) = select_highlight_text(
+        &text_assignment,
+        &accent_assignment,
+        &active_assignment,
+        bg_lab,
+        cvd,
+    );
+    if let Some(rgb) = pins.highlight_text {
+        highlight_assignment =
+            pinned_assignment(ColorRole::HighlightText, rgb, Some(bg_lab), Some(text_lab));
+    }
+    assignments.push(highlight_assignment.clone());
+
+    // 6. Frame color for borders/separators
+    let (mut frame_assignment, frame_debug) =
+        select_frame_color(&accent_assignment, bg_lab, text_lab);
+    if let Some(rgb) = pins.frame {
+        frame_assignment = pinned_assignment(ColorRole::Frame, rgb, Some(bg_lab), Some(text_lab));
+    }
+    assignments.push(frame_assignment.clone());
+
+    // 6b. Header accent derived from accent with ≥4.5:1 vs background
+    let header_accent = match pins.header_accent {
+        Some(rgb) => pinned_assignment(ColorRole::HeaderAccent, rgb, Some(bg_lab), Some(text_lab)),
+        None => select_header_accent(
+            &accent_assignment,
+            bg_lab,
+            text_lab,
+            options.preset.accent_floor(),
+        ),
+    };
+    assignments.push(header_accent);
+
+    // 6. Inactive/muted - reuse border color
+    assignments.push(clone_for_role(
+        ColorRole::InactiveItem,
+        &border_assignment,
+        0.7,
+    ));
+
+    // 7. Progress bar - reuse accent color
+    assignments.push(clone_for_role(
+        ColorRole::ProgressBar,
+        &accent_assignment,
+        accent_assignment.confidence,
+    ));
+
+    // 8. Scrollbar - reuse active color
+    assignments.push(clone_for_role(
+        ColorRole::Scrollbar,
+        &active_assignment,
+        active_assignment.confidence,
+    ));
+
+    // 9. Log-level status colors in fixed hue families
+    assignments.extend(select_status_colors(
+        clusters,
+        &accent_assignment,
+        bg_lab,
+        text_lab,
+        options.preset.accent_floor(),
+    ));
+
+    let playing_debug = {
+        let contrast_active =
+            color::calculate_contrast_ratio(highlight_assignment.lab, active_assignment.lab);
+        let contrast_bg = color::calculate_contrast_ratio(highlight_assignment.lab, bg_lab);
+        let contrast_text =
+            color::calculate_contrast_ratio(highlight_assignment.lab, text_lab);
+        let fg_origin = highlight_assignment.origin.as_deref().unwrap_or("highlight");
+        let bg_origin = active_assignment.origin.as_deref().unwrap_or("active");
+        RoleDebugEntry {
+            hex: highlight_assignment.hex.clone(),
+            origin: format!("fg:{} | bg:{}", fg_origin, bg_origin),
+            contrast_against_active: Some(contrast_active),
+            contrast_against_background: Some(contrast_bg),
+            contrast_against_text: Some(contrast_text),
+        }
+    };
+
+    let roles_debug = if debug_enabled {
+        Some(RolesDebug {
+            highlight_text: highlight_debug,
+            frame: frame_debug,
+            playing_row: playing_debug,
+            cvd: cvd_debug_entries(
+                cvd.unwrap_or(CvdMode::All),
+                &accent_assignment,
+                &active_assignment,
+                &highlight_assignment,
+                bg_lab,
+            ),
+        })
+    } else {
+        None
+    };
+
+    (assignments, pairwise_debug, roles_debug)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn low_contrast_palette_falls_back_to_synthetic_text() {
+        let clusters = vec![
+            ColorCluster::from_rgb([40, 40, 48], 0.6),
+            ColorCluster::from_rgb([52, 50, 60], 0.3),
+            ColorCluster::from_rgb([60, 44, 44], 0.1),
+        ];
+        let (roles, pairwise, roles_debug) =
+            map_colors_to_roles(&clusters, &RoleOptions::default(), false);
+        assert!(pairwise.is_none() && roles_debug.is_none());
+
+        let find = |role| roles.iter().find(|a| a.role == role).expect("role assigned");
+        let background = find(ColorRole::Background);
+        let text = find(ColorRole::Text);
+        assert_eq!(background.source_cluster_index, Some(0));
+        assert_eq!(text.origin.as_deref(), Some("synthetic"));
+        assert!(color::calculate_contrast_ratio(background.lab, text.lab) >= 4.5);
+        for role in [ColorRole::Accent, ColorRole::ActiveItem, ColorRole::Frame] {
+            find(role);
+        }
+    }
+
+    #[test]
+    fn global_solver_keeps_palette_colors_where_greedy_falls_back() {
+        // Accent at 4.5:1 against both background and text needs a black/white core
+        let clusters = vec![
+            ColorCluster::from_rgb([0, 0, 0], 0.45),
+            ColorCluster::from_rgb([255, 255, 255], 0.2),
+            ColorCluster::from_rgb([230, 120, 40], 0.15),
+            ColorCluster::from_rgb([40, 170, 170], 0.1),
+            ColorCluster::from_rgb([200, 90, 160], 0.1),
+        ];
+        let find = |roles: &[RoleAssignment], role| {
+            roles
+                .iter()
+                .find(|a| a.role == role)
+                .expect("role assigned")
+                .clone()
+        };
+
+        let (greedy, _, _) = map_colors_to_roles(&clusters, &RoleOptions::default(), false);
+        assert_eq!(
+            find(&greedy, ColorRole::ActiveItem).origin.as_deref(),
+            Some("synthetic:midline")
+        );
+
+        let global_options = RoleOptions {
+            solver: RoleSolver::Global,
+            ..RoleOptions::default()
+        };
+        let (global, pairwise, _) = map_colors_to_roles(&clusters, &global_options, true);
+        assert!(pairwise.expect("debug").pass_mode.starts_with("global"));
+        let bg = find(&global, ColorRole::Background);
+        let text = find(&global, ColorRole::Text);
+        let accent = find(&global, ColorRole::Accent);
+        let active = find(&global, ColorRole::ActiveItem);
+        assert!(accent.source_cluster_index.is_some());
+        assert!(active.source_cluster_index.is_some());
+        assert!(color::calculate_contrast_ratio(text.lab, bg.lab) >= 4.5);
+        assert!(color::calculate_contrast_ratio(accent.lab, bg.lab) >= ACCENT_BG_MIN);
+        assert!(color::calculate_contrast_ratio(accent.lab, text.lab) >= ACCENT_TEXT_MIN);
+        assert!(color::calculate_contrast_ratio(active.lab, bg.lab) >= ACTIVE_BG_RELAXED_MIN);
+        assert!(color::calculate_contrast_ratio(active.lab, text.lab) >= ACTIVE_TEXT_MIN);
+        assert!(color::delta_e_cie76(accent.lab, active.lab) >= RELAXED_PEER_DELTA_E_MIN);
+        let border = find(&global, ColorRole::Border);
+        assert!(border.source_cluster_index.is_some());
+        assert!(color::delta_e_cie76(border.lab, bg.lab) > 20.0);
+
+        let (again, _, _) = map_colors_to_roles(&clusters, &global_options, false);
+        let hexes =
+            |roles: &[RoleAssignment]| roles.iter().map(|a| a.hex.clone()).collect::<Vec<_>>();
+        assert_eq!(hexes(&global), hexes(&again));
+    }
+
+    #[test]
+    fn cvd_guardrail_keeps_roles_apart_for_deuteranopes() {
+        let clusters = vec![
+            ColorCluster::from_rgb([0, 0, 0], 0.45),
+            ColorCluster::from_rgb([255, 255, 255], 0.2),
+            ColorCluster::from_rgb([210, 50, 40], 0.15),
+            ColorCluster::from_rgb([60, 170, 50], 0.12),
+            ColorCluster::from_rgb([150, 150, 60], 0.08),
+        ];
+        let deutan_delta = |roles: &[RoleAssignment], a, b| {
+            let lab = |role| roles.iter().find(|x| x.role == role).expect("role").lab;
+            cvd_delta_e(lab(a), lab(b), CvdKind::Deuteranopia)
+        };
+
+        let plain = RoleOptions {
+            solver: RoleSolver::Global,
+            ..RoleOptions::default()
+        };
+        let (roles, _, _) = map_colors_to_roles(&clusters, &plain, false);
+        assert!(deutan_delta(&roles, ColorRole::Accent, ColorRole::ActiveItem) < CVD_DELTA_E_MIN);
+
+        let guarded = RoleOptions {
+            cvd: Some(CvdMode::Deutan),
+            ..plain
+        };
+        let (roles, _, roles_debug) = map_colors_to_roles(&clusters, &guarded, true);
+        for (a, b) in [
+            (ColorRole::Accent, ColorRole::ActiveItem),
+            (ColorRole::Accent, ColorRole::Background),
+            (ColorRole::ActiveItem, ColorRole::Background),
+            (ColorRole::HighlightText, ColorRole::ActiveItem),
+        ] {
+            assert!(deutan_delta(&roles, a, b) >= CVD_DELTA_E_MIN, "{a:?}/{b:?}");
+        }
+        let cvd = roles_debug.expect("debug").cvd;
+        assert_eq!(cvd.len(), 1);
+        assert!(cvd[0].kind == "deutan" && cvd[0].passes);
+    }
+
+    #[test]
+    fn unmet_cvd_separations_are_reported() {
+        let clusters = vec![
+            ColorCluster::from_rgb([40, 40, 48], 0.6),
+            ColorCluster::from_rgb([52, 50, 60], 0.3),
+            ColorCluster::from_rgb([60, 44, 44], 0.1),
+        ];
+        let options = RoleOptions {
+            cvd: Some(CvdMode::Deutan),
+            ..RoleOptions::default()
+        };
+        let (roles, _, roles_debug) = map_colors_to_roles(&clusters, &options, true);
+        assert!(!roles_debug.expect("debug").cvd[0].passes);
+        let conflicts = cvd_conflicts(&roles, &options);
+        assert!(!conflicts.is_empty());
+        for conflict in &conflicts {
+            assert_eq!(conflict.cvd, Some("deutan"));
+            assert!(conflict.pinned.is_empty() && conflict.value < conflict.min);
+        }
+        assert!(cvd_conflicts(&roles, &RoleOptions::default()).is_empty());
+    }
+
+    fn oklch_of(assignment: &RoleAssignment) -> [f32; 3] {
+        let rgb = [assignment.rgb.r, assignment.rgb.g, assignment.rgb.b];
+        let mut lch = color::oklab_to_oklch(color::rgb8_to_oklab(rgb));
+        lch[2] = lch[2].rem_euclid(360.0);
+        lch
+    }
+
+    #[test]
+    fn status_colors_stay_readable_on_light_backgrounds() {
+        let clusters = vec![
+            ColorCluster::from_rgb([242, 238, 228], 0.7),
+            ColorCluster::from_rgb([40, 44, 52], 0.2),
+            ColorCluster::from_rgb([70, 100, 170], 0.1),
+        ];
+        let (roles, _, _) = map_colors_to_roles(&clusters, &RoleOptions::default(), false);
+        let bg = roles
+            .iter()
+            .find(|a| a.role == ColorRole::Background)
+            .expect("background");
+        for (role, _, anchor) in STATUS_HUES {
+            let status = roles.iter().find(|a| a.role == role).expect("status role");
+            assert!(color::calculate_contrast_ratio(status.lab, bg.lab) >= ACCENT_BG_MIN);
+            let hue = oklch_of(status)[2];
+            assert!(
+                color::delta_hue_degrees(hue, anchor) <= STATUS_MAX_HUE_SHIFT + 5.0,
+                "{role:?} hue {hue} drifted from {anchor}"
+            );
+        }
+        let info = roles
+            .iter()
+            .find(|a| a.role == ColorRole::Info)
+            .expect("info");
+        assert!(color::calculate_contrast_ratio(info.lab, bg.lab) >= ACCENT_BG_MIN);
+    }
+
+    #[test]
+    fn status_hue_leans_toward_nearby_palette_color() {
+        let bg = color::rgb8_to_lab([20, 20, 24]);
+        let text = color::rgb8_to_lab([230, 230, 230]);
+        // Strong orange-yellow about 30 degrees past the amber anchor
+        let tint = oklch_to_lab_mapped(0.7, 0.15, 105.0);
+        let clusters = vec![
+            ColorCluster::from_rgb([20, 20, 24], 0.6),
+            ColorCluster::from_rgb(color::lab_to_rgb8(tint), 0.4),
+        ];
+        let accent =
+            role_assignment_from_lab(ColorRole::Accent, text, None, 1.0, None, Some(bg), None);
+        let statuses = select_status_colors(&clusters, &accent, bg, text, ACCENT_BG_MIN);
+        let warn = statuses
+            .iter()
+            .find(|a| a.role == ColorRole::Warn)
+            .expect("warn");
+        assert_eq!(warn.origin.as_deref(), Some("status:warn:palette"));
+        let shift = signed_hue_delta(75.0, oklch_of(warn)[2]);
+        assert!(
+            shift > 8.0 && shift < STATUS_MAX_HUE_SHIFT + 3.0,
+            "shift {shift}"
+        );
+    }
+
+    #[test]
+    fn status_lightness_keeps_a_tint_near_white() {
+        // Only the last sliver below white clears 7:1 on this gray
+        let bg = color::rgb8_to_lab([88, 88, 88]);
+        let lab = solve_status_lightness(140.0, 0.12, bg, 7.0).expect("tinted status color");
+        assert!(color::calculate_contrast_ratio(lab, bg) >= 7.0);
+        let [_, chroma, hue] = color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(lab)));
+        assert!(chroma > 0.01, "chroma {chroma}");
+        assert!(color::delta_hue_degrees(hue, 140.0) < 15.0, "hue {hue}");
+    }
+
+    fn palette() -> Vec<ColorCluster> {
+        vec![
+            ColorCluster::from_rgb([30, 60, 120], 0.45),
+            ColorCluster::from_rgb([216, 180, 90], 0.25),
+            ColorCluster::from_rgb([77, 180, 90], 0.15),
+            ColorCluster::from_rgb([200, 60, 50], 0.1),
+            ColorCluster::from_rgb([240, 240, 232], 0.05),
+        ]
+    }
+
+    #[test]
+    fn pinned_roles_are_kept_and_the_rest_solved_around_them() {
+        let mut pins = RolePins::default();
+        pins.set("background", Some([16, 16, 20])).expect("pin");
+        pins.set("accent", Some([230, 120, 200])).expect("pin");
+        for solver in [RoleSolver::Greedy, RoleSolver::Global] {
+            let options = RoleOptions {
+                solver,
+                pins,
+                ..RoleOptions::default()
+            };
+            let (roles, _, _) = map_colors_to_roles(&palette(), &options, false);
+            let find = |role| roles.iter().find(|a| a.role == role).expect("role");
+            let bg = find(ColorRole::Background);
+            assert_eq!(bg.hex, "#101014");
+            assert_eq!(find(ColorRole::Accent).hex, "#e678c8");
+            assert_eq!(find(ColorRole::ProgressBar).hex, "#e678c8");
+            assert!(color::calculate_contrast_ratio(find(ColorRole::Text).lab, bg.lab) >= 4.5);
+            let active = find(ColorRole::ActiveItem);
+            assert!(
+                color::calculate_contrast_ratio(active.lab, bg.lab) >= ACTIVE_BG_RELAXED_MIN,
+                "{solver:?}"
+            );
+            assert!(pin_conflicts(&roles, &options).is_empty(), "{solver:?}");
+        }
+    }
+
+    #[test]
+    fn impossible_pins_are_reported() {
+        let mut pins = RolePins::default();
+        pins.set("bg", Some([16, 16, 20])).expect("pin");
+        pins.set("text", Some([32, 32, 36])).expect("pin");
+        let options = RoleOptions {
+            pins,
+            ..RoleOptions::default()
+        };
+        let (roles, _, _) = map_colors_to_roles(&palette(), &options, false);
+        let conflicts = pin_conflicts(&roles, &options);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].guardrail, "text/bg");
+        assert_eq!(conflicts[0].pinned, ["text", "background"]);
+        assert!(conflicts[0].value < conflicts[0].min);
+
+        // Only pairs touching a pin are reported
+        assert!(pin_conflicts(&roles, &RoleOptions::default()).is_empty());
+        assert!(pins.set("scrollbar", Some([0, 0, 0])).is_err());
+        assert!(pins.set("sidebar", None).is_err());
+    }
+
+    #[test]
+    fn downstream_pins_are_solved_around() {
+        let clusters = vec![
+            ColorCluster::from_rgb([0, 0, 0], 0.45),
+            ColorCluster::from_rgb([255, 255, 255], 0.2),
+            ColorCluster::from_rgb([230, 120, 40], 0.15),
+            ColorCluster::from_rgb([40, 170, 170], 0.1),
+            ColorCluster::from_rgb([200, 90, 160], 0.1),
+        ];
+        let solve = |pins: RolePins| {
+            let options = RoleOptions {
+                solver: RoleSolver::Global,
+                pins,
+                ..RoleOptions::default()
+            };
+            let (roles, _, _) = map_colors_to_roles(&clusters, &options, false);
+            assert!(pin_conflicts(&roles, &options).is_empty(), "{pins:?}");
+            roles
+        };
+        let find = |roles: &[RoleAssignment], role| {
+            roles.iter().find(|a| a.role == role).expect("role").clone()
+        };
+        let unpinned_active = find(&solve(RolePins::default()), ColorRole::ActiveItem);
+
+        let mut pins = RolePins::default();
+        pins.set("highlight_text", Some([0, 0, 0])).expect("pin");
+        let roles = solve(pins);
+        let (highlight, active) = (
+            find(&roles, ColorRole::HighlightText),
+            find(&roles, ColorRole::ActiveItem),
+        );
+        assert_eq!(highlight.hex, "#000000");
+        assert!(
+            color::calculate_contrast_ratio(highlight.lab, active.lab)
+                >= HIGHLIGHT_TEXT_MIN_CONTRAST
+        );
+
+        // A frame pinned to the active color pushes active away from it
+        let frame = [
+            unpinned_active.rgb.r,
+            unpinned_active.rgb.g,
+            unpinned_active.rgb.b,
+        ];
+        let mut pins = RolePins::default();
+        pins.set("frame", Some(frame)).expect("pin");
+        let active = find(&solve(pins), ColorRole::ActiveItem);
+        assert!(color::delta_e_cie76(active.lab, unpinned_active.lab) >= RELAXED_PEER_DELTA_E_MIN);
+    }
+
+    #[test]
+    fn pin_conflicts_cover_cvd_pairs_and_status_colors() {
+        let mut pins = RolePins::default();
+        pins.set("accent", Some([200, 60, 50])).expect("pin");
+        pins.set("active", Some([70, 150, 50])).expect("pin");
+        let options = RoleOptions {
+            cvd: Some(CvdMode::Deutan),
+            pins,
+            ..RoleOptions::default()
+        };
+        let (roles, _, _) = map_colors_to_roles(&palette(), &options, false);
+        let conflicts = pin_conflicts(&roles, &options);
+        let cvd = conflicts
+            .iter()
+            .find(|c| c.guardrail == "accent/active" && c.cvd.is_some())
+            .expect("deutan conflict");
+        assert_eq!(cvd.cvd, Some("deutan"));
+        assert_eq!(cvd.pinned, ["accent", "active"]);
+        assert!(cvd.value < CVD_DELTA_E_MIN);
+
+        let mut pins = RolePins::default();
+        pins.set("bg", Some([16, 16, 20])).expect("pin");
+        let options = RoleOptions {
+            pins,
+            ..RoleOptions::default()
+        };
+        let (mut roles, _, _) = map_colors_to_roles(&palette(), &options, false);
+        assert!(pin_conflicts(&roles, &options).is_empty());
+        let warn = roles
+            .iter_mut()
+            .find(|a| a.role == ColorRole::Warn)
+            .expect("warn");
+        warn.lab = color::rgb8_to_lab([40, 30, 20]);
+        let conflicts = pin_conflicts(&roles, &options);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].guardrail, "warn/bg");
+        assert_eq!(conflicts[0].pinned, ["background"]);
+    }
+
+    #[test]
+    fn pins_round_trip_as_hex_and_auto() {
+        let pins: RolePins =
+            serde_json::from_str(r##"{"background": "#101014", "accent": "auto"}"##).expect("pins");
+        assert_eq!(pins.background, Some([16, 16, 20]));
+        assert_eq!(pins.accent, None);
+        assert_eq!(
+            serde_json::to_string(&pins).expect("json"),
+            r##"{"background":"#101014"}"##
+        );
+        assert!(serde_json::from_str::<RolePins>(r#"{"text": "white"}"#).is_err());
+    }
+
+    #[test]
+    fn presets_reshape_the_palette_and_raise_floors() {
+        let solve = |name: &str| {
+            let options = RoleOptions {
+                solver: RoleSolver::Global,
+                preset: Preset::builtin(name).expect("builtin preset"),
+                ..RoleOptions::default()
+            };
+            let (roles, _, _) = map_colors_to_roles(&palette(), &options, false);
+            roles
+        };
+        let find = |roles: &[RoleAssignment], role| {
+            roles.iter().find(|a| a.role == role).expect("role").clone()
+        };
+        let bg_hue =
+            color::oklab_to_oklch(color::rgb8_to_oklab([30, 60, 120]))[2].rem_euclid(360.0);
+
+        let roles = solve("high-contrast");
+        let bg = find(&roles, ColorRole::Background).lab;
+        for role in [ColorRole::Accent, ColorRole::HeaderAccent, ColorRole::Warn] {
+            let contrast = color::calculate_contrast_ratio(find(&roles, role).lab, bg);
+            assert!(contrast >= 7.0, "{role:?} at {contrast}");
+        }
+
+        // CIELAB lightness adjustments drift blues a little in OKLCH hue
+        let roles = solve("monochrome");
+        for role in [ColorRole::Accent, ColorRole::ActiveItem] {
+            let [_, chroma, hue] = oklch_of(&find(&roles, role));
+            assert!(
+                chroma < MIN_CHROMA_THRESHOLD || color::delta_hue_degrees(hue, bg_hue) <= 20.0,
+                "{role:?} hue {hue} vs {bg_hue}"
+            );
+        }
+
+        let roles = solve("pastel");
+        assert!(oklch_of(&find(&roles, ColorRole::Accent))[1] <= 0.11);
+
+        let default_accent = {
+            let (roles, _, _) = map_colors_to_roles(
+                &palette(),
+                &RoleOptions {
+                    solver: RoleSolver::Global,
+                    ..RoleOptions::default()
+                },
+                false,
+            );
+            oklch_of(&find(&roles, ColorRole::Accent))[1]
+        };
+        assert!(oklch_of(&find(&solve("vivid"), ColorRole::Accent))[1] >= default_accent);
+
+        // The global solver takes the raised floors as hard constraints
+        let high_contrast = Preset::builtin("high-contrast").expect("builtin preset");
+        let guardrails = GLOBAL_GUARDRAILS.for_preset(&high_contrast);
+        assert_eq!(guardrails.min_accent_vs_bg, 7.0);
+        assert_eq!(guardrails.min_active_vs_text, 7.0);
+        let clusters = vec![
+            ColorCluster::from_rgb([0, 0, 0], 0.45),
+            ColorCluster::from_rgb([255, 255, 255], 0.2),
+            ColorCluster::from_rgb([230, 120, 40], 0.15),
+            ColorCluster::from_rgb([40, 170, 170], 0.1),
+            ColorCluster::from_rgb([200, 90, 160], 0.1),
+        ];
+        let options = RoleOptions {
+            solver: RoleSolver::Global,
+            preset: Preset {
+                active_text_min: Some(5.5),
+                ..Preset::default()
+            },
+            ..RoleOptions::default()
+        };
+        let (roles, pairwise, _) = map_colors_to_roles(&clusters, &options, true);
+        assert!(pairwise.expect("debug").pass_mode.starts_with("global"));
+        let (active, text) = (
+            find(&roles, ColorRole::ActiveItem),
+            find(&roles, ColorRole::Text),
+        );
+        assert!(color::calculate_contrast_ratio(active.lab, text.lab) >= 5.5);
+    }
+
+    #[test]
+    fn config_presets_deserialize_with_defaults() {
+        let preset: Preset =
+            serde_json::from_str(r#"{"maxChroma": 0.05, "accentBgMin": 6.0}"#).expect("preset");
+        assert_eq!(preset.max_chroma, Some(0.05));
+        assert_eq!(preset.chroma_weight, 0.0);
+        assert_eq!(preset.accent_floor(), 6.0);
+        assert!(Preset::default().is_default());
+        assert!(serde_json::from_str::<Preset>(r#"{"chroma": 1.0}"#).is_err());
+        for name in Preset::BUILTIN_NAMES {
+            assert!(Preset::builtin(name).is_some_and(|preset| !preset.is_default()));
+        }
+    }
+
+    #[test]
+    fn harmony_candidates_precede_grays_when_no_cluster_passes() {
+        let clusters = vec![
+            ColorCluster::from_rgb([8, 10, 28], 0.6),
+            ColorCluster::from_rgb([250, 250, 250], 0.3),
+            ColorCluster::from_rgb([30, 36, 70], 0.1),
+        ];
+        let (bg, text) = (clusters[0].lab, clusters[1].lab);
+        let candidates = build_active_candidates(&clusters, bg, text, &[0, 1], &Preset::default());
+        assert!(candidates.iter().all(|c| c.source_cluster_index.is_none()));
+
+        let harmony: Vec<&RoleColorCandidate> = candidates
+            .iter()
+            .filter(|c| c.origin_label.starts_with("harmony:"))
+            .collect();
+        assert!(harmony.len() >= 3, "{candidates:?}");
+        let bg_hue = color::oklab_to_oklch(color::rgb8_to_oklab([8, 10, 28]))[2];
+        for candidate in &harmony {
+            assert_eq!(candidate.provenance_rank, 3);
+            let (contrast_bg, contrast_text) = contrast_metrics(candidate.lab, bg, text);
+            assert!(contrast_bg >= ACTIVE_BG_MIN && contrast_text >= ACTIVE_TEXT_MIN);
+            let hue =
+                color::oklab_to_oklch(color::rgb8_to_oklab(color::lab_to_rgb8(candidate.lab)))[2];
+            assert!(
+                color::delta_hue_degrees(hue, bg_hue) >= 20.0,
+                "{candidate:?}"
+            );
+        }
+        let first_gray = candidates
+            .iter()
+            .position(|c| c.provenance_rank == 4)
+            .unwrap_or(candidates.len());
+        assert!(candidates[..first_gray]
+            .iter()
+            .all(|c| c.provenance_rank == 3));
+    }
+
+    #[test]
+    fn harmony_accent_clears_the_background_with_mid_tone_text() {
+        let clusters = vec![
+            ColorCluster::from_rgb([30, 60, 120], 0.6),
+            ColorCluster::from_rgb([216, 180, 90], 0.3),
+            ColorCluster::from_rgb([40, 70, 130], 0.1),
+        ];
+        let (roles, _, _) = map_colors_to_roles(&clusters, &RoleOptions::default(), false);
+        let find = |role| {
+            roles
+                .iter()
+                .find(|a| a.role == role)
+                .expect("role assigned")
+        };
+        let (bg, accent) = (find(ColorRole::Background), find(ColorRole::Accent));
+        assert!(accent
+            .origin
+            .as_deref()
+            .is_some_and(|o| o.starts_with("harmony:")));
+        assert!(color::calculate_contrast_ratio(accent.lab, bg.lab) >= ACCENT_BG_MIN);
+
+        let (bg, text) = (clusters[0].lab, clusters[1].lab);
+        let bg_hue = color::oklab_to_oklch(color::rgb8_to_oklab([30, 60, 120]))[2];
+        for name in ["muted", "monochrome"] {
+            let preset = Preset::builtin(name).expect("builtin preset");
+            let candidates = build_accent_candidates(&clusters, bg, text, &[0, 1], &preset);
+            for candidate in candidates
+                .iter()
+                .filter(|c| c.origin_label.starts_with("harmony:"))
+            {
+                let rgb = color::lab_to_rgb8(candidate.lab);
+                let [_, chroma, hue] = color::oklab_to_oklch(color::rgb8_to_oklab(rgb));
+                // sRGB rounding nudges chroma and hue slightly
+                if let Some(max) = preset.max_chroma {
+                    assert!(chroma <= max + 0.01, "{name}: {candidate:?}");
+                }
+                if let Some(spread) = preset.hue_spread {
+                    assert!(
+                        color::delta_hue_degrees(hue, bg_hue) <= spread + 5.0,
+                        "{name}: {candidate:?}"
+                    );
+                }
+            }
+        }
+    }
+}
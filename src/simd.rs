@@ -0,0 +1,216 @@
+//! Runtime-dispatched SIMD kernels for the `simd` feature.
+//!
+//! On x86_64 the 8-lane AVX2 kernels are used when the CPU reports AVX2 at
+//! runtime; callers fall back to the portable `wide` or scalar paths otherwise.
+//! Distance kernels evaluate `dx*dx + dy*dy + dz*dz` with separate multiplies
+//! and adds in the same order as the scalar code, so they return bit-identical
+//! results and the same tie-breaking (lowest index wins).
+
+#[cfg(target_arch = "x86_64")]
+#[inline]
+pub(crate) fn has_avx2() -> bool {
+    std::arch::is_x86_feature_detected!("avx2")
+}
+
+pub(crate) fn dispatch() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    if has_avx2() {
+        return "avx2";
+    }
+    "wide"
+}
+
+#[cfg(target_arch = "x86_64")]
+pub(crate) mod avx2 {
+    use std::arch::x86_64::*;
+
+    const LANES: usize = 8;
+
+    /// Nearest centroid for one point over SoA centroid components.
+    ///
+    /// # Safety
+    /// The CPU must support AVX2, and the three slices must have equal length.
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn nearest_centroid(
+        px: f32,
+        py: f32,
+        pz: f32,
+        cx: &[f32],
+        cy: &[f32],
+        cz: &[f32],
+    ) -> (usize, f32) {
+        let len = cx.len();
+        let px_v = _mm256_set1_ps(px);
+        let py_v = _mm256_set1_ps(py);
+        let pz_v = _mm256_set1_ps(pz);
+        let mut best_d = _mm256_set1_ps(f32::MAX);
+        let mut best_i = _mm256_set1_epi32(0);
+        let mut lane_idx = _mm256_setr_epi32(0, 1, 2, 3, 4, 5, 6, 7);
+        let step = _mm256_set1_epi32(LANES as i32);
+
+        let mut idx = 0;
+        while idx + LANES <= len {
+            let dx = _mm256_sub_ps(px_v, _mm256_loadu_ps(cx.as_ptr().add(idx)));
+            let dy = _mm256_sub_ps(py_v, _mm256_loadu_ps(cy.as_ptr().add(idx)));
+            let dz = _mm256_sub_ps(pz_v, _mm256_loadu_ps(cz.as_ptr().add(idx)));
+            let d = _mm256_add_ps(
+                _mm256_add_ps(_mm256_mul_ps(dx, dx), _mm256_mul_ps(dy, dy)),
+                _mm256_mul_ps(dz, dz),
+            );
+            // Strictly-less keeps the earliest index per lane on ties
+            let closer = _mm256_cmp_ps(d, best_d, _CMP_LT_OQ);
+            best_d = _mm256_blendv_ps(best_d, d, closer);
+            best_i = _mm256_blendv_epi8(best_i, lane_idx, _mm256_castps_si256(closer));
+            lane_idx = _mm256_add_epi32(lane_idx, step);
+            idx += LANES;
+        }
+
+        let mut dists = [0.0f32; LANES];
+        let mut indices = [0i32; LANES];
+        _mm256_storeu_ps(dists.as_mut_ptr(), best_d);
+        _mm256_storeu_si256(indices.as_mut_ptr() as *mut __m256i, best_i);
+        let mut best_idx = 0usize;
+        let mut best_dist = f32::MAX;
+        for (&d, &i) in dists.iter().zip(indices.iter()) {
+            let i = i as usize;
+            if d < best_dist || (d == best_dist && d != f32::MAX && i < best_idx) {
+                best_dist = d;
+                best_idx = i;
+            }
+        }
+
+        while idx < len {
+            let dx = px - cx[idx];
+            let dy = py - cy[idx];
+            let dz = pz - cz[idx];
+            let d = dx * dx + dy * dy + dz * dz;
+            if d < best_dist {
+                best_dist = d;
+                best_idx = idx;
+            }
+            idx += 1;
+        }
+        (best_idx, best_dist)
+    }
+
+    /// `distances[i] = min(distances[i], weight[i] * |p_i - c|²)` for k-means++.
+    ///
+    /// # Safety
+    /// The CPU must support AVX2; all slices must have the same length.
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn update_min_distances(
+        px: &[f32],
+        py: &[f32],
+        pz: &[f32],
+        weights: Option<&[f32]>,
+        centroid: [f32; 3],
+        distances: &mut [f32],
+    ) {
+        let len = distances.len();
+        let cx = _mm256_set1_ps(centroid[0]);
+        let cy = _mm256_set1_ps(centroid[1]);
+        let cz = _mm256_set1_ps(centroid[2]);
+        let mut i = 0;
+        while i + LANES <= len {
+            let dx = _mm256_sub_ps(_mm256_loadu_ps(px.as_ptr().add(i)), cx);
+            let dy = _mm256_sub_ps(_mm256_loadu_ps(py.as_ptr().add(i)), cy);
+            let dz = _mm256_sub_ps(_mm256_loadu_ps(pz.as_ptr().add(i)), cz);
+            let mut d = _mm256_add_ps(
+                _mm256_add_ps(_mm256_mul_ps(dx, dx), _mm256_mul_ps(dy, dy)),
+                _mm256_mul_ps(dz, dz),
+            );
+            if let Some(w) = weights {
+                d = _mm256_mul_ps(_mm256_loadu_ps(w.as_ptr().add(i)), d);
+            }
+            let current = _mm256_loadu_ps(distances.as_ptr().add(i));
+            let closer = _mm256_cmp_ps(d, current, _CMP_LT_OQ);
+            _mm256_storeu_ps(
+                distances.as_mut_ptr().add(i),
+                _mm256_blendv_ps(current, d, closer),
+            );
+            i += LANES;
+        }
+        while i < len {
+            let dx = px[i] - centroid[0];
+            let dy = py[i] - centroid[1];
+            let dz = pz[i] - centroid[2];
+            let d = weights.map_or(1.0, |w| w[i]) * (dx * dx + dy * dy + dz * dz);
+            if d < distances[i] {
+                distances[i] = d;
+            }
+            i += 1;
+        }
+    }
+
+    /// Scalar `f_lab` applied to each lane.
+    #[target_feature(enable = "avx2")]
+    unsafe fn f_lab(t: __m256) -> __m256 {
+        let mut lanes = [0.0f32; LANES];
+        _mm256_storeu_ps(lanes.as_mut_ptr(), t);
+        for lane in &mut lanes {
+            *lane = crate::color::f_lab(*lane);
+        }
+        _mm256_loadu_ps(lanes.as_ptr())
+    }
+
+    /// `(m0*r + m1*g + m2*b) / white`, in the scalar evaluation order.
+    #[target_feature(enable = "avx2")]
+    unsafe fn matrix_row(m: [f32; 3], r: __m256, g: __m256, b: __m256, white: f32) -> __m256 {
+        let v = _mm256_add_ps(
+            _mm256_add_ps(
+                _mm256_mul_ps(_mm256_set1_ps(m[0]), r),
+                _mm256_mul_ps(_mm256_set1_ps(m[1]), g),
+            ),
+            _mm256_mul_ps(_mm256_set1_ps(m[2]), b),
+        );
+        _mm256_div_ps(v, _mm256_set1_ps(white))
+    }
+
+    /// Lab for 8-bit sRGB pixels: LUT linearization, then the XYZ matrix and
+    /// Lab axes eight pixels at a time. The cube root goes through the scalar
+    /// `f_lab` per lane, so the result is bit-identical to `rgb8_to_lab`.
+    ///
+    /// # Safety
+    /// The CPU must support AVX2; `out` must be as long as `pixels`.
+    #[target_feature(enable = "avx2")]
+    pub(crate) unsafe fn rgb8_to_lab(pixels: &[[u8; 3]], lut: &[f32; 256], out: &mut [[f32; 3]]) {
+        let white = crate::color::XYZ_WHITE;
+        let mut chunks = pixels.chunks_exact(LANES);
+        let mut out_chunks = out.chunks_exact_mut(LANES);
+        for (chunk, dst) in (&mut chunks).zip(&mut out_chunks) {
+            let mut r = [0.0f32; LANES];
+            let mut g = [0.0f32; LANES];
+            let mut b = [0.0f32; LANES];
+            for (lane, px) in chunk.iter().enumerate() {
+                r[lane] = lut[px[0] as usize];
+                g[lane] = lut[px[1] as usize];
+                b[lane] = lut[px[2] as usize];
+            }
+            let r = _mm256_loadu_ps(r.as_ptr());
+            let g = _mm256_loadu_ps(g.as_ptr());
+            let b = _mm256_loadu_ps(b.as_ptr());
+            let m = crate::color::SRGB_TO_XYZ;
+            let fx = f_lab(matrix_row(m[0], r, g, b, white[0]));
+            let fy = f_lab(matrix_row(m[1], r, g, b, white[1]));
+            let fz = f_lab(matrix_row(m[2], r, g, b, white[2]));
+            let l = _mm256_sub_ps(
+                _mm256_mul_ps(_mm256_set1_ps(116.0), fy),
+                _mm256_set1_ps(16.0),
+            );
+            let a = _mm256_mul_ps(_mm256_set1_ps(500.0), _mm256_sub_ps(fx, fy));
+            let bb = _mm256_mul_ps(_mm256_set1_ps(200.0), _mm256_sub_ps(fy, fz));
+            let mut ls = [0.0f32; LANES];
+            let mut as_ = [0.0f32; LANES];
+            let mut bs = [0.0f32; LANES];
+            _mm256_storeu_ps(ls.as_mut_ptr(), l);
+            _mm256_storeu_ps(as_.as_mut_ptr(), a);
+            _mm256_storeu_ps(bs.as_mut_ptr(), bb);
+            for (lane, lab) in dst.iter_mut().enumerate() {
+                *lab = [ls[lane], as_[lane], bs[lane]];
+            }
+        }
+        for (px, lab) in chunks.remainder().iter().zip(out_chunks.into_remainder()) {
+            *lab = crate::color::rgb8_to_lab(*px);
+        }
+    }
+}
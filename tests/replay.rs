@@ -0,0 +1,95 @@
+//! Round-trips a run through its JSON provenance with the built binary.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use image::{Rgb, RgbImage};
+use serde_json::Value;
+
+fn theme_gen(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rmpc-theme-gen"))
+        .args(args)
+        .output()
+        .expect("run rmpc-theme-gen")
+}
+
+fn record(dir: &Path) -> (String, Value) {
+    let image = dir.join("cover.png");
+    let mut img = RgbImage::new(48, 48);
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        *pixel = match (x / 16, y / 24) {
+            (0, _) => Rgb([20, 24, 40]),
+            (1, 0) => Rgb([220, 80, 60]),
+            (1, _) => Rgb([240, 230, 210]),
+            _ => Rgb([60, 140, 200]),
+        };
+    }
+    img.save(&image).expect("save image");
+    let json = dir.join("analysis.json");
+    let out = theme_gen(&[
+        "-i",
+        image.to_str().unwrap(),
+        "-k",
+        "6",
+        "-o",
+        json.to_str().unwrap(),
+    ]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let text = std::fs::read_to_string(&json).expect("read json");
+    let parsed = serde_json::from_str(&text).expect("parse json");
+    (json.to_str().unwrap().to_string(), parsed)
+}
+
+fn rewrite(path: &str, value: &Value) {
+    std::fs::write(path, serde_json::to_string_pretty(value).unwrap()).expect("write json");
+}
+
+#[test]
+fn replay_verifies_the_recorded_theme() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let (json, recorded) = record(dir.path());
+    let provenance = &recorded["provenance"];
+    assert_eq!(provenance["themeSha256"].as_str().unwrap().len(), 64);
+    assert_eq!(
+        provenance["simdDispatch"].as_str(),
+        Some(rmpc_theme_gen::simd_dispatch())
+    );
+
+    let out = theme_gen(&["replay", &json]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(String::from_utf8_lossy(&out.stderr).contains("Replay verified"));
+
+    let mut tampered = recorded.clone();
+    tampered["provenance"]["themeSha256"] = Value::from("0".repeat(64));
+    rewrite(&json, &tampered);
+    let out = theme_gen(&["replay", &json]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("does not match recorded"));
+}
+
+#[test]
+fn replay_refuses_a_different_simd_dispatch_unless_forced() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let (json, mut recorded) = record(dir.path());
+    recorded["provenance"]["simdDispatch"] = Value::from("elsewhere");
+    rewrite(&json, &recorded);
+
+    let out = theme_gen(&["replay", &json]);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("SIMD dispatch elsewhere"));
+
+    let out = theme_gen(&["replay", &json, "--force"]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+}